@@ -2,9 +2,19 @@
 //!
 //! **PRs that only change admin or batch behavior should edit this file only.**
 
-use crate::charge_core::charge_one;
-use crate::types::{BatchChargeResult, Error, RecoveryEvent, RecoveryReason};
-use soroban_sdk::{Address, Env, Symbol, Vec};
+use crate::charge_core::{charge_one, charge_requirement, simulate_charge_one};
+use crate::queries::{get_subscription, MAX_BATCH_SIZE, MAX_SCAN_LIMIT};
+use crate::state_machine::validate_status_transition;
+use crate::subscription::{
+    add_to_subscriber_index, adjust_total_prepaid, create_subscription_record,
+    finalize_cancellation, get_cancellation_request,
+};
+use crate::types::{
+    AdminForceCancelEvent, BatchChargeResult, BatchChargeSummaryEvent, CancellationReason, DataKey,
+    Error, InsufficientBalanceError, RecoveryEvent, RecoveryReason, Subscription,
+    SubscriptionStatus, SubscriptionV1, SubscriptionV2, SubscriptionV3, SubscriptionV4,
+};
+use soroban_sdk::{Address, Env, Map, Symbol, TryFromVal, Val, Vec};
 
 pub fn do_init(
     env: &Env,
@@ -27,6 +37,10 @@ pub fn do_init(
     instance.set(&Symbol::new(env, "admin"), &admin);
     instance.set(&Symbol::new(env, "min_topup"), &min_topup);
     instance.set(&Symbol::new(env, "grace_period"), &grace_period);
+    instance.set(
+        &Symbol::new(env, "storage_version"),
+        &crate::STORAGE_VERSION,
+    );
 
     env.events().publish(
         (Symbol::new(env, "initialized"),),
@@ -35,6 +49,218 @@ pub fn do_init(
     Ok(())
 }
 
+/// Schema version of the stored data; 0 for deployments initialized before it was recorded.
+pub fn get_storage_version(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "storage_version"))
+        .unwrap_or(0)
+}
+
+/// Fails with `NotInitialized` until `init` has stored the token and admin.
+pub fn require_initialized(env: &Env) -> Result<(), Error> {
+    let instance = env.storage().instance();
+    if !instance.has(&Symbol::new(env, "token")) || !instance.has(&Symbol::new(env, "admin")) {
+        return Err(Error::NotInitialized);
+    }
+    Ok(())
+}
+
+/// Gate at the top of every mutating entrypoint: fails with `NotInitialized` before
+/// `init`, and with `MigrationRequired` while the stored version is older than
+/// `STORAGE_VERSION`.
+pub fn require_current_storage(env: &Env) -> Result<(), Error> {
+    require_initialized(env)?;
+    if get_storage_version(env) < crate::STORAGE_VERSION {
+        return Err(Error::MigrationRequired);
+    }
+    Ok(())
+}
+
+/// Brings stored data up to `STORAGE_VERSION`, visiting at most `limit` (capped at
+/// `MAX_SCAN_LIMIT`) subscription ids per call. Every id is visited twice: once to
+/// rewrite its record (progress under `migrate_cursor`) and once to drop the recipient
+/// markers used to build the merchant total (progress under `migrate_cleanup`), so the
+/// admin can resume with further calls; the version is only stamped once both passes
+/// are done. Returns the number of visits still to make (0 when done). Admin only; a
+/// no-op when already current.
+pub fn do_migrate(env: &Env, admin: Address, limit: u32) -> Result<u32, Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+
+    let from = get_storage_version(env);
+    if from >= crate::STORAGE_VERSION {
+        return Ok(0);
+    }
+    let storage = env.storage().instance();
+    let cursor_key = Symbol::new(env, "migrate_cursor");
+    let cleanup_key = Symbol::new(env, "migrate_cleanup");
+    let next_id: u32 = storage.get(&Symbol::new(env, "next_id")).unwrap_or(0);
+    let mut budget = limit.min(MAX_SCAN_LIMIT);
+
+    // First pass: rewrite the records and count their recipients.
+    if !storage.has(&cursor_key) {
+        if let Ok(collector) = get_fee_collector(env) {
+            count_recipient(env, &collector)?;
+        }
+    }
+    let start: u32 = storage.get(&cursor_key).unwrap_or(0);
+    let end = start.saturating_add(budget).min(next_id);
+    // Deployments initialized before the version was recorded hold version 1 records.
+    upgrade_legacy_subscriptions(env, from.max(1), start, end)?;
+    storage.set(&cursor_key, &end);
+    budget -= end - start;
+
+    // Second pass, once every record is rewritten: drop the recipient markers.
+    let cleanup_start: u32 = storage.get(&cleanup_key).unwrap_or(0);
+    let cleanup_end = if end == next_id {
+        let cleanup_end = cleanup_start.saturating_add(budget).min(next_id);
+        forget_recipients(env, cleanup_start, cleanup_end);
+        storage.set(&cleanup_key, &cleanup_end);
+        cleanup_end
+    } else {
+        cleanup_start
+    };
+
+    let remaining = (next_id - end) + (next_id - cleanup_end);
+    if remaining > 0 {
+        return Ok(remaining);
+    }
+    storage.remove(&cursor_key);
+    storage.remove(&cleanup_key);
+    if let Ok(collector) = get_fee_collector(env) {
+        storage.remove(&counted_key(env, &collector));
+    }
+    storage.set(
+        &Symbol::new(env, "storage_version"),
+        &crate::STORAGE_VERSION,
+    );
+    env.events().publish(
+        (Symbol::new(env, "migrated"),),
+        (from, crate::STORAGE_VERSION),
+    );
+    Ok(0)
+}
+
+/// Whether subscription `id`'s balance is already part of the prepaid running total:
+/// always once storage is current, and during a migration only for ids below the
+/// migration cursor.
+pub fn prepaid_total_includes(env: &Env, subscription_id: u32) -> bool {
+    get_storage_version(env) >= crate::STORAGE_VERSION
+        || subscription_id
+            < env
+                .storage()
+                .instance()
+                .get(&Symbol::new(env, "migrate_cursor"))
+                .unwrap_or(0)
+}
+
+/// Storage version that introduced the current subscription record layout.
+const RECORD_LAYOUT_VERSION: u32 = 5;
+
+/// Whether subscription `id` is stored in the current record layout and can be read with
+/// `get_subscription`: always from version 5 on, and before that only for ids the
+/// migration has already rewritten.
+pub fn record_layout_current(env: &Env, subscription_id: u32) -> bool {
+    get_storage_version(env) >= RECORD_LAYOUT_VERSION
+        || prepaid_total_includes(env, subscription_id)
+}
+
+/// Rewrites the stored subscription records in `start..end` from layout version `from`
+/// (1 to 5) in the current layout. Records are decoded in the layout matching `from`
+/// only, since decoding a struct with a different field set fails; a record that does
+/// not decode aborts the migration with `InvariantViolation` rather than being left
+/// in a layout the current code cannot read.
+///
+/// Each record's balance is added to the prepaid running total (introduced in version
+/// 6), and its merchant and payees are counted with [`count_recipient`].
+fn upgrade_legacy_subscriptions(env: &Env, from: u32, start: u32, end: u32) -> Result<(), Error> {
+    let storage = env.storage().instance();
+    for id in start..end {
+        let Some(raw) = storage.get::<u32, Val>(&id) else {
+            continue;
+        };
+        // Decoding a map with a different field count traps instead of failing, so the
+        // layout is checked against the version's field count first (8 fields in
+        // version 1, one more in each version up to 5).
+        let fields =
+            Map::<Symbol, Val>::try_from_val(env, &raw).map_err(|_| Error::InvariantViolation)?;
+        if fields.len() != from + 7 {
+            return Err(Error::InvariantViolation);
+        }
+        let upgraded = match from {
+            1 => SubscriptionV1::try_from_val(env, &raw).map(SubscriptionV1::upgrade),
+            2 => SubscriptionV2::try_from_val(env, &raw).map(SubscriptionV2::upgrade),
+            3 => SubscriptionV3::try_from_val(env, &raw).map(SubscriptionV3::upgrade),
+            4 => SubscriptionV4::try_from_val(env, &raw).map(SubscriptionV4::upgrade),
+            _ => Subscription::try_from_val(env, &raw),
+        };
+        let sub = upgraded.map_err(|_| Error::InvariantViolation)?;
+        storage.set(&id, &sub);
+        // Records created before the subscriber index existed are missing from it,
+        // which would let those subscribers exceed the per-subscriber cap.
+        if sub.status != SubscriptionStatus::Cancelled {
+            add_to_subscriber_index(env, &sub.subscriber, id);
+        }
+
+        adjust_total_prepaid(env, sub.prepaid_balance)?;
+        for recipient in migration_recipients(env, id, sub.merchant).iter() {
+            count_recipient(env, &recipient)?;
+        }
+    }
+    Ok(())
+}
+
+/// The merchant and payees of subscription `id`, whose balances make up the merchant
+/// running total.
+fn migration_recipients(env: &Env, id: u32, merchant: Address) -> Vec<Address> {
+    let mut recipients = Vec::from_array(env, [merchant]);
+    for (payee, _) in crate::merchant::get_payees(env, id).iter() {
+        recipients.push_back(payee);
+    }
+    recipients
+}
+
+/// Marker for a recipient whose balance the running migration has already added to the
+/// merchant total, so one shared by several subscriptions is only counted once.
+fn counted_key(env: &Env, recipient: &Address) -> (Symbol, Address) {
+    (Symbol::new(env, "migrate_counted"), recipient.clone())
+}
+
+/// Adds `recipient`'s balance to the merchant running total (introduced in version 6)
+/// unless the migration has counted it already. Balances cannot change while the
+/// migration is pending, so the total is exact once every record has been visited.
+fn count_recipient(env: &Env, recipient: &Address) -> Result<(), Error> {
+    let storage = env.storage().instance();
+    let key = counted_key(env, recipient);
+    if storage.has(&key) {
+        return Ok(());
+    }
+    storage.set(&key, &true);
+    let total = crate::safe_math::safe_add(
+        crate::merchant::get_total_merchant_balance(env),
+        crate::merchant::get_merchant_balance(env, recipient),
+    )?;
+    storage.set(&Symbol::new(env, "total_merchant"), &total);
+    Ok(())
+}
+
+/// Removes the [`counted_key`] markers left by the records in `start..end`.
+fn forget_recipients(env: &Env, start: u32, end: u32) {
+    let storage = env.storage().instance();
+    for id in start..end {
+        let Some(sub) = storage.get::<u32, Subscription>(&id) else {
+            continue;
+        };
+        for recipient in migration_recipients(env, id, sub.merchant).iter() {
+            storage.remove(&counted_key(env, &recipient));
+        }
+    }
+}
+
 pub fn require_admin(env: &Env) -> Result<Address, Error> {
     env.storage()
         .instance()
@@ -63,6 +289,22 @@ pub fn get_min_topup(env: &Env) -> Result<i128, Error> {
         .ok_or(Error::NotInitialized)
 }
 
+/// Billing token address as recorded at `init`.
+pub fn get_token(env: &Env) -> Result<Address, Error> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "token"))
+        .ok_or(Error::NotInitialized)
+}
+
+/// Decimals of the billing token as recorded at `init`, for client-side formatting.
+pub fn get_token_decimals(env: &Env) -> Result<u32, Error> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "token_decimals"))
+        .ok_or(Error::NotInitialized)
+}
+
 pub fn do_set_grace_period(env: &Env, admin: Address, grace_period: u64) -> Result<(), Error> {
     admin.require_auth();
     let stored = require_admin(env)?;
@@ -83,32 +325,416 @@ pub fn get_grace_period(env: &Env) -> Result<u64, Error> {
         .unwrap_or(0))
 }
 
+/// Admin sets the grace window applied to a subscription's first charge only
+/// (0 disables). The longer of this and the global grace period is used.
+pub fn do_set_first_charge_grace(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "first_charge_grace"), &seconds);
+    Ok(())
+}
+
+/// Grace window for a subscription's first charge (default 0).
+pub fn get_first_charge_grace(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "first_charge_grace"))
+        .unwrap_or(0)
+}
+
+/// Admin sets a grace window for one subscription that replaces the global grace period
+/// (the first-charge grace still applies if longer). `None` removes the override.
+pub fn do_set_grace_override(
+    env: &Env,
+    admin: Address,
+    subscription_id: u32,
+    seconds: Option<u64>,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    get_subscription(env, subscription_id)?;
+    let key = DataKey::GraceOverride(subscription_id);
+    match seconds {
+        Some(seconds) => env.storage().instance().set(&key, &seconds),
+        None => env.storage().instance().remove(&key),
+    }
+    Ok(())
+}
+
+/// Per-subscription grace override, if one is set.
+pub fn get_grace_override(env: &Env, subscription_id: u32) -> Option<u64> {
+    env.storage()
+        .instance()
+        .get(&DataKey::GraceOverride(subscription_id))
+}
+
+/// Admin sets the address that receives platform fees.
+pub fn do_set_fee_collector(env: &Env, admin: Address, collector: Address) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "fee_collector"), &collector);
+    Ok(())
+}
+
+/// Configured platform fee collector (`NotFound` if never set).
+pub fn get_fee_collector(env: &Env) -> Result<Address, Error> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "fee_collector"))
+        .ok_or(Error::NotFound)
+}
+
+/// Admin sets the platform fee in basis points (at most
+/// [`crate::subscription::MAX_FEE_BPS`]). A nonzero fee requires a fee collector to be
+/// configured first, so fees are never routed to no one; returns `InvalidInput` otherwise.
+pub fn do_set_fee_bps(env: &Env, admin: Address, bps: u32) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    if bps > crate::subscription::MAX_FEE_BPS {
+        return Err(Error::InvalidInput);
+    }
+    if bps > 0 && get_fee_collector(env).is_err() {
+        return Err(Error::InvalidInput);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "fee_bps"), &bps);
+    Ok(())
+}
+
+/// Platform fee in basis points (default 0).
+pub fn get_fee_bps(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "fee_bps"))
+        .unwrap_or(0)
+}
+
+/// Upper bound for [`do_set_charge_tolerance`]: one hour.
+pub const MAX_CHARGE_TOLERANCE_SECONDS: u64 = 60 * 60;
+
+/// Admin sets how many seconds early an interval charge is accepted (0 disables). An
+/// early charge is recorded at its scheduled time. Above
+/// [`MAX_CHARGE_TOLERANCE_SECONDS`] returns `InvalidInput`.
+pub fn do_set_charge_tolerance(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    if seconds > MAX_CHARGE_TOLERANCE_SECONDS {
+        return Err(Error::InvalidInput);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "charge_tolerance"), &seconds);
+    Ok(())
+}
+
+/// Seconds an interval charge may run ahead of its due time (default 0).
+pub fn get_charge_tolerance(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "charge_tolerance"))
+        .unwrap_or(0)
+}
+
+pub fn do_set_withdrawal_cooldown(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "withdraw_cooldown"), &seconds);
+    Ok(())
+}
+
+/// Minimum seconds between withdrawals by the same merchant (0 = no cooldown).
+pub fn get_withdrawal_cooldown(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "withdraw_cooldown"))
+        .unwrap_or(0)
+}
+
+pub fn do_set_min_balance_buffer(env: &Env, admin: Address, buffer: i128) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    if buffer < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "min_balance_buffer"), &buffer);
+    Ok(())
+}
+
+/// Post-charge balance below which a `low_balance` warning is emitted (0 = disabled).
+pub fn get_min_balance_buffer(env: &Env) -> i128 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "min_balance_buffer"))
+        .unwrap_or(0)
+}
+
+pub fn do_set_max_subs_per_subscriber(env: &Env, admin: Address, max: u32) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    env.storage()
+        .instance()
+        .set(&DataKey::MaxSubsPerSubscriber, &max);
+    Ok(())
+}
+
+/// Maximum non-cancelled subscriptions per subscriber (0 = unlimited).
+pub fn get_max_subs_per_subscriber(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MaxSubsPerSubscriber)
+        .unwrap_or(0)
+}
+
+/// Admin sets the smallest `amount` a new subscription may charge per interval, so
+/// dust subscriptions are refused at creation (0 disables). Negative returns `InvalidAmount`.
+pub fn do_set_min_charge_amount(env: &Env, admin: Address, min: i128) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    if min < 0 {
+        return Err(Error::InvalidAmount);
+    }
+    env.storage()
+        .instance()
+        .set(&DataKey::MinChargeAmount, &min);
+    Ok(())
+}
+
+/// Minimum per-interval `amount` for new subscriptions (0 = no minimum).
+pub fn get_min_charge_amount(env: &Env) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MinChargeAmount)
+        .unwrap_or(0)
+}
+
+pub fn do_set_events_verbose(env: &Env, admin: Address, verbose: bool) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "events_verbose"), &verbose);
+    Ok(())
+}
+
+/// Whether the charge path emits secondary informational events (default `true`).
+pub fn events_verbose(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "events_verbose"))
+        .unwrap_or(true)
+}
+
+/// Address allowed to run interval charges: the configured billing engine, or the admin.
+pub fn get_billing_engine(env: &Env) -> Result<Address, Error> {
+    match env.storage().instance().get(&DataKey::BillingEngine) {
+        Some(engine) => Ok(engine),
+        None => require_admin(env),
+    }
+}
+
+/// Requires the billing engine's auth for a charge entrypoint.
+pub fn require_billing_engine_auth(env: &Env) -> Result<(), Error> {
+    get_billing_engine(env)?.require_auth();
+    Ok(())
+}
+
+pub fn do_set_billing_engine(env: &Env, admin: Address, engine: Address) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    env.storage()
+        .instance()
+        .set(&DataKey::BillingEngine, &engine);
+    env.events()
+        .publish((Symbol::new(env, "billing_engine_set"),), engine);
+    Ok(())
+}
+
+pub fn do_set_restrict_deposits_to_owner(
+    env: &Env,
+    admin: Address,
+    restrict: bool,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "restrict_deposits"), &restrict);
+    Ok(())
+}
+
+/// Whether only a subscription's own subscriber may deposit into it (default `false`).
+pub fn restrict_deposits_to_owner(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "restrict_deposits"))
+        .unwrap_or(false)
+}
+
+/// Admin turns two-sided confirmation on or off. While on, new subscriptions start
+/// pending until their merchant calls `confirm_subscription`.
+pub fn do_set_require_merchant_confirmation(
+    env: &Env,
+    admin: Address,
+    required: bool,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "merchant_confirm"), &required);
+    Ok(())
+}
+
+/// Whether new subscriptions need merchant confirmation before charging (default `false`).
+pub fn require_merchant_confirmation(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "merchant_confirm"))
+        .unwrap_or(false)
+}
+
+/// Charges each ID independently, collecting a result per ID, then emits one
+/// `batch_charge_summary` event with the success and failure counts.
 pub fn do_batch_charge(
     env: &Env,
     subscription_ids: &Vec<u32>,
 ) -> Result<Vec<BatchChargeResult>, Error> {
-    let auth_admin = require_admin(env)?;
-    auth_admin.require_auth();
+    require_billing_engine_auth(env)?;
 
     let now = env.ledger().timestamp();
     let mut results = Vec::new(env);
+    let mut succeeded = 0u32;
     for id in subscription_ids.iter() {
-        let r = charge_one(env, id, now, None);
-        let res = match &r {
-            Ok(()) => BatchChargeResult {
-                success: true,
-                error_code: 0,
-            },
-            Err(e) => BatchChargeResult {
-                success: false,
-                error_code: e.clone().to_code(),
-            },
-        };
-        results.push_back(res);
+        let result = to_batch_result(charge_one(env, id, now, None, None));
+        if result.success {
+            succeeded += 1;
+        }
+        results.push_back(result);
     }
+
+    env.events().publish(
+        (Symbol::new(env, "batch_charge_summary"),),
+        BatchChargeSummaryEvent {
+            total: results.len(),
+            succeeded,
+            failed: results.len() - succeeded,
+            timestamp: now,
+        },
+    );
     Ok(results)
 }
 
+/// All-or-nothing charge of one subscriber's bundled subscriptions.
+///
+/// Every ID is first checked with `simulate_charge_one` (status, interval, balance), must
+/// be able to pay its full amount and must belong to the same subscriber; only then are
+/// they charged. Any failure returns
+/// the first error, which reverts the whole call, unlike `batch_charge`.
+pub fn do_charge_bundle(env: &Env, subscription_ids: &Vec<u32>) -> Result<(), Error> {
+    require_billing_engine_auth(env)?;
+    if subscription_ids.is_empty() || subscription_ids.len() > MAX_BATCH_SIZE {
+        return Err(Error::InvalidInput);
+    }
+
+    let now = env.ledger().timestamp();
+    let mut subscriber: Option<Address> = None;
+    for id in subscription_ids.iter() {
+        let sub = get_subscription(env, id)?;
+        match &subscriber {
+            Some(s) if *s != sub.subscriber => return Err(Error::InvalidInput),
+            Some(_) => {}
+            None => subscriber = Some(sub.subscriber.clone()),
+        }
+        simulate_charge_one(env, id, now)?;
+        // A partial charge would leave arrears behind, so it does not count here.
+        let need = charge_requirement(env, id, &sub);
+        if need.available < need.required {
+            return Err(Error::InsufficientBalance);
+        }
+    }
+    for id in subscription_ids.iter() {
+        charge_one(env, id, now, None, None)?;
+    }
+    Ok(())
+}
+
+/// Dry run of [`do_batch_charge`]: evaluates each ID with `simulate_charge_one` and
+/// returns the results `batch_charge` would produce, without writing state.
+///
+/// Each ID is evaluated against current state, so a duplicate ID reports the same
+/// result twice where a real batch would reject the repeat.
+pub fn do_simulate_batch_charge(env: &Env, subscription_ids: &Vec<u32>) -> Vec<BatchChargeResult> {
+    let now = env.ledger().timestamp();
+    let mut results = Vec::new(env);
+    for id in subscription_ids.iter() {
+        results.push_back(to_batch_result(
+            simulate_charge_one(env, id, now).map(|_| ()),
+        ));
+    }
+    results
+}
+
+fn to_batch_result(r: Result<(), Error>) -> BatchChargeResult {
+    match r {
+        Ok(()) => BatchChargeResult {
+            success: true,
+            error_code: 0,
+        },
+        Err(e) => BatchChargeResult {
+            success: false,
+            error_code: e.to_code(),
+        },
+    }
+}
+
 pub fn do_get_admin(env: &Env) -> Result<Address, Error> {
     env.storage()
         .instance()
@@ -132,6 +758,9 @@ pub fn do_rotate_admin(env: &Env, current_admin: Address, new_admin: Address) ->
     env.storage()
         .instance()
         .set(&Symbol::new(env, "admin"), &new_admin);
+    env.storage()
+        .instance()
+        .set(&DataKey::PreviousAdmin, &current_admin);
 
     env.events().publish(
         (Symbol::new(env, "admin_rotation"), current_admin.clone()),
@@ -141,6 +770,80 @@ pub fn do_rotate_admin(env: &Env, current_admin: Address, new_admin: Address) ->
     Ok(())
 }
 
+/// Admin adds or removes `address` from the set of addresses that may not be used as
+/// a merchant. Subscriptions paying a blocked merchant cannot be created or charged.
+pub fn do_set_address_blocked(
+    env: &Env,
+    admin: Address,
+    address: Address,
+    blocked: bool,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    let key = DataKey::BlockedAddress(address.clone());
+    if blocked {
+        env.storage().instance().set(&key, &true);
+    } else {
+        env.storage().instance().remove(&key);
+    }
+    env.events().publish(
+        (Symbol::new(env, "address_blocked"), address),
+        (admin, blocked),
+    );
+    Ok(())
+}
+
+/// Admin pauses or unpauses the contract. While paused, charges, deposits and new
+/// subscriptions fail with [`Error::ContractPaused`]; subscribers may still withdraw
+/// through [`crate::subscription::do_emergency_withdraw`].
+pub fn do_set_contract_paused(env: &Env, admin: Address, paused: bool) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "contract_paused"), &paused);
+    env.events()
+        .publish((Symbol::new(env, "contract_paused"),), (admin, paused));
+    Ok(())
+}
+
+/// Whether the admin has paused the contract. Defaults to `false`.
+pub fn is_contract_paused(env: &Env) -> bool {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "contract_paused"))
+        .unwrap_or(false)
+}
+
+/// Returns [`Error::ContractPaused`] while the contract is paused.
+pub fn require_not_paused(env: &Env) -> Result<(), Error> {
+    if is_contract_paused(env) {
+        return Err(Error::ContractPaused);
+    }
+    Ok(())
+}
+
+/// Whether `address` is on the admin-maintained block list.
+pub fn is_address_blocked(env: &Env, address: &Address) -> bool {
+    env.storage()
+        .instance()
+        .has(&DataKey::BlockedAddress(address.clone()))
+}
+
+/// Admin replaced by the most recent rotation; `NotFound` if the admin was never rotated.
+pub fn get_previous_admin(env: &Env) -> Result<Address, Error> {
+    env.storage()
+        .instance()
+        .get(&DataKey::PreviousAdmin)
+        .ok_or(Error::NotFound)
+}
+
 pub fn do_recover_stranded_funds(
     env: &Env,
     admin: Address,
@@ -164,6 +867,12 @@ pub fn do_recover_stranded_funds(
         return Err(Error::InvalidRecoveryAmount);
     }
 
+    // A self-transfer moves nothing but would still emit a recovery audit event.
+    // (Soroban has no zero-address sentinel to reject.)
+    if recipient == env.current_contract_address() {
+        return Err(Error::InvalidInput);
+    }
+
     let recovery_event = RecoveryEvent {
         admin: admin.clone(),
         recipient: recipient.clone(),
@@ -182,3 +891,374 @@ pub fn do_recover_stranded_funds(
 
     Ok(())
 }
+
+/// Suspends every `GracePeriod` subscription among IDs `start_id..start_id + limit`
+/// (`limit` capped at [`MAX_SCAN_LIMIT`]) whose grace window has ended, as a failed
+/// charge would. Returns how many were moved to `InsufficientBalance`.
+pub fn do_sweep_expired_grace(
+    env: &Env,
+    admin: Address,
+    start_id: u32,
+    limit: u32,
+) -> Result<u32, Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+
+    let next_id: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "next_id"))
+        .unwrap_or(0);
+    let end_id = start_id
+        .saturating_add(limit.min(MAX_SCAN_LIMIT))
+        .min(next_id);
+    let now = env.ledger().timestamp();
+
+    let mut swept = 0;
+    for id in start_id..end_id {
+        let Some(mut sub) = env.storage().instance().get::<u32, Subscription>(&id) else {
+            continue;
+        };
+        if sub.status != SubscriptionStatus::GracePeriod {
+            continue;
+        }
+        let deadline = crate::charge_core::grace_deadline(env, id, &sub).unwrap_or(u64::MAX);
+        if now < deadline {
+            continue;
+        }
+        validate_status_transition(&sub.status, &SubscriptionStatus::InsufficientBalance)?;
+        sub.status = SubscriptionStatus::InsufficientBalance;
+        env.storage().instance().set(&id, &sub);
+        env.events().publish(
+            (Symbol::new(env, "sub_suspended"), id),
+            (
+                sub.subscriber.clone(),
+                InsufficientBalanceError::new(sub.prepaid_balance, sub.amount),
+            ),
+        );
+        swept += 1;
+    }
+    Ok(swept)
+}
+
+/// Completes every cancellation scheduled under a notice period whose effective time
+/// has passed among IDs `start_id..start_id + limit` (`limit` capped at
+/// [`MAX_SCAN_LIMIT`]), exactly as a `cancel_subscription` call by the original
+/// requester would. Returns how many were cancelled.
+pub fn do_sweep_scheduled_cancellations(
+    env: &Env,
+    admin: Address,
+    start_id: u32,
+    limit: u32,
+) -> Result<u32, Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+
+    let next_id: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "next_id"))
+        .unwrap_or(0);
+    let end_id = start_id
+        .saturating_add(limit.min(MAX_SCAN_LIMIT))
+        .min(next_id);
+    let now = env.ledger().timestamp();
+
+    let mut swept = 0;
+    for id in start_id..end_id {
+        let Some((effective_at, authorizer, reason)) = get_cancellation_request(env, id) else {
+            continue;
+        };
+        if now < effective_at {
+            continue;
+        }
+        let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) else {
+            continue;
+        };
+        finalize_cancellation(env, id, sub, authorizer, reason)?;
+        swept += 1;
+    }
+    Ok(swept)
+}
+
+/// Admin sets the longest a subscription may stay paused before
+/// [`do_sweep_stale_pauses`] cancels and refunds it (0 disables the sweep).
+pub fn do_set_max_pause_seconds(env: &Env, admin: Address, seconds: u64) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "max_pause_seconds"), &seconds);
+    Ok(())
+}
+
+/// Longest allowed pause in seconds (default 0, meaning unlimited).
+pub fn get_max_pause_seconds(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "max_pause_seconds"))
+        .unwrap_or(0)
+}
+
+/// Cancels every `Paused` subscription among IDs `start_id..start_id + limit` (`limit`
+/// capped at [`MAX_SCAN_LIMIT`]) that has been paused for longer than
+/// [`get_max_pause_seconds`]. Each goes through [`finalize_cancellation`] (so `cancelled`
+/// is emitted with `CancellationReason::Delinquent`), then the remaining balance is
+/// refunded to the subscriber. Returns how many were cancelled.
+pub fn do_sweep_stale_pauses(
+    env: &Env,
+    admin: Address,
+    start_id: u32,
+    limit: u32,
+) -> Result<u32, Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    let max_pause = get_max_pause_seconds(env);
+    if max_pause == 0 {
+        return Ok(0);
+    }
+
+    let next_id: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "next_id"))
+        .unwrap_or(0);
+    let end_id = start_id
+        .saturating_add(limit.min(MAX_SCAN_LIMIT))
+        .min(next_id);
+    let now = env.ledger().timestamp();
+
+    let mut swept = 0;
+    for id in start_id..end_id {
+        let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) else {
+            continue;
+        };
+        if sub.status != SubscriptionStatus::Paused {
+            continue;
+        }
+        let paused_key = DataKey::PausedAt(id);
+        let Some(paused_at) = env.storage().instance().get::<_, u64>(&paused_key) else {
+            continue;
+        };
+        if now.saturating_sub(paused_at) <= max_pause {
+            continue;
+        }
+
+        finalize_cancellation(env, id, sub, admin.clone(), CancellationReason::Delinquent)?;
+        let mut sub = get_subscription(env, id)?;
+        let refunded = crate::subscription::refund_prepaid(env, id, &mut sub)?;
+        env.events().publish(
+            (Symbol::new(env, "pause_expired"), id),
+            (sub.subscriber.clone(), refunded),
+        );
+        swept += 1;
+    }
+    Ok(swept)
+}
+
+/// Admin dunning cleanup: cancels a subscription only when it is `InsufficientBalance`
+/// or `GracePeriod` and its balance is below `min_balance`; otherwise
+/// `InvalidStatusTransition`. Pending usage is settled and the remainder stays
+/// withdrawable by the subscriber, as with a normal cancel.
+pub fn do_cancel_if_delinquent(
+    env: &Env,
+    admin: Address,
+    subscription_id: u32,
+    min_balance: i128,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+
+    let sub = get_subscription(env, subscription_id)?;
+    let delinquent = matches!(
+        sub.status,
+        SubscriptionStatus::InsufficientBalance | SubscriptionStatus::GracePeriod
+    );
+    if !delinquent || sub.prepaid_balance >= min_balance {
+        return Err(Error::InvalidStatusTransition);
+    }
+    crate::subscription::finalize_cancellation(
+        env,
+        subscription_id,
+        sub,
+        admin.clone(),
+        CancellationReason::Delinquent,
+    )?;
+
+    let remaining = get_subscription(env, subscription_id)?.prepaid_balance;
+    env.events().publish(
+        (Symbol::new(env, "delinquent_cancelled"), subscription_id),
+        (admin, remaining),
+    );
+    Ok(())
+}
+
+/// Cancels a subscription without subscriber or merchant auth (`cancelled` carries
+/// [`CancellationReason::Fraud`]) and refunds its prepaid balance to the refund
+/// destination. Intended for abuse handling; `NotActive` if already cancelled.
+pub fn do_admin_force_cancel(
+    env: &Env,
+    admin: Address,
+    subscription_id: u32,
+    reason: RecoveryReason,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.status == SubscriptionStatus::Cancelled {
+        return Err(Error::NotActive);
+    }
+    // Settles pending usage to the merchant before the remainder is refunded.
+    crate::subscription::finalize_cancellation(
+        env,
+        subscription_id,
+        sub,
+        admin.clone(),
+        CancellationReason::Fraud,
+    )?;
+    let mut sub = get_subscription(env, subscription_id)?;
+    let refunded = crate::subscription::refund_prepaid(env, subscription_id, &mut sub)?;
+
+    env.events().publish(
+        (Symbol::new(env, "admin_force_cancel"), subscription_id),
+        AdminForceCancelEvent {
+            subscription_id,
+            admin,
+            refunded,
+            reason,
+            timestamp: env.ledger().timestamp(),
+        },
+    );
+    Ok(())
+}
+
+/// Re-creates a cancelled subscription's terms as a fresh `Active` subscription with a
+/// new ID, recording `DataKey::ReactivatedFrom(new_id) = old_id`. The original stays
+/// `Cancelled`; the new subscription starts with a zero balance.
+pub fn do_admin_reactivate(
+    env: &Env,
+    admin: Address,
+    old_subscription_id: u32,
+) -> Result<u32, Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+
+    let old = get_subscription(env, old_subscription_id)?;
+    if old.status != SubscriptionStatus::Cancelled {
+        return Err(Error::InvalidStatusTransition);
+    }
+
+    let new_id = create_subscription_record(
+        env,
+        old.subscriber,
+        old.merchant,
+        old.amount,
+        old.interval_seconds,
+        old.usage_enabled,
+        env.ledger().timestamp(),
+    )?;
+    env.storage()
+        .instance()
+        .set(&DataKey::ReactivatedFrom(new_id), &old_subscription_id);
+    env.events().publish(
+        (Symbol::new(env, "reactivated"), new_id),
+        (old_subscription_id, admin),
+    );
+    Ok(new_id)
+}
+
+/// Corrects a subscription's `last_payment_timestamp` after a billing error, emitting
+/// `last_payment_corrected` with the old and new values. The new value may not be in
+/// the future. The replay marker is cleared so the corrected schedule alone decides
+/// when the next charge is due.
+pub fn do_admin_set_last_payment(
+    env: &Env,
+    admin: Address,
+    subscription_id: u32,
+    timestamp: u64,
+) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    if timestamp > env.ledger().timestamp() {
+        return Err(Error::InvalidInput);
+    }
+
+    let mut sub = get_subscription(env, subscription_id)?;
+    let old = sub.last_payment_timestamp;
+    sub.last_payment_timestamp = timestamp;
+    env.storage().instance().set(&subscription_id, &sub);
+    crate::charge_core::reset_charged_period(env, subscription_id);
+
+    env.events().publish(
+        (Symbol::new(env, "last_payment_corrected"), subscription_id),
+        (admin, old, timestamp),
+    );
+    Ok(())
+}
+
+/// The cancelled subscription a reactivated one was created from, if any.
+pub fn get_reactivated_from(env: &Env, subscription_id: u32) -> Option<u32> {
+    env.storage()
+        .instance()
+        .get(&DataKey::ReactivatedFrom(subscription_id))
+}
+
+/// Drops IDs of cancelled or missing subscriptions from a merchant's index.
+///
+/// Maintenance call for indices populated before cancellation pruned them
+/// automatically. Returns the number of IDs removed.
+pub fn do_compact_merchant_index(
+    env: &Env,
+    admin: Address,
+    merchant: Address,
+) -> Result<u32, Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+
+    let key = DataKey::MerchantSubs(merchant.clone());
+    let ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
+    let mut kept = Vec::new(env);
+    for id in ids.iter() {
+        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+            if sub.status != SubscriptionStatus::Cancelled {
+                kept.push_back(id);
+            }
+        }
+    }
+    let removed = ids.len() - kept.len();
+    env.storage().instance().set(&key, &kept);
+
+    env.events().publish(
+        (Symbol::new(env, "merchant_index_compacted"), merchant),
+        removed,
+    );
+    Ok(removed)
+}
@@ -14,11 +14,14 @@
 //!   we store one key per subscription. A second call with the same key returns `Ok(())` without
 //!   debiting again (idempotent success). Storage stays bounded (one key and one period per sub).
 
-use crate::queries::get_subscription;
-use crate::safe_math::safe_sub_balance;
+use crate::queries::{get_billing_anchor, get_calendar_day, get_subscription, next_due_for};
+use crate::safe_math::{safe_add_balance, safe_sub, safe_sub_balance};
 use crate::state_machine::validate_status_transition;
-use crate::types::{Error, SubscriptionChargedEvent, SubscriptionStatus};
-use soroban_sdk::{symbol_short, Env, Symbol};
+use crate::types::{
+    DataKey, Error, InsufficientBalanceError, Subscription, SubscriptionChargedEvent,
+    SubscriptionStatus,
+};
+use soroban_sdk::{symbol_short, Address, BytesN, Env, Symbol};
 
 const KEY_CHARGED_PERIOD: Symbol = symbol_short!("cp");
 const KEY_IDEM: Symbol = symbol_short!("idem");
@@ -31,38 +34,136 @@ fn idem_key(subscription_id: u32) -> (Symbol, u32) {
     (KEY_IDEM, subscription_id)
 }
 
+/// Billing period containing `now`. Periods start at multiples of `interval_seconds`, or
+/// at the billing anchor's slots when one is set, so each anchored charge opens a new period.
+fn billing_period_index(env: &Env, subscription_id: u32, sub: &Subscription, now: u64) -> u64 {
+    if get_calendar_day(env, subscription_id).is_some() {
+        return crate::queries::calendar_month_index(now);
+    }
+    let shift = get_billing_anchor(env, subscription_id)
+        .map(|anchor| anchor % sub.interval_seconds)
+        .unwrap_or(0);
+    now.saturating_sub(shift) / sub.interval_seconds
+}
+
+/// Whether a cancellation scheduled under a notice period has taken effect by `now`.
+fn cancellation_effective(env: &Env, subscription_id: u32, now: u64) -> bool {
+    crate::subscription::get_scheduled_cancellation(env, subscription_id)
+        .is_some_and(|effective_at| now >= effective_at)
+}
+
+/// `now`, or the scheduled due time when `now` is at most `charge_tolerance` seconds
+/// before it. The tolerance never exceeds half the interval, so an early charge cannot
+/// reach into the period after the one just paid.
+fn effective_charge_time(env: &Env, subscription_id: u32, sub: &Subscription, now: u64) -> u64 {
+    let tolerance = crate::admin::get_charge_tolerance(env).min(sub.interval_seconds / 2);
+    if tolerance == 0 {
+        return now;
+    }
+    match next_due_for(env, subscription_id, sub) {
+        Some(due) if now < due && now.saturating_add(tolerance) >= due => due,
+        _ => now,
+    }
+}
+
+/// Drops the replay marker so the next charge is judged against a new period layout.
+pub fn reset_charged_period(env: &Env, subscription_id: u32) {
+    env.storage()
+        .instance()
+        .remove(&charged_period_key(subscription_id));
+}
+
+/// Re-expresses the replay marker in the subscription's current period layout after a
+/// billing anchor or mode change, so the period of the last payment stays charged.
+/// A no-op when the subscription has never been charged.
+pub fn realign_charged_period(env: &Env, subscription_id: u32, sub: &Subscription) {
+    let key = charged_period_key(subscription_id);
+    if env.storage().instance().has(&key) {
+        let period_index =
+            billing_period_index(env, subscription_id, sub, sub.last_payment_timestamp);
+        env.storage().instance().set(&key, &period_index);
+    }
+}
+
+/// Returns the outstanding arrears recorded for a subscription (0 if none).
+pub fn get_arrears(env: &Env, subscription_id: u32) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::Arrears(subscription_id))
+        .unwrap_or(0)
+}
+
+/// Records the charged billing period and optional idempotency key (bounded storage).
+fn record_charged_period(
+    env: &Env,
+    subscription_id: u32,
+    period_index: u64,
+    idempotency_key: Option<BytesN<32>>,
+) {
+    let storage = env.storage().instance();
+    storage.set(&charged_period_key(subscription_id), &period_index);
+    if let Some(k) = idempotency_key {
+        storage.set(&idem_key(subscription_id), &k);
+    }
+}
+
 /// Performs a single interval-based charge with optional replay protection.
 ///
+/// # Arrears
+///
+/// Any arrears recorded by earlier partial charges are collected from the prepaid
+/// balance (up to what is available) before the interval amount is debited.
+///
 /// # Idempotency
 ///
 /// - If `idempotency_key` is `Some(k)` and we already processed this subscription with key `k`,
 ///   returns `Ok(())` without changing state (idempotent success).
-/// - Otherwise we derive a period from `now / interval_seconds`. If this period was already
+/// - Otherwise we derive a period from `now / interval_seconds` (aligned to the
+///   billing anchor, if set). If this period was already
 ///   charged, returns `Err(Error::Replay)`.
 ///
 /// # Storage
 ///
 /// Bounded: one `u64` (last charged period) and optionally one idempotency key per subscription.
+///
+/// # Expected amount
+///
+/// If `expected_amount` is `Some(x)`, the charge fails with `Error::InvalidAmount` unless
+/// the amount it would debit (after any scheduled plan switch) equals `x`. This protects
+/// automated chargers against a price changed between reading and charging.
 pub fn charge_one(
     env: &Env,
     subscription_id: u32,
     now: u64,
-    idempotency_key: Option<soroban_sdk::BytesN<32>>,
+    idempotency_key: Option<BytesN<32>>,
+    expected_amount: Option<i128>,
 ) -> Result<(), Error> {
+    crate::admin::require_not_paused(env)?;
     let mut sub = get_subscription(env, subscription_id)?;
 
+    check_merchant_chargeable(env, &sub, now)?;
+
     if sub.status != SubscriptionStatus::Active && sub.status != SubscriptionStatus::GracePeriod {
         return Err(Error::NotActive);
     }
+    if !crate::subscription::interval_charges_enabled(env, subscription_id)
+        || crate::subscription::is_pending_merchant(env, subscription_id)
+        || cancellation_effective(env, subscription_id, now)
+    {
+        return Err(Error::NotActive);
+    }
 
-    let period_index = now / sub.interval_seconds;
+    // A charge within the admin-configured tolerance of its due time counts as made at
+    // that time, so `last_payment_timestamp` stays on schedule.
+    let now = effective_charge_time(env, subscription_id, &sub, now);
+    let period_index = billing_period_index(env, subscription_id, &sub, now);
 
     // Idempotent return: same idempotency key already processed for this subscription
     if let Some(ref k) = idempotency_key {
         if let Some(stored) = env
             .storage()
             .instance()
-            .get::<_, soroban_sdk::BytesN<32>>(&idem_key(subscription_id))
+            .get::<_, BytesN<32>>(&idem_key(subscription_id))
         {
             if stored == *k {
                 return Ok(());
@@ -81,47 +182,147 @@ pub fn charge_one(
         }
     }
 
-    let next_allowed = sub
-        .last_payment_timestamp
-        .checked_add(sub.interval_seconds)
-        .ok_or(Error::Overflow)?;
+    if crate::subscription::get_start_timestamp(env, subscription_id)
+        .is_some_and(|start| now < start)
+    {
+        return Err(Error::NotStarted);
+    }
+
+    let next_allowed = next_due_for(env, subscription_id, &sub).ok_or(Error::Overflow)?;
     if now < next_allowed {
         return Err(Error::IntervalNotElapsed);
     }
 
+    // A plan switch scheduled with `switch_plan` takes effect at this cycle boundary.
+    let period_index = if crate::plan::apply_pending_plan(env, subscription_id, &mut sub) {
+        billing_period_index(env, subscription_id, &sub, now)
+    } else {
+        period_index
+    };
+
+    // Post-paid subscriptions bill the usage recorded over the period that just ended
+    // instead of the flat amount.
+    let postpaid = crate::subscription::is_postpaid(env, subscription_id);
+    let due = if postpaid {
+        get_pending_usage(env, subscription_id)
+    } else {
+        sub.amount
+    };
+    if expected_amount.is_some_and(|expected| expected != due) {
+        return Err(Error::InvalidAmount);
+    }
+
     let storage = env.storage().instance();
 
-    match safe_sub_balance(sub.prepaid_balance, sub.amount) {
+    // Outstanding arrears from earlier partial charges are collected first; the
+    // interval charge only succeeds once they are cleared. Nothing is written until the
+    // charge itself is known to go through, so a rejected charge leaves the arrears
+    // untouched.
+    let arrears = get_arrears(env, subscription_id);
+    let arrears_collected = arrears.min(sub.prepaid_balance).max(0);
+    let arrears_remaining = safe_sub_balance(arrears, arrears_collected)?;
+    let available = safe_sub_balance(sub.prepaid_balance, arrears_collected)?;
+
+    match safe_sub_balance(available, due) {
         Ok(new_balance) => {
             sub.prepaid_balance = new_balance;
             sub.last_payment_timestamp = now;
+            sub.charge_count = sub.charge_count.saturating_add(1);
             if sub.status == SubscriptionStatus::GracePeriod {
                 validate_status_transition(&sub.status, &SubscriptionStatus::Active)?;
                 sub.status = SubscriptionStatus::Active;
             }
 
             storage.set(&subscription_id, &sub);
+            storage.remove(&DataKey::PausedSeconds(subscription_id));
+            if postpaid {
+                storage.remove(&DataKey::PendingUsage(subscription_id));
+            }
+            collect_arrears(
+                env,
+                subscription_id,
+                &sub,
+                arrears_collected,
+                arrears_remaining,
+            )?;
+            crate::merchant::credit_charge(env, subscription_id, &sub.merchant, due)?;
+            record_charged_period(env, subscription_id, period_index, idempotency_key);
 
-            // Record charged period and optional idempotency key (bounded storage)
-            storage.set(&charged_period_key(subscription_id), &period_index);
-            if let Some(k) = idempotency_key {
-                storage.set(&idem_key(subscription_id), &k);
+            env.events().publish(
+                (symbol_short!("charged"),),
+                SubscriptionChargedEvent {
+                    subscription_id,
+                    merchant: sub.merchant.clone(),
+                    amount: due,
+                },
+            );
+
+            // Only reached once every check that can reject the charge has passed, so a
+            // rejected charge (maintenance, price guard, ...) never pulls a top-up.
+            apply_auto_topup(env, subscription_id, &mut sub)?;
+
+            // Nudge the subscriber to top up before a charge actually fails.
+            let buffer = crate::admin::get_min_balance_buffer(env);
+            if sub.prepaid_balance < buffer && crate::admin::events_verbose(env) {
+                env.events().publish(
+                    (Symbol::new(env, "low_balance"), subscription_id),
+                    (sub.subscriber.clone(), sub.prepaid_balance, buffer),
+                );
+            }
+
+            Ok(())
+        }
+        Err(_)
+            if crate::subscription::allows_partial_charge(env, subscription_id)
+                && available > 0 =>
+        {
+            // Partial charge: collect what remains and carry the shortfall as arrears.
+            let collected = available;
+            let shortfall = safe_sub(due, collected)?;
+            let arrears = safe_add_balance(arrears_remaining, shortfall)?;
+
+            validate_status_transition(&sub.status, &SubscriptionStatus::GracePeriod)?;
+            sub.status = SubscriptionStatus::GracePeriod;
+            sub.prepaid_balance = 0;
+            sub.last_payment_timestamp = now;
+            sub.charge_count = sub.charge_count.saturating_add(1);
+
+            storage.set(&subscription_id, &sub);
+            collect_arrears(
+                env,
+                subscription_id,
+                &sub,
+                arrears_collected,
+                arrears_remaining,
+            )?;
+            storage.set(&DataKey::Arrears(subscription_id), &arrears);
+            storage.remove(&DataKey::PausedSeconds(subscription_id));
+            if postpaid {
+                storage.remove(&DataKey::PendingUsage(subscription_id));
             }
+            crate::merchant::credit_charge(env, subscription_id, &sub.merchant, collected)?;
+            record_charged_period(env, subscription_id, period_index, idempotency_key);
 
             env.events().publish(
                 (symbol_short!("charged"),),
                 SubscriptionChargedEvent {
                     subscription_id,
                     merchant: sub.merchant.clone(),
-                    amount: sub.amount,
+                    amount: collected,
                 },
             );
+            if crate::admin::events_verbose(env) {
+                env.events().publish(
+                    (Symbol::new(env, "partial_charge"), subscription_id),
+                    (collected, shortfall, arrears),
+                );
+            }
 
             Ok(())
         }
         Err(_) => {
             // Insufficient balance — check if grace period applies
-            let grace_duration = crate::admin::get_grace_period(env).unwrap_or(0);
+            let grace_duration = grace_duration(env, subscription_id, &sub);
             let grace_expires = next_allowed
                 .checked_add(grace_duration)
                 .ok_or(Error::Overflow)?;
@@ -137,17 +338,213 @@ pub fn charge_one(
                 validate_status_transition(&sub.status, &SubscriptionStatus::InsufficientBalance)?;
                 sub.status = SubscriptionStatus::InsufficientBalance;
                 storage.set(&subscription_id, &sub);
+                env.events().publish(
+                    (Symbol::new(env, "sub_suspended"), subscription_id),
+                    (
+                        sub.subscriber.clone(),
+                        InsufficientBalanceError::new(sub.prepaid_balance, due),
+                    ),
+                );
                 Err(Error::InsufficientBalance)
             }
         }
     }
 }
 
+/// Commits the arrears part of a successful charge: stores what is still owed and
+/// credits the merchant with `collected`. A no-op when nothing was collected.
+fn collect_arrears(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    collected: i128,
+    remaining: i128,
+) -> Result<(), Error> {
+    if collected <= 0 {
+        return Ok(());
+    }
+    env.storage()
+        .instance()
+        .set(&DataKey::Arrears(subscription_id), &remaining);
+    crate::merchant::credit_charge(env, subscription_id, &sub.merchant, collected)?;
+    if crate::admin::events_verbose(env) {
+        env.events().publish(
+            (Symbol::new(env, "arrears_collected"), subscription_id),
+            (collected, remaining),
+        );
+    }
+    Ok(())
+}
+
+/// Pulls the configured auto top-up from the subscriber's wallet when the balance has
+/// dropped below the threshold. A failed `transfer_from` (e.g. a missing or spent
+/// allowance) is ignored so the charge itself still succeeds.
+fn apply_auto_topup(env: &Env, subscription_id: u32, sub: &mut Subscription) -> Result<(), Error> {
+    let Some(config) = crate::subscription::get_auto_topup(env, subscription_id) else {
+        return Ok(());
+    };
+    if sub.prepaid_balance >= config.threshold {
+        return Ok(());
+    }
+
+    let token_addr: Address = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "token"))
+        .ok_or(Error::NotInitialized)?;
+    let token_client = soroban_sdk::token::Client::new(env, &token_addr);
+    let vault = env.current_contract_address();
+    if !matches!(
+        token_client.try_transfer_from(&vault, &sub.subscriber, &vault, &config.amount),
+        Ok(Ok(()))
+    ) {
+        return Ok(());
+    }
+
+    sub.prepaid_balance = safe_add_balance(sub.prepaid_balance, config.amount)?;
+    env.storage().instance().set(&subscription_id, &*sub);
+    crate::subscription::adjust_total_prepaid(env, config.amount)?;
+    if crate::admin::events_verbose(env) {
+        env.events().publish(
+            (Symbol::new(env, "auto_topup"), subscription_id),
+            (config.amount, sub.prepaid_balance),
+        );
+    }
+    Ok(())
+}
+
+/// Grace window after a missed charge: the subscription's override if set, else the
+/// global grace period. A first charge also gets the signup grace, since new
+/// subscribers often fund just after creating.
+fn grace_duration(env: &Env, subscription_id: u32, sub: &Subscription) -> u64 {
+    grace_window(
+        env,
+        crate::admin::get_grace_override(env, subscription_id),
+        sub,
+    )
+}
+
+/// [`grace_duration`] with the override passed in, for subscriptions that are not stored.
+pub fn grace_window(env: &Env, grace_override: Option<u64>, sub: &Subscription) -> u64 {
+    let grace = grace_override.unwrap_or_else(|| crate::admin::get_grace_period(env).unwrap_or(0));
+    if sub.charge_count == 0 {
+        grace.max(crate::admin::get_first_charge_grace(env))
+    } else {
+        grace
+    }
+}
+
+/// Time at which a subscription in `GracePeriod` is due to be suspended: its next due
+/// time plus the grace window, as applied by [`charge_one`]. `None` on overflow.
+pub fn grace_deadline(env: &Env, subscription_id: u32, sub: &Subscription) -> Option<u64> {
+    next_due_for(env, subscription_id, sub)?.checked_add(grace_duration(env, subscription_id, sub))
+}
+
+/// Merchant-side guards shared by interval and usage charges: the vault cannot be its
+/// own merchant (`InvalidInput`), a blocked merchant cannot be paid (`Forbidden`), and
+/// nothing is charged during the merchant's maintenance window (`InMaintenance`).
+fn check_merchant_chargeable(env: &Env, sub: &Subscription, now: u64) -> Result<(), Error> {
+    // Paying the vault itself would credit earnings it already holds in custody.
+    if sub.merchant == env.current_contract_address() {
+        return Err(Error::InvalidInput);
+    }
+    if crate::admin::is_address_blocked(env, &sub.merchant) {
+        return Err(Error::Forbidden);
+    }
+    if crate::merchant::get_maintenance_window(env, &sub.merchant)
+        .is_some_and(|window| now < window.until)
+    {
+        return Err(Error::InMaintenance);
+    }
+    Ok(())
+}
+
+/// Read-only evaluation of [`charge_one`]'s acceptance rules at `now`.
+///
+/// Returns the amount the interval charge would debit (the remaining balance for a
+/// partial charge) and the resulting `last_payment_timestamp`, or the error
+/// `charge_one` would return. Never writes storage, emits events or moves tokens, so
+/// the subscription's status is not advanced on failure.
+pub fn simulate_charge_one(
+    env: &Env,
+    subscription_id: u32,
+    now: u64,
+) -> Result<(i128, u64), Error> {
+    crate::admin::require_not_paused(env)?;
+    let sub = get_subscription(env, subscription_id)?;
+    check_merchant_chargeable(env, &sub, now)?;
+
+    if sub.status != SubscriptionStatus::Active && sub.status != SubscriptionStatus::GracePeriod {
+        return Err(Error::NotActive);
+    }
+    if !crate::subscription::interval_charges_enabled(env, subscription_id)
+        || crate::subscription::is_pending_merchant(env, subscription_id)
+        || cancellation_effective(env, subscription_id, now)
+    {
+        return Err(Error::NotActive);
+    }
+
+    // A charge within the admin-configured tolerance of its due time counts as made at
+    // that time, so `last_payment_timestamp` stays on schedule.
+    let now = effective_charge_time(env, subscription_id, &sub, now);
+    let period_index = billing_period_index(env, subscription_id, &sub, now);
+    if let Some(stored_period) = env
+        .storage()
+        .instance()
+        .get::<_, u64>(&charged_period_key(subscription_id))
+    {
+        if period_index <= stored_period {
+            return Err(Error::Replay);
+        }
+    }
+
+    if crate::subscription::get_start_timestamp(env, subscription_id)
+        .is_some_and(|start| now < start)
+    {
+        return Err(Error::NotStarted);
+    }
+
+    let next_allowed = next_due_for(env, subscription_id, &sub).ok_or(Error::Overflow)?;
+    if now < next_allowed {
+        return Err(Error::IntervalNotElapsed);
+    }
+
+    let need = charge_requirement(env, subscription_id, &sub);
+    if need.available >= need.required {
+        Ok((need.required, now))
+    } else if crate::subscription::allows_partial_charge(env, subscription_id) && need.available > 0
+    {
+        Ok((need.available, now))
+    } else {
+        Err(Error::InsufficientBalance)
+    }
+}
+
+/// What the next interval charge needs: `required` is the amount it would debit (the
+/// pending plan's amount, or recorded usage when postpaid) and `available` the balance
+/// left for it once arrears are collected.
+pub fn charge_requirement(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+) -> InsufficientBalanceError {
+    let mut required = crate::plan::pending_plan_template(env, subscription_id)
+        .map_or(sub.amount, |plan| plan.amount);
+    if crate::subscription::is_postpaid(env, subscription_id) {
+        required = get_pending_usage(env, subscription_id);
+    }
+    let arrears = get_arrears(env, subscription_id).min(sub.prepaid_balance);
+    InsufficientBalanceError::new(sub.prepaid_balance - arrears, required)
+}
+
 /// Debit a metered `usage_amount` from a subscription's prepaid balance.
 ///
 /// Shared safety checks:
 /// * Subscription must exist (`NotFound`).
-/// * Subscription must be `Active` (`NotActive`).
+/// * The merchant must be chargeable: not blocked (`Forbidden`) and outside its
+///   maintenance window (`InMaintenance`), as for interval charges.
+/// * Subscription must be `Active` (`NotActive`). Unlike interval charges, usage is
+///   not billed in `GracePeriod`: a delinquent subscriber should not run up more usage.
 /// * `usage_enabled` must be `true` (`UsageNotEnabled`).
 /// * `usage_amount` must be positive (`InvalidAmount`).
 /// * `prepaid_balance >= usage_amount` (`InsufficientPrepaidBalance`).
@@ -156,28 +553,42 @@ pub fn charge_one(
 /// the subscription transitions to `InsufficientBalance`, blocking further
 /// charges until the subscriber tops up.
 pub fn charge_usage_one(env: &Env, subscription_id: u32, usage_amount: i128) -> Result<(), Error> {
+    crate::admin::require_not_paused(env)?;
     let mut sub = get_subscription(env, subscription_id)?;
+    check_merchant_chargeable(env, &sub, env.ledger().timestamp())?;
 
-    if sub.status != SubscriptionStatus::Active {
-        return Err(Error::NotActive);
+    match sub.status {
+        SubscriptionStatus::Active => {}
+        SubscriptionStatus::GracePeriod | SubscriptionStatus::InsufficientBalance => {
+            return Err(Error::NotActive);
+        }
+        SubscriptionStatus::Paused | SubscriptionStatus::Cancelled => {
+            return Err(Error::NotActive);
+        }
     }
 
     if !sub.usage_enabled {
         return Err(Error::UsageNotEnabled);
     }
+    if !crate::subscription::usage_charges_enabled(env, subscription_id)
+        || crate::subscription::is_pending_merchant(env, subscription_id)
+        || cancellation_effective(env, subscription_id, env.ledger().timestamp())
+    {
+        return Err(Error::NotActive);
+    }
 
     if usage_amount <= 0 {
         return Err(Error::InvalidAmount);
     }
 
-    if sub.prepaid_balance < usage_amount {
+    let cost = usage_amount
+        .checked_mul(crate::subscription::get_usage_rate(env, subscription_id))
+        .ok_or(Error::Overflow)?;
+    if sub.prepaid_balance < cost {
         return Err(Error::InsufficientPrepaidBalance);
     }
 
-    sub.prepaid_balance = sub
-        .prepaid_balance
-        .checked_sub(usage_amount)
-        .ok_or(Error::Overflow)?;
+    sub.prepaid_balance = safe_sub_balance(sub.prepaid_balance, cost)?;
 
     // If the vault is now empty, transition to InsufficientBalance so no
     // further charges (interval or usage) can proceed until top-up.
@@ -187,5 +598,77 @@ pub fn charge_usage_one(env: &Env, subscription_id: u32, usage_amount: i128) ->
     }
 
     env.storage().instance().set(&subscription_id, &sub);
+    crate::merchant::credit_merchant(env, subscription_id, &sub.merchant, cost)?;
+    Ok(())
+}
+
+/// Usage recorded with [`record_usage`] that has not been settled yet (0 if none).
+pub fn get_pending_usage(env: &Env, subscription_id: u32) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::PendingUsage(subscription_id))
+        .unwrap_or(0)
+}
+
+/// Records `usage_amount` owed by an `Active`, usage-enabled subscription without
+/// debiting the balance yet. Merchant auth required.
+pub fn record_usage(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    usage_amount: i128,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if sub.status != SubscriptionStatus::Active {
+        return Err(Error::NotActive);
+    }
+    if !sub.usage_enabled {
+        return Err(Error::UsageNotEnabled);
+    }
+    if usage_amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    let pending = safe_add_balance(get_pending_usage(env, subscription_id), usage_amount)?;
+    env.storage()
+        .instance()
+        .set(&DataKey::PendingUsage(subscription_id), &pending);
+    env.events().publish(
+        (Symbol::new(env, "usage_recorded"), subscription_id),
+        (usage_amount, pending),
+    );
+    Ok(())
+}
+
+/// Pays pending usage to the merchant out of `sub.prepaid_balance`, as far as the
+/// balance allows, and clears the pending amount. Any unpaid remainder is written off
+/// and reported in the `usage_settled` event. The caller persists `sub`.
+pub fn settle_pending_usage(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut crate::types::Subscription,
+) -> Result<(), Error> {
+    let pending = get_pending_usage(env, subscription_id);
+    if pending <= 0 {
+        return Ok(());
+    }
+
+    let settled = pending.min(sub.prepaid_balance.max(0));
+    sub.prepaid_balance = safe_sub_balance(sub.prepaid_balance, settled)?;
+    env.storage()
+        .instance()
+        .remove(&DataKey::PendingUsage(subscription_id));
+    if settled > 0 {
+        crate::merchant::credit_merchant(env, subscription_id, &sub.merchant, settled)?;
+    }
+    env.events().publish(
+        (Symbol::new(env, "usage_settled"), subscription_id),
+        (settled, safe_sub(pending, settled)?),
+    );
     Ok(())
 }
@@ -7,6 +7,7 @@
 mod admin;
 mod charge_core;
 mod merchant;
+mod plan;
 mod queries;
 mod state_machine;
 mod subscription;
@@ -18,10 +19,12 @@ mod safe_math;
 pub use state_machine::{can_transition, get_allowed_transitions, validate_status_transition};
 pub use types::*;
 
-pub use queries::compute_next_charge_info;
-use soroban_sdk::{contract, contractimpl, Address, Env, Symbol, Vec};
+pub use queries::{
+    compute_anchored_next, compute_next_charge_info, next_calendar_month, next_calendar_month_on,
+};
+use soroban_sdk::{contract, contractimpl, Address, Bytes, BytesN, Env, Symbol, Vec};
 
-const STORAGE_VERSION: u32 = 1;
+const STORAGE_VERSION: u32 = 6;
 const MAX_EXPORT_LIMIT: u32 = 100;
 
 fn require_admin_auth(env: &Env, admin: &Address) -> Result<(), Error> {
@@ -33,6 +36,60 @@ fn require_admin_auth(env: &Env, admin: &Address) -> Result<(), Error> {
     Ok(())
 }
 
+/// Summaries for IDs `start_id..start_id + limit`, optionally only those in `status`,
+/// followed by a `migration_export` event with the number exported.
+fn export_summaries(
+    env: &Env,
+    admin: Address,
+    start_id: u32,
+    limit: u32,
+    status: Option<SubscriptionStatus>,
+) -> Result<Vec<SubscriptionSummary>, Error> {
+    require_admin_auth(env, &admin)?;
+    if limit > MAX_EXPORT_LIMIT {
+        return Err(Error::InvalidExportLimit);
+    }
+    if limit == 0 {
+        return Ok(Vec::new(env));
+    }
+
+    let next_id: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "next_id"))
+        .unwrap_or(0);
+    if start_id >= next_id {
+        return Ok(Vec::new(env));
+    }
+
+    let end_id = start_id.saturating_add(limit).min(next_id);
+    let mut out = Vec::new(env);
+    let mut exported = 0u32;
+    let mut id = start_id;
+    while id < end_id {
+        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+            if status.as_ref().is_none_or(|s| *s == sub.status) {
+                out.push_back(queries::to_summary(id, sub));
+                exported += 1;
+            }
+        }
+        id += 1;
+    }
+
+    env.events().publish(
+        (Symbol::new(env, "migration_export"),),
+        MigrationExportEvent {
+            admin,
+            start_id,
+            limit,
+            exported,
+            timestamp: env.ledger().timestamp(),
+        },
+    );
+
+    Ok(out)
+}
+
 // ── Contract ─────────────────────────────────────────────────────────────────
 
 #[contract]
@@ -54,8 +111,24 @@ impl SubscriptionVault {
         admin::do_init(&env, token, token_decimals, admin, min_topup, grace_period)
     }
 
+    /// Schema version of the stored data (0 if it predates version tracking).
+    pub fn get_storage_version(env: Env) -> u32 {
+        admin::get_storage_version(&env)
+    }
+
+    /// **ADMIN ONLY**: Upgrade stored data to the current `STORAGE_VERSION`, rewriting
+    /// up to `limit` subscription ids per call.
+    ///
+    /// Returns the number of ids still to migrate; call again until it returns 0.
+    /// Mutating entrypoints return `MigrationRequired` until the migration completes
+    /// on a contract whose stored version is older than the code's.
+    pub fn migrate(env: Env, admin: Address, limit: u32) -> Result<u32, Error> {
+        admin::do_migrate(&env, admin, limit)
+    }
+
     /// Update the minimum top-up threshold. Only callable by admin.
     pub fn set_min_topup(env: Env, admin: Address, min_topup: i128) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
         admin::do_set_min_topup(&env, admin, min_topup)
     }
 
@@ -64,6 +137,18 @@ impl SubscriptionVault {
         admin::get_min_topup(&env)
     }
 
+    /// Get the billing token address passed to `init`, e.g. to display the currency or
+    /// approve allowances.
+    pub fn get_token(env: Env) -> Result<Address, Error> {
+        admin::get_token(&env)
+    }
+
+    /// Get the billing token's decimals as passed to `init` (e.g. 7 for Stellar USDC),
+    /// so clients can format amounts without hardcoding them.
+    pub fn get_token_decimals(env: Env) -> Result<u32, Error> {
+        admin::get_token_decimals(&env)
+    }
+
     /// Get the current admin address.
     pub fn get_admin(env: Env) -> Result<Address, Error> {
         admin::do_get_admin(&env)
@@ -76,10 +161,46 @@ impl SubscriptionVault {
     /// - Immediate effect — old admin loses access instantly.
     /// - Irreversible without the new admin's cooperation.
     /// - Emits an `admin_rotation` event for audit trail.
+    /// - Records the outgoing admin, readable via [`Self::get_previous_admin`].
     pub fn rotate_admin(env: Env, current_admin: Address, new_admin: Address) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
         admin::do_rotate_admin(&env, current_admin, new_admin)
     }
 
+    /// **ADMIN ONLY**: Block or unblock `address` as a payment destination. Creating or
+    /// charging a subscription whose merchant is blocked fails with `Forbidden`.
+    pub fn set_address_blocked(
+        env: Env,
+        admin: Address,
+        address: Address,
+        blocked: bool,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_address_blocked(&env, admin, address, blocked)
+    }
+
+    /// Whether `address` is blocked from receiving subscription payments.
+    pub fn is_address_blocked(env: Env, address: Address) -> bool {
+        admin::is_address_blocked(&env, &address)
+    }
+
+    /// **ADMIN ONLY**: Pause or unpause the contract. While paused, charges, deposits,
+    /// one-off charges and new subscriptions fail with `ContractPaused`.
+    pub fn set_contract_paused(env: Env, admin: Address, paused: bool) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_contract_paused(&env, admin, paused)
+    }
+
+    /// Whether the admin has paused the contract.
+    pub fn is_contract_paused(env: Env) -> bool {
+        admin::is_contract_paused(&env)
+    }
+
+    /// The admin replaced by the most recent rotation (`NotFound` if never rotated).
+    pub fn get_previous_admin(env: Env) -> Result<Address, Error> {
+        admin::get_previous_admin(&env)
+    }
+
     /// **ADMIN ONLY**: Recover stranded funds from the contract.
     ///
     /// Tightly-scoped mechanism for recovering funds that have become
@@ -92,20 +213,168 @@ impl SubscriptionVault {
         amount: i128,
         reason: RecoveryReason,
     ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
         admin::do_recover_stranded_funds(&env, admin, recipient, amount, reason)
     }
 
-    /// Charge a batch of subscriptions in one transaction. Admin only.
+    /// **ADMIN ONLY**: Set how long a subscription may stay paused before
+    /// [`Self::sweep_stale_pauses`] cancels it (0, the default, disables the sweep).
+    pub fn set_max_pause_seconds(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_max_pause_seconds(&env, admin, seconds)
+    }
+
+    pub fn get_max_pause_seconds(env: Env) -> u64 {
+        admin::get_max_pause_seconds(&env)
+    }
+
+    /// **ADMIN ONLY**: Cancel and refund subscriptions paused for longer than
+    /// `max_pause_seconds`.
+    ///
+    /// Scans IDs `start_id..start_id + limit` (capped at `MAX_SCAN_LIMIT`). Pending usage
+    /// is settled to the merchant, the rest of the balance goes back to the subscriber,
+    /// and the number cancelled is returned.
+    pub fn sweep_stale_pauses(
+        env: Env,
+        admin: Address,
+        start_id: u32,
+        limit: u32,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_sweep_stale_pauses(&env, admin, start_id, limit)
+    }
+
+    /// **ADMIN ONLY**: Suspend `GracePeriod` subscriptions whose grace window has ended.
+    ///
+    /// Scans IDs `start_id..start_id + limit` (capped at `MAX_SCAN_LIMIT`), moves each
+    /// expired one to `InsufficientBalance` and returns how many were transitioned.
+    pub fn sweep_expired_grace(
+        env: Env,
+        admin: Address,
+        start_id: u32,
+        limit: u32,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_sweep_expired_grace(&env, admin, start_id, limit)
+    }
+
+    /// **ADMIN ONLY**: Complete cancellations scheduled under a notice period.
+    ///
+    /// Scans IDs `start_id..start_id + limit` (capped at `MAX_SCAN_LIMIT`), cancels each
+    /// one whose notice has run out on behalf of its original requester (fee and
+    /// refundable balance as for `cancel_subscription`) and returns how many were cancelled.
+    pub fn sweep_scheduled_cancellations(
+        env: Env,
+        admin: Address,
+        start_id: u32,
+        limit: u32,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_sweep_scheduled_cancellations(&env, admin, start_id, limit)
+    }
+
+    /// **ADMIN ONLY**: Cancel a chronically delinquent subscription.
+    ///
+    /// Succeeds only if the subscription is `InsufficientBalance` or `GracePeriod` and
+    /// its prepaid balance is below `min_balance`; otherwise `InvalidStatusTransition`.
+    /// Safe for an automated dunning job to call on every candidate.
+    pub fn cancel_if_delinquent(
+        env: Env,
+        admin: Address,
+        subscription_id: u32,
+        min_balance: i128,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_cancel_if_delinquent(&env, admin, subscription_id, min_balance)
+    }
+
+    /// **ADMIN ONLY**: Force-cancel a subscription, e.g. one tied to a fraudulent merchant.
+    ///
+    /// Bypasses subscriber/merchant auth, settles pending usage to the merchant, refunds
+    /// the remaining prepaid balance to the subscriber and emits an `admin_force_cancel`
+    /// event with the admin and reason, plus `cancelled` with `CancellationReason::Fraud`.
+    /// Fails with `NotActive` if the subscription is already cancelled.
+    pub fn admin_force_cancel(
+        env: Env,
+        admin: Address,
+        subscription_id: u32,
+        reason: RecoveryReason,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_admin_force_cancel(&env, admin, subscription_id, reason)
+    }
+
+    /// **ADMIN ONLY**: Give a returning customer a fresh `Active` subscription with the
+    /// terms of a cancelled one. Returns the new ID; the original stays `Cancelled` and
+    /// `get_reactivated_from(new_id)` points back to it.
+    pub fn admin_reactivate(
+        env: Env,
+        admin: Address,
+        old_subscription_id: u32,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_admin_reactivate(&env, admin, old_subscription_id)
+    }
+
+    /// **ADMIN ONLY**: Correct a subscription's `last_payment_timestamp` after a billing
+    /// error. The timestamp may not be in the future; an audit event records the old and
+    /// new values.
+    pub fn admin_set_last_payment(
+        env: Env,
+        admin: Address,
+        subscription_id: u32,
+        timestamp: u64,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_admin_set_last_payment(&env, admin, subscription_id, timestamp)
+    }
+
+    /// The cancelled subscription ID a reactivated subscription was created from.
+    pub fn get_reactivated_from(env: Env, subscription_id: u32) -> Option<u32> {
+        admin::get_reactivated_from(&env, subscription_id)
+    }
+
+    /// Charge a batch of subscriptions in one transaction. Billing engine only
+    /// (the admin unless `set_billing_engine` designated another address).
     ///
     /// Returns a per-subscription result vector so callers can identify
-    /// which charges succeeded and which failed (with error codes).
+    /// which charges succeeded and which failed (with error codes). A single
+    /// [`BatchChargeSummaryEvent`] with the totals is emitted at the end.
     pub fn batch_charge(
         env: Env,
         subscription_ids: Vec<u32>,
     ) -> Result<Vec<BatchChargeResult>, Error> {
+        admin::require_current_storage(&env)?;
         admin::do_batch_charge(&env, &subscription_ids)
     }
 
+    /// Charge one subscriber's bundled subscriptions atomically: either every charge
+    /// succeeds in full or the call reverts with the first error (`InsufficientBalance`
+    /// where only a partial charge is possible). All IDs must share a subscriber (at
+    /// most `MAX_BATCH_SIZE`). Billing engine only.
+    pub fn charge_bundle(env: Env, subscription_ids: Vec<u32>) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_charge_bundle(&env, &subscription_ids)
+    }
+
+    /// Dry run of `batch_charge`: reports which charges would succeed under the
+    /// contract's own rules (status, interval, balance) without writing state.
+    pub fn simulate_batch_charge(env: Env, subscription_ids: Vec<u32>) -> Vec<BatchChargeResult> {
+        admin::do_simulate_batch_charge(&env, &subscription_ids)
+    }
+
+    /// **ADMIN ONLY**: Drop cancelled subscription IDs from a merchant's index.
+    ///
+    /// Returns the number of IDs removed.
+    pub fn compact_merchant_index(
+        env: Env,
+        admin: Address,
+        merchant: Address,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_compact_merchant_index(&env, admin, merchant)
+    }
+
     /// **ADMIN ONLY**: Export contract-level configuration for migration tooling.
     ///
     /// Read-only snapshot intended for carefully managed upgrades.
@@ -159,17 +428,7 @@ impl SubscriptionVault {
             },
         );
 
-        Ok(SubscriptionSummary {
-            subscription_id,
-            subscriber: sub.subscriber,
-            merchant: sub.merchant,
-            amount: sub.amount,
-            interval_seconds: sub.interval_seconds,
-            last_payment_timestamp: sub.last_payment_timestamp,
-            status: sub.status,
-            prepaid_balance: sub.prepaid_balance,
-            usage_enabled: sub.usage_enabled,
-        })
+        Ok(queries::to_summary(subscription_id, sub))
     }
 
     /// **ADMIN ONLY**: Export a paginated list of subscription summaries.
@@ -179,60 +438,23 @@ impl SubscriptionVault {
         start_id: u32,
         limit: u32,
     ) -> Result<Vec<SubscriptionSummary>, Error> {
-        require_admin_auth(&env, &admin)?;
-        if limit > MAX_EXPORT_LIMIT {
-            return Err(Error::InvalidExportLimit);
-        }
-        if limit == 0 {
-            return Ok(Vec::new(&env));
-        }
-
-        let next_id: u32 = env
-            .storage()
-            .instance()
-            .get(&Symbol::new(&env, "next_id"))
-            .unwrap_or(0);
-        if start_id >= next_id {
-            return Ok(Vec::new(&env));
-        }
-
-        let end_id = start_id.saturating_add(limit).min(next_id);
-        let mut out = Vec::new(&env);
-        let mut exported = 0u32;
-        let mut id = start_id;
-        while id < end_id {
-            if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
-                out.push_back(SubscriptionSummary {
-                    subscription_id: id,
-                    subscriber: sub.subscriber,
-                    merchant: sub.merchant,
-                    amount: sub.amount,
-                    interval_seconds: sub.interval_seconds,
-                    last_payment_timestamp: sub.last_payment_timestamp,
-                    status: sub.status,
-                    prepaid_balance: sub.prepaid_balance,
-                    usage_enabled: sub.usage_enabled,
-                });
-                exported += 1;
-            }
-            id += 1;
-        }
-
-        env.events().publish(
-            (Symbol::new(&env, "migration_export"),),
-            MigrationExportEvent {
-                admin,
-                start_id,
-                limit,
-                exported,
-                timestamp: env.ledger().timestamp(),
-            },
-        );
+        export_summaries(&env, admin, start_id, limit, None)
+    }
 
-        Ok(out)
+    /// **ADMIN ONLY**: Like [`Self::export_subscription_summaries`], but returns only
+    /// subscriptions in `status`. The `migration_export` event reports the filtered count.
+    pub fn export_subscriptions_by_status(
+        env: Env,
+        admin: Address,
+        status: SubscriptionStatus,
+        start_id: u32,
+        limit: u32,
+    ) -> Result<Vec<SubscriptionSummary>, Error> {
+        export_summaries(&env, admin, start_id, limit, Some(status))
     }
 
     pub fn set_grace_period(env: Env, admin: Address, grace_period: u64) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
         admin::do_set_grace_period(&env, admin, grace_period)
     }
 
@@ -240,6 +462,190 @@ impl SubscriptionVault {
         admin::get_grace_period(&env)
     }
 
+    /// Set the grace window for a subscription's first charge (0 disables). Only
+    /// callable by admin.
+    ///
+    /// When the first charge finds too little balance, the subscription enters
+    /// `GracePeriod` for the longer of this and the global grace period instead of being
+    /// suspended. Later charges use the global grace period only.
+    pub fn set_first_charge_grace(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_first_charge_grace(&env, admin, seconds)
+    }
+
+    pub fn get_first_charge_grace(env: Env) -> u64 {
+        admin::get_first_charge_grace(&env)
+    }
+
+    /// **ADMIN ONLY**: Give one subscription its own grace window, used instead of the
+    /// global grace period when a charge fails. `None` reverts to the global value.
+    pub fn set_grace_override(
+        env: Env,
+        admin: Address,
+        subscription_id: u32,
+        seconds: Option<u64>,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_grace_override(&env, admin, subscription_id, seconds)
+    }
+
+    /// Per-subscription grace override, or `None` when the global grace period applies.
+    pub fn get_grace_override(env: Env, subscription_id: u32) -> Option<u64> {
+        admin::get_grace_override(&env, subscription_id)
+    }
+
+    /// Set the address that receives platform fees. Only callable by admin.
+    pub fn set_fee_collector(env: Env, admin: Address, collector: Address) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_fee_collector(&env, admin, collector)
+    }
+
+    /// The platform fee collector (`NotFound` if never set).
+    pub fn get_fee_collector(env: Env) -> Result<Address, Error> {
+        admin::get_fee_collector(&env)
+    }
+
+    /// Set the platform fee in basis points (max 10 000). Only callable by admin.
+    ///
+    /// The fee is taken from each interval charge and credited to the fee collector.
+    /// A nonzero fee is rejected with `InvalidInput` until a fee collector has been set
+    /// via [`Self::set_fee_collector`]. Setting 0 is always allowed.
+    pub fn set_fee_bps(env: Env, admin: Address, bps: u32) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_fee_bps(&env, admin, bps)
+    }
+
+    pub fn get_fee_bps(env: Env) -> u32 {
+        admin::get_fee_bps(&env)
+    }
+
+    /// Accept interval charges up to `seconds` before they are due (0 disables, max one
+    /// hour). Only callable by admin.
+    ///
+    /// An early charge is recorded at its scheduled time rather than the ledger time, so
+    /// cron jitter does not shift the schedule. The tolerance is capped at half of each
+    /// subscription's interval.
+    pub fn set_charge_tolerance(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_charge_tolerance(&env, admin, seconds)
+    }
+
+    pub fn get_charge_tolerance(env: Env) -> u64 {
+        admin::get_charge_tolerance(&env)
+    }
+
+    /// Set the minimum seconds between withdrawals by the same merchant (0 disables).
+    /// Only callable by admin.
+    pub fn set_withdrawal_cooldown(env: Env, admin: Address, seconds: u64) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_withdrawal_cooldown(&env, admin, seconds)
+    }
+
+    /// Set the balance buffer: a charge that leaves `prepaid_balance` below it still
+    /// succeeds but emits a `low_balance` warning so the subscriber can top up before a
+    /// charge fails (0 disables; also skipped when verbose events are off). Only
+    /// callable by admin.
+    pub fn set_min_balance_buffer(env: Env, admin: Address, buffer: i128) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_min_balance_buffer(&env, admin, buffer)
+    }
+
+    /// Cap the number of non-cancelled subscriptions a single subscriber may hold
+    /// (0 = unlimited). Creation beyond the cap fails with `SubscriptionLimitReached`.
+    /// Only callable by admin.
+    pub fn set_max_subs_per_subscriber(env: Env, admin: Address, max: u32) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_max_subs_per_subscriber(&env, admin, max)
+    }
+
+    /// Refuse new subscriptions whose per-interval `amount` is below `min` (0 disables),
+    /// avoiding dust charges. Creation below the minimum fails with `InvalidAmount`;
+    /// existing subscriptions are unaffected. Only callable by admin.
+    pub fn set_min_charge_amount(env: Env, admin: Address, min: i128) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_min_charge_amount(&env, admin, min)
+    }
+
+    /// Toggle secondary informational events on the charge path (`arrears_collected`,
+    /// `partial_charge`, `low_balance`, `auto_topup`) to save gas. The `charged` event
+    /// and audit events (recovery, admin rotation, suspension) are always emitted. Only
+    /// callable by admin.
+    pub fn set_events_verbose(env: Env, admin: Address, verbose: bool) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_events_verbose(&env, admin, verbose)
+    }
+
+    /// Whether secondary charge events are emitted (default `true`).
+    pub fn get_events_verbose(env: Env) -> bool {
+        admin::events_verbose(&env)
+    }
+
+    /// Designate the address whose auth `charge_subscription` and `batch_charge`
+    /// require. Until set, the admin is the billing engine. Only callable by admin.
+    pub fn set_billing_engine(env: Env, admin: Address, engine: Address) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_billing_engine(&env, admin, engine)
+    }
+
+    /// Address currently authorized to run interval charges.
+    pub fn get_billing_engine(env: Env) -> Result<Address, Error> {
+        admin::get_billing_engine(&env)
+    }
+
+    /// When `true`, `deposit_funds` rejects depositors other than the subscription's
+    /// subscriber with `Forbidden`. Off by default (third parties may top up).
+    /// Only callable by admin.
+    pub fn set_restrict_deposits_to_owner(
+        env: Env,
+        admin: Address,
+        restrict: bool,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_restrict_deposits_to_owner(&env, admin, restrict)
+    }
+
+    /// Whether deposits are restricted to the subscription's own subscriber.
+    pub fn get_restrict_deposits_to_owner(env: Env) -> bool {
+        admin::restrict_deposits_to_owner(&env)
+    }
+
+    /// When `true`, new subscriptions start pending and cannot be charged (`NotActive`)
+    /// until their merchant calls [`Self::confirm_subscription`]. Off by default, so
+    /// subscriptions are auto-confirmed. Only callable by admin.
+    pub fn set_merchant_confirmation(
+        env: Env,
+        admin: Address,
+        required: bool,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        admin::do_set_require_merchant_confirmation(&env, admin, required)
+    }
+
+    /// Whether new subscriptions need merchant confirmation before they can be charged.
+    pub fn get_merchant_confirmation(env: Env) -> bool {
+        admin::require_merchant_confirmation(&env)
+    }
+
+    /// Get the low-balance warning buffer (0 = disabled).
+    pub fn get_min_balance_buffer(env: Env) -> i128 {
+        admin::get_min_balance_buffer(&env)
+    }
+
+    /// Get the per-subscriber subscription cap (0 = unlimited).
+    pub fn get_max_subs_per_subscriber(env: Env) -> u32 {
+        admin::get_max_subs_per_subscriber(&env)
+    }
+
+    /// Get the minimum per-interval amount for new subscriptions (0 = none).
+    pub fn get_min_charge_amount(env: Env) -> i128 {
+        admin::get_min_charge_amount(&env)
+    }
+
+    /// Get the merchant withdrawal cooldown in seconds.
+    pub fn get_withdrawal_cooldown(env: Env) -> u64 {
+        admin::get_withdrawal_cooldown(&env)
+    }
+
     // ── Subscription lifecycle ───────────────────────────────────────────
 
     /// Create a new subscription. Caller deposits initial USDC; contract stores agreement.
@@ -256,6 +662,7 @@ impl SubscriptionVault {
         usage_enabled: bool,
         _expiration: Option<u64>,
     ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
         subscription::do_create_subscription(
             &env,
             subscriber,
@@ -266,61 +673,551 @@ impl SubscriptionVault {
         )
     }
 
-    /// Subscriber deposits more USDC into their prepaid vault.
-    ///
-    /// Rejects deposits below the configured minimum threshold.
-    pub fn deposit_funds(
+    /// Same as [`create_subscription`](Self::create_subscription), attaching opaque
+    /// `metadata` (at most `MAX_METADATA_LEN` bytes) for integrators.
+    pub fn create_subscription_with_meta(
         env: Env,
-        subscription_id: u32,
         subscriber: Address,
+        merchant: Address,
         amount: i128,
-    ) -> Result<(), Error> {
-        subscription::do_deposit_funds(&env, subscription_id, subscriber, amount)
-    }
-
-    /// Cancel the subscription. Allowed from Active, Paused, or InsufficientBalance.
-    /// Transitions to the terminal `Cancelled` state.
-    pub fn cancel_subscription(
-        env: Env,
-        subscription_id: u32,
-        authorizer: Address,
-    ) -> Result<(), Error> {
-        subscription::do_cancel_subscription(&env, subscription_id, authorizer)
+        interval_seconds: u64,
+        usage_enabled: bool,
+        metadata: Bytes,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_create_subscription_with_meta(
+            &env,
+            subscriber,
+            merchant,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            metadata,
+        )
     }
 
-    /// Subscriber withdraws their remaining prepaid_balance after cancellation.
-    pub fn withdraw_subscriber_funds(
+    /// Create a subscription and deposit `deposit` into it atomically. Returns the new id
+    /// only if both steps succeed; a failed deposit leaves no subscription behind.
+    pub fn create_and_fund(
         env: Env,
-        subscription_id: u32,
         subscriber: Address,
-    ) -> Result<(), Error> {
-        subscription::do_withdraw_subscriber_funds(&env, subscription_id, subscriber)
-    }
-
-    /// Pause subscription (no charges until resumed). Allowed from Active.
-    pub fn pause_subscription(
-        env: Env,
-        subscription_id: u32,
-        authorizer: Address,
-    ) -> Result<(), Error> {
-        subscription::do_pause_subscription(&env, subscription_id, authorizer)
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        deposit: i128,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_create_and_fund(
+            &env,
+            subscriber,
+            merchant,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            deposit,
+        )
     }
 
-    /// Resume a subscription to Active. Allowed from Paused or InsufficientBalance.
-    pub fn resume_subscription(
+    /// Replace a subscription's metadata blob. Subscriber or merchant auth required.
+    pub fn set_subscription_metadata(
         env: Env,
         subscription_id: u32,
         authorizer: Address,
+        data: Bytes,
     ) -> Result<(), Error> {
-        subscription::do_resume_subscription(&env, subscription_id, authorizer)
+        admin::require_current_storage(&env)?;
+        subscription::do_set_subscription_metadata(&env, subscription_id, authorizer, data)
     }
 
-    // ── Charging ─────────────────────────────────────────────────────────
-
-    /// Charge a subscription for one billing interval.
+    /// Create a subscription that only becomes chargeable at `start_timestamp`
+    /// (e.g. a future activation date). The first charge is due at the start time.
     ///
-    /// This function attempts to charge the subscriber's prepaid balance for the
-    /// recurring subscription fee. It enforces:
+    /// Charges before then fail with `NotStarted`; a start time in the past is rejected
+    /// with `InvalidInput`.
+    pub fn create_subscription_starting(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        start_timestamp: u64,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_create_subscription_starting(
+            &env,
+            subscriber,
+            merchant,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            start_timestamp,
+        )
+    }
+
+    /// Deferred start time set by `create_subscription_starting`, or `None` when the
+    /// subscription was chargeable from creation.
+    pub fn get_start_timestamp(env: Env, subscription_id: u32) -> Option<u64> {
+        subscription::get_start_timestamp(&env, subscription_id)
+    }
+
+    /// Create a subscription idempotently, keyed by an integrator-supplied `reference`.
+    ///
+    /// Repeating the call with the same `reference` returns the originally created ID
+    /// instead of creating a duplicate, so creation can be safely retried.
+    pub fn create_subscription_with_ref(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        reference: BytesN<32>,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_create_subscription_with_ref(
+            &env,
+            subscriber,
+            merchant,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            reference,
+        )
+    }
+
+    /// Subscriber deposits more USDC into their prepaid vault.
+    ///
+    /// Rejects deposits below the configured minimum threshold. The optional `memo`
+    /// is echoed in the `deposited` event for off-chain reconciliation and is not stored.
+    pub fn deposit_funds(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+        amount: i128,
+        memo: Option<BytesN<32>>,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_deposit_funds(&env, subscription_id, subscriber, amount, memo)
+    }
+
+    /// A sponsor tops up someone else's subscription, paying from their own balance.
+    ///
+    /// Emits `gift_deposit` with a `GiftDepositEvent` recording the sponsor. The
+    /// subscriber is unchanged and any refund still goes to the subscriber. Unlike
+    /// `deposit_funds`, a gift is accepted while deposits are restricted to owners.
+    pub fn gift_deposit(
+        env: Env,
+        subscription_id: u32,
+        sponsor: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_gift_deposit(&env, subscription_id, sponsor, amount)
+    }
+
+    /// Cancel the subscription. Allowed from Active, Paused, or InsufficientBalance.
+    /// Transitions to the terminal `Cancelled` state.
+    ///
+    /// Recorded with `CancellationReason::UserRequested`; use
+    /// [`Self::cancel_subscription_with_reason`] to give another reason.
+    pub fn cancel_subscription(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_cancel_subscription(
+            &env,
+            subscription_id,
+            authorizer,
+            CancellationReason::UserRequested,
+        )
+    }
+
+    /// Same as [`Self::cancel_subscription`], with `reason` carried in the
+    /// `SubscriptionCancelledEvent` for churn analytics.
+    pub fn cancel_subscription_with_reason(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+        reason: CancellationReason,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_cancel_subscription(&env, subscription_id, authorizer, reason)
+    }
+
+    /// Whether partial charging is enabled for the subscription.
+    pub fn get_allow_partial_charge(env: Env, subscription_id: u32) -> bool {
+        subscription::allows_partial_charge(&env, subscription_id)
+    }
+
+    /// Enable or disable partial charging. Merchant auth required.
+    ///
+    /// When enabled, an interval charge that finds a non-zero balance below `amount`
+    /// collects the remaining balance, records the shortfall as arrears, and moves the
+    /// subscription to `GracePeriod` rather than failing.
+    pub fn set_allow_partial_charge(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        allow: bool,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_set_allow_partial_charge(&env, subscription_id, merchant, allow)
+    }
+
+    /// Switch a usage-enabled subscription to post-paid billing, or back. Merchant auth
+    /// required; `UsageNotEnabled` if usage billing is off.
+    ///
+    /// A post-paid interval charge still waits for the interval to complete, then bills
+    /// the usage recorded with `record_usage` during that period (clearing it) instead of
+    /// the flat `amount`. The prepaid balance remains the funding source.
+    pub fn set_postpaid(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        postpaid: bool,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_set_postpaid(&env, subscription_id, merchant, postpaid)
+    }
+
+    /// Whether a subscription is billed post-paid.
+    pub fn is_postpaid(env: Env, subscription_id: u32) -> bool {
+        subscription::is_postpaid(&env, subscription_id)
+    }
+
+    /// Merchant pins billing to a fixed schedule (e.g. every Monday): charges become due
+    /// at `anchor + k * interval_seconds` rather than drifting from the last payment.
+    /// Merchant auth required.
+    pub fn set_billing_anchor(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        anchor: u64,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_set_billing_anchor(&env, subscription_id, merchant, anchor)
+    }
+
+    /// Merchant switches between `Interval` billing and `MonthlyCalendar` billing, which
+    /// charges on the same day each calendar month (clamped to the last day of shorter
+    /// months) instead of every `interval_seconds`. Merchant auth required.
+    pub fn set_billing_mode(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        mode: BillingMode,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_set_billing_mode(&env, subscription_id, merchant, mode)
+    }
+
+    /// The subscription's billing mode.
+    pub fn get_billing_mode(env: Env, subscription_id: u32) -> BillingMode {
+        subscription::get_billing_mode(&env, subscription_id)
+    }
+
+    /// The subscription's billing anchor, if one is set.
+    pub fn get_billing_anchor(env: Env, subscription_id: u32) -> Option<u64> {
+        queries::get_billing_anchor(&env, subscription_id)
+    }
+
+    /// Merchant sets a cancellation fee in basis points (max 10 000) kept from the
+    /// remaining prepaid balance when the subscriber cancels; the rest stays refundable.
+    /// Merchant auth required.
+    pub fn set_cancellation_fee_bps(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        bps: u32,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_set_cancellation_fee_bps(&env, subscription_id, merchant, bps)
+    }
+
+    /// Cancellation fee in basis points for a subscription (0 if unset).
+    pub fn get_cancellation_fee_bps(env: Env, subscription_id: u32) -> u32 {
+        subscription::get_cancellation_fee_bps(&env, subscription_id)
+    }
+
+    /// Merchant proposes a new per-interval amount. Merchant auth required.
+    ///
+    /// The change only takes effect once the subscriber calls `accept_amount_change`;
+    /// charges use the current amount until then.
+    pub fn propose_amount_change(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        new_amount: i128,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_propose_amount_change(&env, subscription_id, merchant, new_amount)
+    }
+
+    /// Subscriber accepts the pending amount change. Subscriber auth required.
+    pub fn accept_amount_change(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_accept_amount_change(&env, subscription_id, subscriber)
+    }
+
+    /// Subscriber rejects and clears the pending amount change. Subscriber auth required.
+    pub fn reject_amount_change(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_reject_amount_change(&env, subscription_id, subscriber)
+    }
+
+    /// Pending merchant-proposed amount awaiting subscriber acceptance, if any.
+    pub fn get_pending_amount_change(env: Env, subscription_id: u32) -> Option<i128> {
+        subscription::get_pending_amount_change(&env, subscription_id)
+    }
+
+    /// Tag a subscription with a reporting category (e.g. `pro`). Merchant auth required.
+    pub fn set_subscription_category(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        category: Symbol,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_set_subscription_category(&env, subscription_id, merchant, category)
+    }
+
+    /// Address set with `set_refund_address`, or `None` when refunds go to the subscriber.
+    pub fn get_refund_address(env: Env, subscription_id: u32) -> Option<Address> {
+        subscription::get_refund_address(&env, subscription_id)
+    }
+
+    /// Route prepaid refunds to `refund_address` instead of the subscriber. Subscriber auth required.
+    pub fn set_refund_address(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+        refund_address: Address,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_set_refund_address(&env, subscription_id, subscriber, refund_address)
+    }
+
+    /// Subscriber withdraws their remaining prepaid_balance after cancellation.
+    ///
+    /// The refund goes to the subscription's `refund_address` when one is set.
+    pub fn withdraw_subscriber_funds(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_withdraw_subscriber_funds(&env, subscription_id, subscriber)
+    }
+
+    /// While the contract is paused, the subscriber withdraws the full prepaid balance
+    /// regardless of status; the subscription is cancelled. `Forbidden` when not paused.
+    ///
+    /// Unlike other mutating entrypoints this stays available while a migration is
+    /// pending, for records already in the current layout. A record the migration has
+    /// not rewritten yet from a version 1 to 4 layout gets `MigrationRequired`.
+    pub fn emergency_withdraw(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+    ) -> Result<(), Error> {
+        admin::require_initialized(&env)?;
+        subscription::do_emergency_withdraw(&env, subscription_id, subscriber)
+    }
+
+    /// Pause subscription (no charges until resumed). Allowed from Active.
+    ///
+    /// `reason` (e.g. `vacation`, `dunning`) is kept until resume and included in the
+    /// `paused` event so support can tell voluntary pauses from collection pauses.
+    pub fn pause_subscription(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+        reason: Option<Symbol>,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_pause_subscription(&env, subscription_id, authorizer, reason)
+    }
+
+    /// Reason given for the subscription's current pause, if any.
+    pub fn get_pause_reason(env: Env, subscription_id: u32) -> Option<Symbol> {
+        subscription::get_pause_reason(&env, subscription_id)
+    }
+
+    /// Seconds paused since the last interval charge; the next due time is pushed back
+    /// by this much and it resets on each interval charge.
+    pub fn get_paused_seconds(env: Env, subscription_id: u32) -> u64 {
+        subscription::get_paused_seconds(&env, subscription_id)
+    }
+
+    /// Resume a subscription to Active. Allowed from Paused or InsufficientBalance.
+    pub fn resume_subscription(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_resume_subscription(&env, subscription_id, authorizer)
+    }
+
+    // ── Plan templates ───────────────────────────────────────────────────
+
+    /// Publish a plan template with custom terms. Merchant auth required.
+    ///
+    /// `amount` must be positive (`InvalidAmount`); `interval_seconds` must be non-zero
+    /// and at most five years (`InvalidInput`).
+    pub fn create_plan_template(
+        env: Env,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        plan::do_create_plan_template(&env, merchant, amount, interval_seconds, usage_enabled)
+    }
+
+    /// Publish a plan billed every 7 days.
+    pub fn create_weekly_plan(
+        env: Env,
+        merchant: Address,
+        amount: i128,
+        usage_enabled: bool,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        plan::do_create_weekly_plan(&env, merchant, amount, usage_enabled)
+    }
+
+    /// Publish a plan billed every 30 days.
+    pub fn create_monthly_plan(
+        env: Env,
+        merchant: Address,
+        amount: i128,
+        usage_enabled: bool,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        plan::do_create_monthly_plan(&env, merchant, amount, usage_enabled)
+    }
+
+    /// Publish a plan billed every 365 days.
+    pub fn create_annual_plan(
+        env: Env,
+        merchant: Address,
+        amount: i128,
+        usage_enabled: bool,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        plan::do_create_annual_plan(&env, merchant, amount, usage_enabled)
+    }
+
+    /// Deactivate a plan template so it accepts no new subscriptions.
+    ///
+    /// Merchant auth required. Subscriptions already created from the plan keep running.
+    pub fn deactivate_plan_template(
+        env: Env,
+        merchant: Address,
+        plan_id: u32,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        plan::do_deactivate_plan_template(&env, merchant, plan_id)
+    }
+
+    /// Read a plan template by id.
+    pub fn get_plan_template(env: Env, plan_id: u32) -> Result<PlanTemplate, Error> {
+        plan::get_plan_template(&env, plan_id)
+    }
+
+    /// List the plan template IDs created by a merchant.
+    pub fn get_merchant_plans(env: Env, merchant: Address) -> Vec<u32> {
+        plan::get_merchant_plans(&env, merchant)
+    }
+
+    /// List the plan templates created by a merchant.
+    pub fn get_merchant_plan_templates(env: Env, merchant: Address) -> Vec<PlanTemplate> {
+        plan::get_merchant_plan_templates(&env, merchant)
+    }
+
+    /// Browse plan templates across all merchants: `(plan_id, template)` pairs for IDs
+    /// `start_plan_id..start_plan_id + limit` (capped at `MAX_SCAN_LIMIT`).
+    pub fn get_plans_page(env: Env, start_plan_id: u32, limit: u32) -> Vec<(u32, PlanTemplate)> {
+        plan::get_plans_page(&env, start_plan_id, limit)
+    }
+
+    /// Create a subscription using a plan template's terms. Subscriber auth required.
+    pub fn create_subscription_from_plan(
+        env: Env,
+        subscriber: Address,
+        plan_id: u32,
+    ) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        plan::do_create_subscription_from_plan(&env, subscriber, plan_id)
+    }
+
+    /// Move a subscription to another of its merchant's plans. The plan's amount,
+    /// interval and usage flag apply from the next interval charge. Subscriber auth
+    /// required.
+    pub fn switch_plan(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+        new_plan_id: u32,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        plan::do_switch_plan(&env, subscription_id, subscriber, new_plan_id)
+    }
+
+    /// Plan switch scheduled by `switch_plan` that has not yet taken effect.
+    pub fn get_pending_plan(env: Env, subscription_id: u32) -> Option<u32> {
+        plan::get_pending_plan(&env, subscription_id)
+    }
+
+    /// **ADMIN ONLY**: Set the plan used by `subscribe_default`, for integrations that
+    /// offer a single tier.
+    pub fn set_default_plan(env: Env, admin: Address, plan_id: u32) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        plan::do_set_default_plan(&env, admin, plan_id)
+    }
+
+    /// The default plan ID, if the admin has configured one.
+    pub fn get_default_plan(env: Env) -> Option<u32> {
+        plan::get_default_plan(&env)
+    }
+
+    /// Subscribe to the default plan in one call. Subscriber auth required.
+    /// Returns `NotFound` when no default plan is configured.
+    pub fn subscribe_default(env: Env, subscriber: Address) -> Result<u32, Error> {
+        admin::require_current_storage(&env)?;
+        plan::do_subscribe_default(&env, subscriber)
+    }
+
+    /// Create one subscription per subscriber from a plan template, returning the new
+    /// IDs in order. Each subscriber must authorize; duplicates are not allowed.
+    pub fn batch_create_from_plan(
+        env: Env,
+        subscribers: Vec<Address>,
+        plan_id: u32,
+    ) -> Result<Vec<u32>, Error> {
+        admin::require_current_storage(&env)?;
+        plan::do_batch_create_from_plan(&env, subscribers, plan_id)
+    }
+
+    // ── Charging ─────────────────────────────────────────────────────────
+
+    /// Charge a subscription for one billing interval.
+    ///
+    /// This function attempts to charge the subscriber's prepaid balance for the
+    /// recurring subscription fee. It enforces:
     /// - The subscription must be in `Active` status
     /// - The billing interval must have elapsed since the last charge
     /// - The prepaid balance must be sufficient to cover the charge amount
@@ -374,7 +1271,33 @@ impl SubscriptionVault {
     /// The function uses early validation to avoid unnecessary state modifications.
     /// Balance check is performed before any state changes.
     pub fn charge_subscription(env: Env, subscription_id: u32) -> Result<(), Error> {
-        charge_core::charge_one(&env, subscription_id, env.ledger().timestamp(), None)
+        admin::require_current_storage(&env)?;
+        subscription::do_charge_subscription(&env, subscription_id, None)
+    }
+
+    /// Same as [`charge_subscription`](Self::charge_subscription), but fails with
+    /// `InvalidAmount` (charging nothing) unless the amount to be debited equals
+    /// `expected_amount`. Lets automated chargers guard against a price that changed
+    /// after they read it. `None` behaves exactly like `charge_subscription`.
+    pub fn charge_subscription_expected(
+        env: Env,
+        subscription_id: u32,
+        expected_amount: Option<i128>,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_charge_subscription(&env, subscription_id, expected_amount)
+    }
+
+    /// Same as [`charge_subscription`](Self::charge_subscription), but returns the
+    /// recomputed [`NextChargeInfo`] so the caller can schedule the following charge
+    /// without a second query.
+    pub fn charge_subscription_detailed(
+        env: Env,
+        subscription_id: u32,
+    ) -> Result<NextChargeInfo, Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_charge_subscription(&env, subscription_id, None)?;
+        queries::get_next_charge_info(&env, subscription_id)
     }
 
     /// Charge a metered usage amount against the subscription's prepaid balance.
@@ -405,18 +1328,202 @@ impl SubscriptionVault {
     /// | `NotActive` | Subscription is not in the `Active` state. |
     /// | `UsageNotEnabled` | `usage_enabled` is flag is set to `false`. |
     /// | `InvalidAmount` | `usage_amount` is zero or negative. |
+    /// | `Overflow` | `usage_amount * usage_rate` overflows. |
     /// | `InsufficientPrepaidBalance` | Prepaid balance in the vault cannot cover the debit. |
     pub fn charge_usage(env: Env, subscription_id: u32, usage_amount: i128) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
         charge_core::charge_usage_one(&env, subscription_id, usage_amount)
     }
 
+    /// Merchant sets the token units charged per usage unit by `charge_usage`.
+    /// Defaults to 1, so `usage_amount` is debited as-is.
+    pub fn set_usage_rate(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        rate: i128,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_set_usage_rate(&env, subscription_id, merchant, rate)
+    }
+
+    /// Token units per usage unit for a subscription (1 if unset).
+    pub fn get_usage_rate(env: Env, subscription_id: u32) -> i128 {
+        subscription::get_usage_rate(&env, subscription_id)
+    }
+
+    /// Merchant sets the usage rate given to subscriptions created from (or switched
+    /// to) `plan_id`.
+    pub fn set_plan_usage_rate(
+        env: Env,
+        merchant: Address,
+        plan_id: u32,
+        rate: i128,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        plan::do_set_plan_usage_rate(&env, merchant, plan_id, rate)
+    }
+
+    /// Usage rate for subscriptions created from `plan_id` (1 if unset).
+    pub fn get_plan_usage_rate(env: Env, plan_id: u32) -> i128 {
+        plan::get_plan_usage_rate(&env, plan_id)
+    }
+
+    /// Merchant sets a cancellation notice period for subscriptions created from (or
+    /// switched to) `plan_id`: cancelling them takes effect `seconds` later, and charges
+    /// continue until then. 0 removes the notice.
+    pub fn set_plan_cancellation_notice(
+        env: Env,
+        merchant: Address,
+        plan_id: u32,
+        seconds: u64,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        plan::do_set_plan_cancellation_notice(&env, merchant, plan_id, seconds)
+    }
+
+    /// Cancellation notice period in seconds for `plan_id` (0 if unset).
+    pub fn get_plan_cancellation_notice(env: Env, plan_id: u32) -> u64 {
+        plan::get_plan_cancellation_notice(&env, plan_id)
+    }
+
+    /// When a cancellation requested under a notice period takes effect, if one is
+    /// pending. Calling `cancel_subscription` at or after that time completes it.
+    pub fn get_scheduled_cancellation(env: Env, subscription_id: u32) -> Option<u64> {
+        subscription::get_scheduled_cancellation(&env, subscription_id)
+    }
+
+    /// Enable or disable interval and usage charges independently (subscriber or
+    /// merchant auth). A disabled charge type returns `NotActive` while the other keeps
+    /// working, unlike `pause_subscription` which stops both.
+    pub fn set_charge_flags(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+        interval_enabled: bool,
+        usage_enabled: bool,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_set_charge_flags(
+            &env,
+            subscription_id,
+            authorizer,
+            interval_enabled,
+            usage_enabled,
+        )
+    }
+
+    /// `(interval_charges_enabled, usage_charges_enabled)` for a subscription.
+    pub fn get_charge_flags(env: Env, subscription_id: u32) -> (bool, bool) {
+        (
+            subscription::interval_charges_enabled(&env, subscription_id),
+            subscription::usage_charges_enabled(&env, subscription_id),
+        )
+    }
+
+    /// Merchant confirms a pending subscription so it can be charged (merchant auth;
+    /// `Unauthorized` for any other address). Emits `sub_confirmed` the first time.
+    pub fn confirm_subscription(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_confirm_subscription(&env, subscription_id, merchant)
+    }
+
+    /// Whether a subscription still awaits merchant confirmation.
+    pub fn is_pending_merchant(env: Env, subscription_id: u32) -> bool {
+        subscription::is_pending_merchant(&env, subscription_id)
+    }
+
+    /// Subscriber enables automatic top-up (`amount == 0` disables it).
+    ///
+    /// After an interval charge leaves the balance below `threshold`, the vault pulls
+    /// `amount` from the subscriber via `transfer_from`, so the subscriber must first
+    /// `approve` the vault as spender. If the pull fails, the charge still succeeds.
+    pub fn set_auto_topup(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+        amount: i128,
+        threshold: i128,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        subscription::do_set_auto_topup(&env, subscription_id, subscriber, amount, threshold)
+    }
+
+    pub fn get_auto_topup(env: Env, subscription_id: u32) -> Option<AutoTopupConfig> {
+        subscription::get_auto_topup(&env, subscription_id)
+    }
+
+    /// Merchant records metered usage owed without debiting the balance yet.
+    ///
+    /// Pending usage is settled to the merchant, as far as the balance allows,
+    /// when the subscription is cancelled and before the remainder is refundable.
+    pub fn record_usage(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        usage_amount: i128,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        charge_core::record_usage(&env, subscription_id, merchant, usage_amount)
+    }
+
+    /// Usage recorded but not yet settled (0 if none).
+    pub fn get_pending_usage(env: Env, subscription_id: u32) -> i128 {
+        charge_core::get_pending_usage(&env, subscription_id)
+    }
+
     // ── Merchant ─────────────────────────────────────────────────────────
 
-    /// Merchant withdraws accumulated USDC to their wallet.
+    /// Merchant withdraws accumulated USDC to their payout address (their own wallet
+    /// unless one was set with `set_payout_address`). Fails with `PayoutFailed`, keeping
+    /// the balance, if the token transfer fails.
     pub fn withdraw_merchant_funds(env: Env, merchant: Address, amount: i128) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
         merchant::withdraw_merchant_funds(&env, merchant, amount)
     }
 
+    /// Send future withdrawals for `merchant` to `payout_address`. Merchant auth required.
+    pub fn set_payout_address(
+        env: Env,
+        merchant: Address,
+        payout_address: Address,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        merchant::do_set_payout_address(&env, merchant, payout_address)
+    }
+
+    /// Accrued amount the merchant can withdraw right now (0 if nothing accrued). Unlike
+    /// lifetime revenue, this drops with each withdrawal and refund.
+    pub fn get_merchant_balance(env: Env, merchant: Address) -> i128 {
+        merchant::get_merchant_balance(&env, &merchant)
+    }
+
+    /// Timestamp of the merchant's last withdrawal (0 if none).
+    pub fn get_last_withdrawal(env: Env, merchant: Address) -> u64 {
+        merchant::get_last_withdrawal(&env, &merchant)
+    }
+
+    /// Address that receives the merchant's withdrawals.
+    pub fn get_payout_address(env: Env, merchant: Address) -> Address {
+        merchant::get_payout_address(&env, &merchant)
+    }
+
+    /// Merchant refunds `amount` of a subscription's accrued earnings back into its
+    /// prepaid balance (e.g. for a disputed charge). Merchant auth required.
+    pub fn merchant_refund(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        merchant::do_merchant_refund(&env, subscription_id, merchant, amount)
+    }
+
     // ── Queries ──────────────────────────────────────────────────────────
 
     /// Read subscription by id.
@@ -424,6 +1531,52 @@ impl SubscriptionVault {
         queries::get_subscription(&env, subscription_id)
     }
 
+    /// Statuses for many subscriptions in one call, as `(id, status)` pairs in input
+    /// order. Missing IDs are skipped and at most `MAX_BATCH_SIZE` IDs are read.
+    pub fn get_statuses(env: Env, ids: Vec<u32>) -> Vec<(u32, SubscriptionStatus)> {
+        queries::get_statuses(&env, ids)
+    }
+
+    /// Check many status transitions at once; returns one flag per `(from, to)` pair
+    /// using the same rules as `can_transition`. Pure; reads no storage.
+    pub fn validate_transitions(
+        env: Env,
+        pairs: Vec<(SubscriptionStatus, SubscriptionStatus)>,
+    ) -> Vec<bool> {
+        state_machine::validate_transitions(&env, pairs)
+    }
+
+    /// O(1) count of subscriptions that have not reached `Cancelled`.
+    pub fn get_active_count(env: Env) -> u32 {
+        queries::get_active_count(&env)
+    }
+
+    /// Total subscriptions ever created, including cancelled ones.
+    pub fn get_total_created(env: Env) -> u32 {
+        queries::get_total_created(&env)
+    }
+
+    /// Cheap existence check for UIs and indexers; does not return the record.
+    pub fn subscription_exists(env: Env, subscription_id: u32) -> bool {
+        queries::subscription_exists(&env, subscription_id)
+    }
+
+    /// Self-describing view of one subscription: the stored record plus its ID.
+    pub fn get_subscription_summary(
+        env: Env,
+        subscription_id: u32,
+    ) -> Result<SubscriptionSummary, Error> {
+        queries::get_subscription_summary(&env, subscription_id)
+    }
+
+    /// How many full charges the current balance covers ("funded through N cycles").
+    ///
+    /// Floor division of the balance (net of arrears) by `amount`; 0 for usage-enabled
+    /// subscriptions.
+    pub fn remaining_intervals(env: Env, subscription_id: u32) -> Result<u32, Error> {
+        queries::remaining_intervals(&env, subscription_id)
+    }
+
     /// Estimate how much a subscriber needs to deposit to cover N future intervals.
     pub fn estimate_topup_for_intervals(
         env: Env,
@@ -433,10 +1586,87 @@ impl SubscriptionVault {
         queries::estimate_topup_for_intervals(&env, subscription_id, num_intervals)
     }
 
-    /// Get estimated next charge info (timestamp + whether charge is expected).
+    /// Whether the subscription is `Active` and its interval has elapsed (pure view).
+    ///
+    /// Does not check the balance. Returns `NotFound` for unknown ids.
+    pub fn is_charge_due(env: Env, subscription_id: u32) -> Result<bool, Error> {
+        queries::is_charge_due(&env, subscription_id)
+    }
+
+    /// Seconds until the next interval charge is due; 0 once it is due (pure view).
+    pub fn seconds_until_chargeable(env: Env, subscription_id: u32) -> Result<u64, Error> {
+        queries::seconds_until_chargeable(&env, subscription_id)
+    }
+
+    /// Predict whether the next charge would succeed right now (pure view).
+    ///
+    /// `None` if a charge now would go through. Otherwise the detail (available and
+    /// required amounts) of the `Err` from the query of the same name; a contract error
+    /// can only carry a code, so the detail is returned as a value instead.
+    pub fn will_next_charge_succeed(
+        env: Env,
+        subscription_id: u32,
+    ) -> Option<InsufficientBalanceError> {
+        queries::will_next_charge_succeed(&env, subscription_id).err()
+    }
+
+    /// Outstanding arrears left by partial charges (0 if none).
+    ///
+    /// Arrears are collected ahead of the interval amount on the next charge.
+    pub fn get_arrears(env: Env, subscription_id: u32) -> i128 {
+        charge_core::get_arrears(&env, subscription_id)
+    }
+
+    /// Preview an interval charge at the current ledger time without executing it.
+    ///
+    /// Returns `(amount, last_payment_timestamp)` that `charge_subscription` would
+    /// produce now, or the error it would return (e.g. `IntervalNotElapsed`, `NotActive`,
+    /// `InsufficientBalance`). Read-only: no state changes, events or transfers.
+    pub fn preview_charge(env: Env, subscription_id: u32) -> Result<(i128, u64), Error> {
+        charge_core::simulate_charge_one(&env, subscription_id, env.ledger().timestamp())
+    }
+
+    /// Get estimated next charge info (timestamp, whether a charge is expected and the
+    /// grace deadline, which honours the subscription's grace override).
     pub fn get_next_charge_info(env: Env, subscription_id: u32) -> Result<NextChargeInfo, Error> {
-        let sub = queries::get_subscription(&env, subscription_id)?;
-        Ok(compute_next_charge_info(&sub))
+        queries::get_next_charge_info(&env, subscription_id)
+    }
+
+    /// Preview the next charge for a schedule that is not stored, using the same math
+    /// as `get_next_charge_info` (without billing anchors or accumulated pause time).
+    pub fn preview_next_charge(
+        env: Env,
+        last_payment: u64,
+        interval: u64,
+        status: SubscriptionStatus,
+    ) -> NextChargeInfo {
+        queries::preview_next_charge(&env, last_payment, interval, status)
+    }
+
+    /// Audit check over subscription IDs `start_id..start_id + limit` (capped at
+    /// `MAX_SCAN_LIMIT`). Returns `InvariantViolation` on the first corrupt record or
+    /// negative balance.
+    pub fn verify_invariants(env: Env, start_id: u32, limit: u32) -> Result<(), Error> {
+        queries::verify_invariants(&env, start_id, limit)
+    }
+
+    /// Count subscriptions tagged `category` among IDs `start_id..start_id + limit`
+    /// (capped at `MAX_SCAN_LIMIT`).
+    pub fn count_by_category(env: Env, category: Symbol, start_id: u32, limit: u32) -> u32 {
+        queries::count_by_category(&env, category, start_id, limit)
+    }
+
+    /// Return IDs of `Active` subscriptions due within the next `window_seconds`.
+    ///
+    /// Scans at most `MAX_SCAN_LIMIT` IDs starting at `start_id`.
+    pub fn get_due_within(env: Env, window_seconds: u64, start_id: u32, limit: u32) -> Vec<u32> {
+        queries::get_due_within(&env, window_seconds, start_id, limit)
+    }
+
+    /// Return IDs of subscriptions in `InsufficientBalance` or `GracePeriod` among IDs
+    /// `start_id..start_id + limit` (capped at `MAX_SCAN_LIMIT`).
+    pub fn get_delinquent(env: Env, start_id: u32, limit: u32) -> Vec<u32> {
+        queries::get_delinquent(&env, start_id, limit)
     }
 
     /// Return subscriptions for a merchant, paginated.
@@ -454,6 +1684,71 @@ impl SubscriptionVault {
         queries::get_merchant_subscription_count(&env, merchant)
     }
 
+    /// A merchant's key figures in one call: active and total subscriptions, withdrawable
+    /// balance and lifetime revenue. Active subscriptions are counted over positions
+    /// `start..start + limit` of the merchant's index (capped at `MAX_SCAN_LIMIT`).
+    pub fn get_merchant_dashboard(
+        env: Env,
+        merchant: Address,
+        start: u32,
+        limit: u32,
+    ) -> MerchantDashboard {
+        queries::get_merchant_dashboard(&env, merchant, start, limit)
+    }
+
+    /// Total recorded-but-unbilled usage across positions `start..start + limit` of the
+    /// merchant's index (capped at `MAX_SCAN_LIMIT`).
+    pub fn get_merchant_pending_usage(env: Env, merchant: Address, start: u32, limit: u32) -> i128 {
+        queries::get_merchant_pending_usage(&env, merchant, start, limit)
+    }
+
+    /// Vault-wide prepaid and merchant liabilities next to the vault's actual token
+    /// balance. Assets minus liabilities is the amount `recover_stranded_funds` could
+    /// safely move.
+    pub fn get_balance_sheet(env: Env) -> Result<BalanceSheet, Error> {
+        queries::get_balance_sheet(&env)
+    }
+
+    /// Merchant opens a maintenance window from now until `until` (`0` clears it).
+    ///
+    /// Interval charges for the merchant's subscriptions fail with `InMaintenance` until
+    /// the window ends, and their schedules shift so subscribers are not billed for the
+    /// maintenance time. A new window replaces the previous one.
+    pub fn set_maintenance_until(env: Env, merchant: Address, until: u64) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        merchant::do_set_maintenance_until(&env, merchant, until)
+    }
+
+    /// The merchant's current maintenance window, if any.
+    pub fn get_maintenance_window(env: Env, merchant: Address) -> Option<MaintenanceWindow> {
+        merchant::get_maintenance_window(&env, &merchant)
+    }
+
+    /// Split a subscription's interval charges across payees. Each `(payee, bps)` gets
+    /// `bps` basis points of every charge and the merchant keeps the remainder. Shares may
+    /// total at most 10_000 (`InvalidInput` otherwise); an empty list removes the split.
+    /// Merchant auth required.
+    pub fn set_payees(
+        env: Env,
+        subscription_id: u32,
+        merchant: Address,
+        payees: Vec<(Address, u32)>,
+    ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
+        merchant::do_set_payees(&env, subscription_id, merchant, payees)
+    }
+
+    /// A subscription's payee split table (empty when the merchant receives everything).
+    pub fn get_payees(env: Env, subscription_id: u32) -> Vec<(Address, u32)> {
+        merchant::get_payees(&env, subscription_id)
+    }
+
+    /// Gross amount a subscription has generated for its merchant over its lifetime
+    /// (interval, usage and one-off charges), for per-customer LTV. Refunds do not reduce it.
+    pub fn get_subscription_revenue(env: Env, subscription_id: u32) -> i128 {
+        merchant::get_subscription_revenue(&env, subscription_id)
+    }
+
     /// Merchant-initiated one-off charge.
     pub fn charge_one_off(
         env: Env,
@@ -461,6 +1756,7 @@ impl SubscriptionVault {
         merchant: Address,
         amount: i128,
     ) -> Result<(), Error> {
+        admin::require_current_storage(&env)?;
         subscription::do_charge_one_off(&env, subscription_id, merchant, amount)
     }
 
@@ -506,4 +1802,5 @@ impl SubscriptionVault {
 }
 
 #[cfg(test)]
+#[allow(clippy::len_zero, clippy::needless_borrow, clippy::unnecessary_cast)]
 mod test;
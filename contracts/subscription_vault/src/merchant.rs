@@ -1,15 +1,359 @@
-//! Merchant entrypoints: withdraw_merchant_funds.
+//! Merchant entrypoints: withdraw_merchant_funds, merchant_refund, and earnings accounting.
 //!
 //! **PRs that only change merchant payouts should edit this file only.**
 
-use crate::safe_math::validate_non_negative;
-use crate::types::Error;
-use soroban_sdk::{Address, Env, Symbol};
+use crate::queries::get_subscription;
+use crate::safe_math::{
+    safe_add, safe_add_balance, safe_sub, safe_sub_balance, validate_non_negative,
+};
+use crate::types::{DataKey, Error, MaintenanceWindow, MerchantRefundEvent};
+use soroban_sdk::{Address, Env, Symbol, Vec};
 
+/// Withdraws `amount` of the merchant's accrued balance to its payout address.
+///
+/// Requires merchant auth. Tokens go to the address set with `set_payout_address`,
+/// or to the merchant itself when none is configured. Rejected with `WithdrawalTooSoon`
+/// while the admin-configured cooldown since the merchant's last withdrawal is running.
 pub fn withdraw_merchant_funds(env: &Env, merchant: Address, amount: i128) -> Result<(), Error> {
     merchant.require_auth();
     validate_non_negative(amount)?;
+    if amount == 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    let now = env.ledger().timestamp();
+    let last_key = DataKey::LastWithdrawal(merchant.clone());
+    if let Some(last) = env.storage().instance().get::<_, u64>(&last_key) {
+        let cooldown = crate::admin::get_withdrawal_cooldown(env);
+        if now < last.saturating_add(cooldown) {
+            return Err(Error::WithdrawalTooSoon);
+        }
+    }
+
+    let balance = safe_sub_balance(get_merchant_balance(env, &merchant), amount)
+        .map_err(|_| Error::InsufficientBalance)?;
+    set_merchant_balance(env, &merchant, balance)?;
+    env.storage().instance().set(&last_key, &now);
+
+    let token_addr: Address = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "token"))
+        .ok_or(Error::NotInitialized)?;
+    let token_client = soroban_sdk::token::Client::new(env, &token_addr);
+    // A failed payout returns an error so the debit above is rolled back and the
+    // merchant keeps the balance.
+    if !matches!(
+        token_client.try_transfer(
+            &env.current_contract_address(),
+            &get_payout_address(env, &merchant),
+            &amount,
+        ),
+        Ok(Ok(()))
+    ) {
+        return Err(Error::PayoutFailed);
+    }
+
     env.events()
         .publish((Symbol::new(env, "withdrawn"), merchant.clone()), amount);
     Ok(())
 }
+
+/// Timestamp of the merchant's last withdrawal (0 if it has never withdrawn).
+pub fn get_last_withdrawal(env: &Env, merchant: &Address) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::LastWithdrawal(merchant.clone()))
+        .unwrap_or(0)
+}
+
+/// Returns where the merchant's withdrawals are sent: the configured payout address,
+/// or the merchant itself.
+pub fn get_payout_address(env: &Env, merchant: &Address) -> Address {
+    env.storage()
+        .instance()
+        .get(&DataKey::PayoutAddress(merchant.clone()))
+        .unwrap_or_else(|| merchant.clone())
+}
+
+/// Routes future withdrawals for `merchant` to `payout_address` (e.g. a cold wallet).
+/// Merchant auth required.
+pub fn do_set_payout_address(
+    env: &Env,
+    merchant: Address,
+    payout_address: Address,
+) -> Result<(), Error> {
+    merchant.require_auth();
+    env.storage()
+        .instance()
+        .set(&DataKey::PayoutAddress(merchant), &payout_address);
+    Ok(())
+}
+
+/// Opens a maintenance window for all of the merchant's subscriptions from now until
+/// `until`, replacing any earlier window. Interval charges fail with `InMaintenance`
+/// until then, and each subscription's next due time moves back by the part of the
+/// window that falls in its current period. `until == 0` clears the window.
+/// Merchant auth required.
+pub fn do_set_maintenance_until(env: &Env, merchant: Address, until: u64) -> Result<(), Error> {
+    merchant.require_auth();
+    let key = DataKey::MaintenanceWindow(merchant.clone());
+    let now = env.ledger().timestamp();
+    if until == 0 {
+        env.storage().instance().remove(&key);
+    } else if until <= now {
+        return Err(Error::InvalidInput);
+    } else {
+        env.storage()
+            .instance()
+            .set(&key, &MaintenanceWindow { start: now, until });
+    }
+    env.events()
+        .publish((Symbol::new(env, "maintenance"), merchant), (now, until));
+    Ok(())
+}
+
+/// The merchant's maintenance window, if one has been set.
+pub fn get_maintenance_window(env: &Env, merchant: &Address) -> Option<MaintenanceWindow> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MaintenanceWindow(merchant.clone()))
+}
+
+/// Most payees a subscription's split table may hold.
+pub const MAX_PAYEES: u32 = 5;
+
+/// Merchant sets who shares the subscription's interval charges: each `(payee, bps)`
+/// receives `bps` basis points of every charge, and the remainder stays with the merchant.
+/// The shares may total at most [`crate::subscription::MAX_FEE_BPS`]; an empty table
+/// removes the split. Payees withdraw with `withdraw_merchant_funds` like any merchant.
+pub fn do_set_payees(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    payees: Vec<(Address, u32)>,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if payees.len() > MAX_PAYEES {
+        return Err(Error::InvalidInput);
+    }
+    let mut total_bps: u32 = 0;
+    for (payee, bps) in payees.iter() {
+        if bps == 0 || payee == env.current_contract_address() {
+            return Err(Error::InvalidInput);
+        }
+        total_bps = total_bps.saturating_add(bps);
+    }
+    if total_bps > crate::subscription::MAX_FEE_BPS {
+        return Err(Error::InvalidInput);
+    }
+
+    let key = DataKey::Payees(subscription_id);
+    if payees.is_empty() {
+        env.storage().instance().remove(&key);
+    } else {
+        env.storage().instance().set(&key, &payees);
+    }
+    Ok(())
+}
+
+/// The subscription's split table (empty when all charges go to the merchant).
+pub fn get_payees(env: &Env, subscription_id: u32) -> Vec<(Address, u32)> {
+    env.storage()
+        .instance()
+        .get(&DataKey::Payees(subscription_id))
+        .unwrap_or_else(|| Vec::new(env))
+}
+
+/// `amount * bps / 10_000`, rounded down, split so it cannot overflow.
+fn bps_share(amount: i128, bps: u32) -> Result<i128, Error> {
+    let scale = crate::subscription::MAX_FEE_BPS as i128;
+    let bps = bps as i128;
+    let whole = (amount / scale).checked_mul(bps).ok_or(Error::Overflow)?;
+    Ok(whole + (amount % scale) * bps / scale)
+}
+
+/// Credits an interval charge. The platform fee (`fee_bps` of `amount`, rounded down)
+/// goes to the fee collector first; of the rest, each payee gets its basis-point share
+/// (rounded down) and the merchant gets the remainder through [`credit_merchant`]. Fee
+/// and payee shares count towards the recipient's balance and revenue; payee shares also
+/// count towards the subscription's gross revenue, but neither counts towards the
+/// merchant's refundable accrual.
+pub fn credit_charge(
+    env: &Env,
+    subscription_id: u32,
+    merchant: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    crate::subscription::adjust_total_prepaid(env, -amount)?;
+    let storage = env.storage().instance();
+    let fee = bps_share(amount, crate::admin::get_fee_bps(env))?;
+    if fee > 0 {
+        let collector = crate::admin::get_fee_collector(env)?;
+        let balance = safe_add_balance(get_merchant_balance(env, &collector), fee)?;
+        let revenue = safe_add_balance(get_merchant_revenue(env, &collector), fee)?;
+        set_merchant_balance(env, &collector, balance)?;
+        storage.set(&DataKey::MerchantRevenue(collector), &revenue);
+    }
+    let net = safe_sub_balance(amount, fee)?;
+
+    let mut paid_out: i128 = 0;
+    for (payee, bps) in get_payees(env, subscription_id).iter() {
+        let share = bps_share(net, bps)?;
+        if share == 0 {
+            continue;
+        }
+        let balance = safe_add_balance(get_merchant_balance(env, &payee), share)?;
+        let revenue = safe_add_balance(get_merchant_revenue(env, &payee), share)?;
+        set_merchant_balance(env, &payee, balance)?;
+        storage.set(&DataKey::MerchantRevenue(payee), &revenue);
+        paid_out = safe_add_balance(paid_out, share)?;
+    }
+    if paid_out > 0 {
+        let sub_revenue =
+            safe_add_balance(get_subscription_revenue(env, subscription_id), paid_out)?;
+        storage.set(&DataKey::SubscriptionRevenue(subscription_id), &sub_revenue);
+    }
+    accrue_to_merchant(
+        env,
+        subscription_id,
+        merchant,
+        safe_sub_balance(net, paid_out)?,
+    )
+}
+
+/// Returns the merchant's withdrawable accrued balance (0 if nothing accrued).
+pub fn get_merchant_balance(env: &Env, merchant: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantBalance(merchant.clone()))
+        .unwrap_or(0)
+}
+
+/// Sets a recipient's withdrawable balance, moving the running total of merchant
+/// balances by the change.
+fn set_merchant_balance(env: &Env, recipient: &Address, balance: i128) -> Result<(), Error> {
+    let delta = safe_sub(balance, get_merchant_balance(env, recipient))?;
+    let total = safe_add(get_total_merchant_balance(env), delta)?;
+    let storage = env.storage().instance();
+    storage.set(&DataKey::MerchantBalance(recipient.clone()), &balance);
+    storage.set(&Symbol::new(env, "total_merchant"), &total);
+    Ok(())
+}
+
+/// Sum of every merchant, payee and fee collector balance, kept as a running total.
+pub fn get_total_merchant_balance(env: &Env) -> i128 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "total_merchant"))
+        .unwrap_or(0)
+}
+
+/// Returns what the merchant has accrued from one subscription, net of refunds.
+pub fn get_subscription_accrued(env: &Env, subscription_id: u32) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubscriptionAccrued(subscription_id))
+        .unwrap_or(0)
+}
+
+/// Gross amount the subscription has generated for its merchant across all charges
+/// (0 if never charged). Unlike [`get_subscription_accrued`], refunds do not reduce it.
+pub fn get_subscription_revenue(env: &Env, subscription_id: u32) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::SubscriptionRevenue(subscription_id))
+        .unwrap_or(0)
+}
+
+/// Lifetime amount credited to the merchant, net of refunds (0 if none).
+pub fn get_merchant_revenue(env: &Env, merchant: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantRevenue(merchant.clone()))
+        .unwrap_or(0)
+}
+
+/// Credits `amount` collected from `subscription_id` to the merchant's accrued balance.
+///
+/// Called by every charge path after the subscriber's prepaid balance has been debited,
+/// so the amount also moves from the prepaid total to the merchant total.
+pub fn credit_merchant(
+    env: &Env,
+    subscription_id: u32,
+    merchant: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    crate::subscription::adjust_total_prepaid(env, -amount)?;
+    accrue_to_merchant(env, subscription_id, merchant, amount)
+}
+
+fn accrue_to_merchant(
+    env: &Env,
+    subscription_id: u32,
+    merchant: &Address,
+    amount: i128,
+) -> Result<(), Error> {
+    let storage = env.storage().instance();
+    let balance = safe_add_balance(get_merchant_balance(env, merchant), amount)?;
+    let accrued = safe_add_balance(get_subscription_accrued(env, subscription_id), amount)?;
+    let revenue = safe_add_balance(get_merchant_revenue(env, merchant), amount)?;
+    let sub_revenue = safe_add_balance(get_subscription_revenue(env, subscription_id), amount)?;
+    set_merchant_balance(env, merchant, balance)?;
+    storage.set(&DataKey::SubscriptionAccrued(subscription_id), &accrued);
+    storage.set(&DataKey::MerchantRevenue(merchant.clone()), &revenue);
+    storage.set(&DataKey::SubscriptionRevenue(subscription_id), &sub_revenue);
+    Ok(())
+}
+
+/// Merchant-initiated refund: moves `amount` of the merchant's accrued earnings from
+/// `subscription_id` back into that subscription's prepaid balance.
+///
+/// Requires merchant auth; the subscription's merchant must match the caller. The refund
+/// may not exceed what the merchant has accrued from this subscription (net of earlier
+/// refunds) nor the merchant's current withdrawable balance.
+pub fn do_merchant_refund(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    amount: i128,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let mut sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    let accrued = safe_sub_balance(get_subscription_accrued(env, subscription_id), amount)
+        .map_err(|_| Error::ExceedsAccruedBalance)?;
+    let balance = safe_sub_balance(get_merchant_balance(env, &merchant), amount)
+        .map_err(|_| Error::ExceedsAccruedBalance)?;
+    sub.prepaid_balance = safe_add_balance(sub.prepaid_balance, amount)?;
+    let revenue = safe_sub_balance(get_merchant_revenue(env, &merchant), amount).unwrap_or(0);
+
+    let storage = env.storage().instance();
+    storage.set(&DataKey::SubscriptionAccrued(subscription_id), &accrued);
+    set_merchant_balance(env, &merchant, balance)?;
+    storage.set(&DataKey::MerchantRevenue(merchant.clone()), &revenue);
+    storage.set(&subscription_id, &sub);
+    crate::subscription::adjust_total_prepaid(env, amount)?;
+
+    env.events().publish(
+        (Symbol::new(env, "merchant_refund"), subscription_id),
+        MerchantRefundEvent {
+            subscription_id,
+            merchant,
+            amount,
+        },
+    );
+    Ok(())
+}
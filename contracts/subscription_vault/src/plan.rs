@@ -0,0 +1,421 @@
+//! Plan templates: merchant-published subscription terms and creation from a plan.
+//!
+//! **PRs that only change plan templates should edit this file only.**
+
+use crate::queries::get_subscription;
+use crate::subscription::create_subscription_record;
+use crate::types::{DataKey, Error, PlanTemplate, Subscription, SubscriptionStatus};
+use soroban_sdk::{Address, Env, Symbol, Vec};
+
+/// Interval used by [`do_create_weekly_plan`]: 7 days.
+pub const WEEKLY_INTERVAL_SECONDS: u64 = 7 * 24 * 60 * 60;
+/// Interval used by [`do_create_monthly_plan`]: 30 days.
+pub const MONTHLY_INTERVAL_SECONDS: u64 = 30 * 24 * 60 * 60;
+/// Interval used by [`do_create_annual_plan`]: 365 days.
+pub const ANNUAL_INTERVAL_SECONDS: u64 = 365 * 24 * 60 * 60;
+/// Longest interval a plan template may use: 5 years.
+pub const MAX_PLAN_INTERVAL_SECONDS: u64 = 5 * ANNUAL_INTERVAL_SECONDS;
+
+fn next_plan_id(env: &Env) -> u32 {
+    let key = Symbol::new(env, "next_plan_id");
+    let storage = env.storage().instance();
+    let id: u32 = storage.get(&key).unwrap_or(0);
+    storage.set(&key, &(id + 1));
+    id
+}
+
+pub fn get_plan_template(env: &Env, plan_id: u32) -> Result<PlanTemplate, Error> {
+    env.storage()
+        .instance()
+        .get(&DataKey::Plan(plan_id))
+        .ok_or(Error::NotFound)
+}
+
+/// Returns the IDs of all plan templates created by `merchant`, in creation order.
+pub fn get_merchant_plans(env: &Env, merchant: Address) -> Vec<u32> {
+    env.storage()
+        .instance()
+        .get(&DataKey::MerchantPlans(merchant))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Returns all plan templates created by `merchant`, in creation order.
+pub fn get_merchant_plan_templates(env: &Env, merchant: Address) -> Vec<PlanTemplate> {
+    let mut result = Vec::new(env);
+    for plan_id in get_merchant_plans(env, merchant).iter() {
+        if let Ok(plan) = get_plan_template(env, plan_id) {
+            result.push_back(plan);
+        }
+    }
+    result
+}
+
+/// Catalog page across all merchants: `(plan_id, template)` for each stored plan among
+/// IDs `start_plan_id..start_plan_id + limit`, skipping gaps. `limit` is capped at
+/// [`crate::queries::MAX_SCAN_LIMIT`]. Deactivated plans are included.
+pub fn get_plans_page(env: &Env, start_plan_id: u32, limit: u32) -> Vec<(u32, PlanTemplate)> {
+    let next_id: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "next_plan_id"))
+        .unwrap_or(0);
+    let end_id = start_plan_id
+        .saturating_add(limit.min(crate::queries::MAX_SCAN_LIMIT))
+        .min(next_id);
+
+    let mut result = Vec::new(env);
+    let mut plan_id = start_plan_id;
+    while plan_id < end_id {
+        if let Ok(plan) = get_plan_template(env, plan_id) {
+            result.push_back((plan_id, plan));
+        }
+        plan_id += 1;
+    }
+    result
+}
+
+/// Stores a new plan template owned by `merchant` and returns its ID. Merchant auth required.
+///
+/// `amount` must be positive (`InvalidAmount`) and `interval_seconds` within
+/// `1..=MAX_PLAN_INTERVAL_SECONDS` (`InvalidInput`), so every plan yields chargeable
+/// subscriptions.
+pub fn do_create_plan_template(
+    env: &Env,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+) -> Result<u32, Error> {
+    merchant.require_auth();
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if interval_seconds == 0 || interval_seconds > MAX_PLAN_INTERVAL_SECONDS {
+        return Err(Error::InvalidInput);
+    }
+
+    let plan = PlanTemplate {
+        merchant: merchant.clone(),
+        amount,
+        interval_seconds,
+        usage_enabled,
+        active: true,
+    };
+    let plan_id = next_plan_id(env);
+    env.storage().instance().set(&DataKey::Plan(plan_id), &plan);
+
+    // Maintain merchant → plan-ID index
+    let key = DataKey::MerchantPlans(merchant.clone());
+    let mut ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
+    ids.push_back(plan_id);
+    env.storage().instance().set(&key, &ids);
+
+    env.events().publish(
+        (Symbol::new(env, "plan_created"), plan_id),
+        (merchant, amount, interval_seconds),
+    );
+    Ok(plan_id)
+}
+
+/// Marks a plan template inactive so no new subscriptions can be created from it.
+///
+/// Requires auth from the plan's merchant. Existing subscriptions keep running.
+pub fn do_deactivate_plan_template(
+    env: &Env,
+    merchant: Address,
+    plan_id: u32,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let mut plan = get_plan_template(env, plan_id)?;
+    if plan.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    plan.active = false;
+    env.storage().instance().set(&DataKey::Plan(plan_id), &plan);
+
+    env.events()
+        .publish((Symbol::new(env, "plan_deactivated"), plan_id), merchant);
+    Ok(())
+}
+
+pub fn do_create_weekly_plan(
+    env: &Env,
+    merchant: Address,
+    amount: i128,
+    usage_enabled: bool,
+) -> Result<u32, Error> {
+    do_create_plan_template(
+        env,
+        merchant,
+        amount,
+        WEEKLY_INTERVAL_SECONDS,
+        usage_enabled,
+    )
+}
+
+pub fn do_create_monthly_plan(
+    env: &Env,
+    merchant: Address,
+    amount: i128,
+    usage_enabled: bool,
+) -> Result<u32, Error> {
+    do_create_plan_template(
+        env,
+        merchant,
+        amount,
+        MONTHLY_INTERVAL_SECONDS,
+        usage_enabled,
+    )
+}
+
+pub fn do_create_annual_plan(
+    env: &Env,
+    merchant: Address,
+    amount: i128,
+    usage_enabled: bool,
+) -> Result<u32, Error> {
+    do_create_plan_template(
+        env,
+        merchant,
+        amount,
+        ANNUAL_INTERVAL_SECONDS,
+        usage_enabled,
+    )
+}
+
+/// Creates a subscription for `subscriber` using the terms of plan `plan_id`.
+/// Subscriber auth required.
+pub fn do_create_subscription_from_plan(
+    env: &Env,
+    subscriber: Address,
+    plan_id: u32,
+) -> Result<u32, Error> {
+    subscriber.require_auth();
+    let plan = get_plan_template(env, plan_id)?;
+    if !plan.active {
+        return Err(Error::PlanInactive);
+    }
+    let id = create_subscription_record(
+        env,
+        subscriber,
+        plan.merchant,
+        plan.amount,
+        plan.interval_seconds,
+        plan.usage_enabled,
+        env.ledger().timestamp(),
+    )?;
+    copy_plan_terms(env, plan_id, id);
+    Ok(id)
+}
+
+/// Merchant sets the usage rate copied onto subscriptions created from `plan_id`.
+/// `rate` must be positive. Existing subscriptions keep their rate.
+pub fn do_set_plan_usage_rate(
+    env: &Env,
+    merchant: Address,
+    plan_id: u32,
+    rate: i128,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let plan = get_plan_template(env, plan_id)?;
+    if plan.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if rate <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    env.storage()
+        .instance()
+        .set(&DataKey::PlanUsageRate(plan_id), &rate);
+    Ok(())
+}
+
+/// Token units per usage unit for subscriptions created from `plan_id` (1 if unset).
+pub fn get_plan_usage_rate(env: &Env, plan_id: u32) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::PlanUsageRate(plan_id))
+        .unwrap_or(1)
+}
+
+fn copy_plan_usage_rate(env: &Env, plan_id: u32, subscription_id: u32) {
+    env.storage().instance().set(
+        &DataKey::UsageRate(subscription_id),
+        &get_plan_usage_rate(env, plan_id),
+    );
+}
+
+/// Merchant sets the cancellation notice period for subscriptions created from (or
+/// switched to) `plan_id`. Cancelling such a subscription takes effect `seconds` later
+/// instead of immediately; 0 removes the notice. Existing subscriptions keep theirs.
+pub fn do_set_plan_cancellation_notice(
+    env: &Env,
+    merchant: Address,
+    plan_id: u32,
+    seconds: u64,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let plan = get_plan_template(env, plan_id)?;
+    if plan.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    env.storage()
+        .instance()
+        .set(&DataKey::PlanCancellationNotice(plan_id), &seconds);
+    Ok(())
+}
+
+/// Cancellation notice period in seconds for subscriptions created from `plan_id`
+/// (0 if unset).
+pub fn get_plan_cancellation_notice(env: &Env, plan_id: u32) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::PlanCancellationNotice(plan_id))
+        .unwrap_or(0)
+}
+
+fn copy_plan_terms(env: &Env, plan_id: u32, subscription_id: u32) {
+    copy_plan_usage_rate(env, plan_id, subscription_id);
+    let key = DataKey::CancellationNotice(subscription_id);
+    match get_plan_cancellation_notice(env, plan_id) {
+        0 => env.storage().instance().remove(&key),
+        notice => env.storage().instance().set(&key, &notice),
+    }
+}
+
+/// Subscriber schedules a move to plan `new_plan_id`. The plan's `amount`,
+/// `interval_seconds` and `usage_enabled` replace the subscription's at its next
+/// interval charge, so the current cycle is billed on the old terms. The plan must be
+/// active and belong to the subscription's merchant. Replaces any earlier switch.
+pub fn do_switch_plan(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    new_plan_id: u32,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.subscriber != subscriber {
+        return Err(Error::Forbidden);
+    }
+    if sub.status == SubscriptionStatus::Cancelled {
+        return Err(Error::NotActive);
+    }
+    let plan = get_plan_template(env, new_plan_id)?;
+    if !plan.active {
+        return Err(Error::PlanInactive);
+    }
+    if plan.merchant != sub.merchant {
+        return Err(Error::InvalidInput);
+    }
+
+    env.storage()
+        .instance()
+        .set(&DataKey::PendingPlan(subscription_id), &new_plan_id);
+    env.events().publish(
+        (Symbol::new(env, "plan_switch_scheduled"), subscription_id),
+        (subscriber, new_plan_id),
+    );
+    Ok(())
+}
+
+/// Plan ID scheduled by [`do_switch_plan`] and not yet applied, if any.
+pub fn get_pending_plan(env: &Env, subscription_id: u32) -> Option<u32> {
+    env.storage()
+        .instance()
+        .get(&DataKey::PendingPlan(subscription_id))
+}
+
+/// Template of the pending plan switch, if any.
+pub fn pending_plan_template(env: &Env, subscription_id: u32) -> Option<PlanTemplate> {
+    get_pending_plan(env, subscription_id).and_then(|id| get_plan_template(env, id).ok())
+}
+
+/// Applies a pending plan switch to `sub` (not stored here) and clears it. Returns
+/// whether the billing terms changed; the replay period is reset since its length
+/// depends on the interval.
+pub fn apply_pending_plan(env: &Env, subscription_id: u32, sub: &mut Subscription) -> bool {
+    let Some(plan_id) = get_pending_plan(env, subscription_id) else {
+        return false;
+    };
+    let Ok(plan) = get_plan_template(env, plan_id) else {
+        return false;
+    };
+    env.storage()
+        .instance()
+        .remove(&DataKey::PendingPlan(subscription_id));
+    copy_plan_terms(env, plan_id, subscription_id);
+    sub.amount = plan.amount;
+    sub.interval_seconds = plan.interval_seconds;
+    sub.usage_enabled = plan.usage_enabled;
+    crate::charge_core::reset_charged_period(env, subscription_id);
+    env.events().publish(
+        (Symbol::new(env, "plan_switched"), subscription_id),
+        (plan.amount, plan.interval_seconds),
+    );
+    true
+}
+
+/// Admin picks the plan used by [`do_subscribe_default`]. The plan must exist.
+pub fn do_set_default_plan(env: &Env, admin: Address, plan_id: u32) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = crate::admin::require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Forbidden);
+    }
+    get_plan_template(env, plan_id)?;
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "default_plan"), &plan_id);
+    Ok(())
+}
+
+/// The admin-configured default plan, if any.
+pub fn get_default_plan(env: &Env) -> Option<u32> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "default_plan"))
+}
+
+/// Subscribes `subscriber` to the default plan; `NotFound` if none is configured.
+pub fn do_subscribe_default(env: &Env, subscriber: Address) -> Result<u32, Error> {
+    let plan_id = get_default_plan(env).ok_or(Error::NotFound)?;
+    do_create_subscription_from_plan(env, subscriber, plan_id)
+}
+
+/// Creates one subscription from plan `plan_id` for each of `subscribers`, returning the
+/// new IDs in input order.
+///
+/// Every subscriber must authorize the call, so each one consents to the plan's terms;
+/// an address may therefore appear only once. Fails as a whole if the plan is inactive.
+pub fn do_batch_create_from_plan(
+    env: &Env,
+    subscribers: Vec<Address>,
+    plan_id: u32,
+) -> Result<Vec<u32>, Error> {
+    let plan = get_plan_template(env, plan_id)?;
+    if !plan.active {
+        return Err(Error::PlanInactive);
+    }
+
+    let mut ids = Vec::new(env);
+    for subscriber in subscribers.iter() {
+        subscriber.require_auth();
+        let id = create_subscription_record(
+            env,
+            subscriber,
+            plan.merchant.clone(),
+            plan.amount,
+            plan.interval_seconds,
+            plan.usage_enabled,
+            env.ledger().timestamp(),
+        )?;
+        copy_plan_terms(env, plan_id, id);
+        ids.push_back(id);
+    }
+    Ok(ids)
+}
@@ -2,8 +2,17 @@
 //!
 //! **PRs that only add or change read-only/query behavior should edit this file only.**
 
-use crate::types::{DataKey, Error, NextChargeInfo, Subscription, SubscriptionStatus};
-use soroban_sdk::{contracttype, Address, Env, Symbol, Vec};
+use crate::types::{
+    BalanceSheet, DataKey, Error, InsufficientBalanceError, MerchantDashboard, NextChargeInfo,
+    Subscription, SubscriptionStatus, SubscriptionSummary,
+};
+use soroban_sdk::{contracttype, Address, Env, Symbol, TryFromVal, Val, Vec};
+
+/// Maximum number of subscription IDs scanned by a single range query.
+pub const MAX_SCAN_LIMIT: u32 = 100;
+
+/// Maximum number of IDs read by a single bulk lookup such as [`get_statuses`].
+pub const MAX_BATCH_SIZE: u32 = 100;
 
 pub fn get_subscription(env: &Env, subscription_id: u32) -> Result<Subscription, Error> {
     env.storage()
@@ -12,6 +21,245 @@ pub fn get_subscription(env: &Env, subscription_id: u32) -> Result<Subscription,
         .ok_or(Error::NotFound)
 }
 
+/// Status of each existing subscription in `ids`, in input order. Missing IDs are
+/// skipped; only the first [`MAX_BATCH_SIZE`] IDs are read.
+pub fn get_statuses(env: &Env, ids: Vec<u32>) -> Vec<(u32, SubscriptionStatus)> {
+    let mut result = Vec::new(env);
+    for id in ids.iter().take(MAX_BATCH_SIZE as usize) {
+        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+            result.push_back((id, sub.status));
+        }
+    }
+    result
+}
+
+/// Number of subscriptions not yet `Cancelled`, maintained on creation and cancellation.
+pub fn get_active_count(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::TotalActive)
+        .unwrap_or(0)
+}
+
+/// Number of subscriptions ever created (the next ID to be assigned).
+pub fn get_total_created(env: &Env) -> u32 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "next_id"))
+        .unwrap_or(0)
+}
+
+/// Whether a subscription record is stored under `subscription_id`, without decoding it.
+pub fn subscription_exists(env: &Env, subscription_id: u32) -> bool {
+    env.storage().instance().has(&subscription_id)
+}
+
+/// Returns the earliest timestamp at which the next interval charge is allowed from the
+/// record alone: `last_payment_timestamp + interval_seconds`. [`next_due_for`] adds
+/// paused time and deferred starts.
+///
+/// Returns `None` on `u64` overflow.
+pub fn next_due_timestamp(sub: &Subscription) -> Option<u64> {
+    sub.last_payment_timestamp.checked_add(sub.interval_seconds)
+}
+
+/// Earliest anchored billing slot (`anchor + k * interval`, `k >= 0`) strictly after
+/// `now`, or `anchor` itself while `now` is still before it. `None` for a zero interval
+/// or on overflow.
+pub fn compute_anchored_next(anchor: u64, interval: u64, now: u64) -> Option<u64> {
+    if interval == 0 {
+        return None;
+    }
+    if now < anchor {
+        return Some(anchor);
+    }
+    let slots = (now - anchor) / interval + 1;
+    anchor.checked_add(slots.checked_mul(interval)?)
+}
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Days since 1970-01-01 to `(year, month, day)` in the proleptic Gregorian calendar.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+/// `(year, month, day)` to days since 1970-01-01; inverse of [`civil_from_days`].
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = year - u64::from(month <= 2);
+    let era = year / 400;
+    let yoe = year - era * 400;
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * shifted_month + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Day of the month (1-31, UTC) of timestamp `ts`.
+pub fn day_of_month(ts: u64) -> u32 {
+    civil_from_days(ts / SECONDS_PER_DAY).2 as u32
+}
+
+/// Calendar months since January 1970 of timestamp `ts`; one billing period per month
+/// for `MonthlyCalendar` subscriptions.
+pub fn calendar_month_index(ts: u64) -> u64 {
+    let (year, month, _) = civil_from_days(ts / SECONDS_PER_DAY);
+    (year - 1970) * 12 + (month - 1)
+}
+
+/// Day `day` of the calendar month after `ts`'s, at the same time of day, clamped to
+/// that month's last day (e.g. 31 → Feb 28/29). Saturates at `u64::MAX`.
+pub fn next_calendar_month_on(ts: u64, day: u32) -> u64 {
+    let (year, month, _) = civil_from_days(ts / SECONDS_PER_DAY);
+    let (year, month) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    let day = u64::from(day.max(1)).min(days_in_month(year, month));
+    days_from_civil(year, month, day)
+        .checked_mul(SECONDS_PER_DAY)
+        .and_then(|start| start.checked_add(ts % SECONDS_PER_DAY))
+        .unwrap_or(u64::MAX)
+}
+
+/// The same day of the following calendar month as `ts`, clamped to the month's last
+/// day. Saturates at `u64::MAX`.
+pub fn next_calendar_month(ts: u64) -> u64 {
+    next_calendar_month_on(ts, day_of_month(ts))
+}
+
+/// Billing day of a `MonthlyCalendar` subscription, or `None` in `Interval` mode.
+pub fn get_calendar_day(env: &Env, subscription_id: u32) -> Option<u32> {
+    env.storage()
+        .instance()
+        .get(&DataKey::CalendarDay(subscription_id))
+}
+
+/// The billing anchor set by the merchant, if the subscription bills on a fixed schedule.
+pub fn get_billing_anchor(env: &Env, subscription_id: u32) -> Option<u64> {
+    env.storage()
+        .instance()
+        .get(&DataKey::BillingAnchor(subscription_id))
+}
+
+/// [`next_due_timestamp`], snapped to the subscription's billing anchor when one is set.
+///
+/// Anchored subscriptions are due at the first anchor slot after the last payment (shifted
+/// by accumulated pause time), or at or after a deferred start, so late charges do not
+/// drift the schedule.
+///
+/// `MonthlyCalendar` subscriptions are due on their billing day of the month after the
+/// last payment (plus accumulated pause time); that mode takes precedence over an anchor.
+/// Any merchant maintenance since the last payment is added like pause time.
+pub fn next_due_for(env: &Env, subscription_id: u32, sub: &Subscription) -> Option<u64> {
+    let shift = crate::subscription::get_paused_seconds(env, subscription_id)
+        .checked_add(maintenance_overlap(env, sub))?;
+    // Not charged since a deferred start: the first charge is due at the start time.
+    let deferred_start = crate::subscription::get_start_timestamp(env, subscription_id)
+        .filter(|start| *start > sub.last_payment_timestamp);
+    if let Some(day) = get_calendar_day(env, subscription_id) {
+        if let Some(start) = deferred_start {
+            return Some(start);
+        }
+        return next_calendar_month_on(sub.last_payment_timestamp, day).checked_add(shift);
+    }
+    let Some(anchor) = get_billing_anchor(env, subscription_id) else {
+        if let Some(start) = deferred_start {
+            return Some(start);
+        }
+        return sub
+            .last_payment_timestamp
+            .checked_add(sub.interval_seconds)?
+            .checked_add(shift);
+    };
+    if let Some(start) = deferred_start {
+        return compute_anchored_next(anchor, sub.interval_seconds, start - 1);
+    }
+    let reference = sub.last_payment_timestamp.checked_add(shift)?;
+    compute_anchored_next(anchor, sub.interval_seconds, reference)
+}
+
+/// Seconds of the merchant's maintenance window that fall after the subscription's
+/// last payment. Billing is pushed back by this much, like accumulated pause time.
+pub fn maintenance_overlap(env: &Env, sub: &Subscription) -> u64 {
+    match crate::merchant::get_maintenance_window(env, &sub.merchant) {
+        Some(window) => window
+            .until
+            .saturating_sub(window.start.max(sub.last_payment_timestamp)),
+        None => 0,
+    }
+}
+
+/// Next charge info for a stored subscription, honouring its billing anchor.
+pub fn get_next_charge_info(env: &Env, subscription_id: u32) -> Result<NextChargeInfo, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    let mut info = compute_next_charge_info(&sub);
+    info.next_charge_timestamp = next_due_for(env, subscription_id, &sub).unwrap_or(u64::MAX);
+    // No charge is expected at or after a scheduled cancellation.
+    if crate::subscription::get_scheduled_cancellation(env, subscription_id)
+        .is_some_and(|effective_at| info.next_charge_timestamp >= effective_at)
+    {
+        info.is_charge_expected = false;
+    }
+    if info.is_charge_expected
+        && matches!(
+            sub.status,
+            SubscriptionStatus::Active | SubscriptionStatus::GracePeriod
+        )
+    {
+        info.grace_deadline = crate::charge_core::grace_deadline(env, subscription_id, &sub);
+    }
+    Ok(info)
+}
+
+/// Builds the [`SubscriptionSummary`] for a stored subscription.
+pub fn to_summary(subscription_id: u32, sub: Subscription) -> SubscriptionSummary {
+    SubscriptionSummary {
+        subscription_id,
+        subscriber: sub.subscriber,
+        merchant: sub.merchant,
+        amount: sub.amount,
+        interval_seconds: sub.interval_seconds,
+        last_payment_timestamp: sub.last_payment_timestamp,
+        status: sub.status,
+        prepaid_balance: sub.prepaid_balance,
+        usage_enabled: sub.usage_enabled,
+        charge_count: sub.charge_count,
+        deposit_count: sub.deposit_count,
+    }
+}
+
+pub fn get_subscription_summary(
+    env: &Env,
+    subscription_id: u32,
+) -> Result<SubscriptionSummary, Error> {
+    Ok(to_summary(
+        subscription_id,
+        get_subscription(env, subscription_id)?,
+    ))
+}
+
 pub fn estimate_topup_for_intervals(
     env: &Env,
     subscription_id: u32,
@@ -29,13 +277,68 @@ pub fn estimate_topup_for_intervals(
         .checked_mul(intervals_i128)
         .ok_or(Error::Overflow)?;
 
-    let topup = required
-        .checked_sub(sub.prepaid_balance)
-        .unwrap_or(0)
-        .max(0);
+    // Saturate rather than trap on extreme balances; a surplus never yields a negative top-up.
+    let topup = required.saturating_sub(sub.prepaid_balance).max(0);
     Ok(topup)
 }
 
+/// Number of full interval charges the current `prepaid_balance` covers at the current
+/// `amount` (floor division), after outstanding arrears are set aside.
+///
+/// Returns 0 for usage-enabled subscriptions, whose metered debits make a cycle count
+/// meaningless, and for a non-positive `amount`. Saturates at `u32::MAX`.
+pub fn remaining_intervals(env: &Env, subscription_id: u32) -> Result<u32, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.usage_enabled || sub.amount <= 0 {
+        return Ok(0);
+    }
+
+    let arrears = crate::charge_core::get_arrears(env, subscription_id);
+    let available = sub.prepaid_balance.saturating_sub(arrears).max(0);
+    Ok(u32::try_from(available / sub.amount).unwrap_or(u32::MAX))
+}
+
+/// Returns whether the subscription is `Active` and its next interval charge is due.
+///
+/// Balance is not considered; use [`will_next_charge_succeed`] for that.
+pub fn is_charge_due(env: &Env, subscription_id: u32) -> Result<bool, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.status != SubscriptionStatus::Active {
+        return Ok(false);
+    }
+    let next_allowed = next_due_for(env, subscription_id, &sub).ok_or(Error::Overflow)?;
+    Ok(env.ledger().timestamp() >= next_allowed)
+}
+
+/// Seconds until the next interval charge is due (0 when already due), so a scheduler
+/// can sleep precisely after an `IntervalNotElapsed` rejection. Status and balance are
+/// not considered.
+pub fn seconds_until_chargeable(env: &Env, subscription_id: u32) -> Result<u64, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    let next_allowed = next_due_for(env, subscription_id, &sub).ok_or(Error::Overflow)?;
+    Ok(next_allowed.saturating_sub(env.ledger().timestamp()))
+}
+
+/// Predicts whether the next interval charge would succeed now, without mutating state.
+///
+/// Runs [`crate::charge_core::simulate_charge_one`], so every rule of a real charge
+/// applies. `Ok(())` when the charge would go through; otherwise the
+/// [`InsufficientBalanceError`] carries the balance `available` for it and the amount
+/// `required` (both 0 for an unknown subscription).
+pub fn will_next_charge_succeed(
+    env: &Env,
+    subscription_id: u32,
+) -> Result<(), InsufficientBalanceError> {
+    let now = env.ledger().timestamp();
+    if crate::charge_core::simulate_charge_one(env, subscription_id, now).is_ok() {
+        return Ok(());
+    }
+    Err(match get_subscription(env, subscription_id) {
+        Ok(sub) => crate::charge_core::charge_requirement(env, subscription_id, &sub),
+        Err(_) => InsufficientBalanceError::new(0, 0),
+    })
+}
+
 /// Returns subscriptions for a merchant, paginated by offset.
 ///
 /// * `merchant` – the merchant address to query.
@@ -86,14 +389,203 @@ pub fn get_merchant_subscription_count(env: &Env, merchant: Address) -> u32 {
     ids.len()
 }
 
+/// Key figures for `merchant`. Active subscriptions are counted over positions
+/// `start..start + limit` of the merchant's index (`limit` capped at
+/// [`MAX_SCAN_LIMIT`]); the balance and revenue come from stored counters.
+pub fn get_merchant_dashboard(
+    env: &Env,
+    merchant: Address,
+    start: u32,
+    limit: u32,
+) -> MerchantDashboard {
+    let ids: Vec<u32> = env
+        .storage()
+        .instance()
+        .get(&DataKey::MerchantSubs(merchant.clone()))
+        .unwrap_or(Vec::new(env));
+    let end = start
+        .saturating_add(limit.min(MAX_SCAN_LIMIT))
+        .min(ids.len());
+
+    let mut active_subscriptions = 0;
+    for i in start..end {
+        let id = ids.get(i).unwrap();
+        if let Ok(sub) = get_subscription(env, id) {
+            if sub.status == SubscriptionStatus::Active {
+                active_subscriptions += 1;
+            }
+        }
+    }
+
+    MerchantDashboard {
+        active_subscriptions,
+        total_subscriptions: ids.len(),
+        withdrawable_balance: crate::merchant::get_merchant_balance(env, &merchant),
+        lifetime_revenue: crate::merchant::get_merchant_revenue(env, &merchant),
+    }
+}
+
+/// Sum of recorded-but-unbilled usage (`PendingUsage`) over positions
+/// `start..start + limit` of `merchant`'s index (`limit` capped at [`MAX_SCAN_LIMIT`]).
+pub fn get_merchant_pending_usage(env: &Env, merchant: Address, start: u32, limit: u32) -> i128 {
+    let ids: Vec<u32> = env
+        .storage()
+        .instance()
+        .get(&DataKey::MerchantSubs(merchant))
+        .unwrap_or(Vec::new(env));
+    let end = start
+        .saturating_add(limit.min(MAX_SCAN_LIMIT))
+        .min(ids.len());
+
+    let mut total: i128 = 0;
+    for i in start..end {
+        let id = ids.get(i).unwrap();
+        total = total.saturating_add(crate::charge_core::get_pending_usage(env, id));
+    }
+    total
+}
+
+/// Vault-wide liabilities against its token balance. Both liabilities are running totals
+/// kept up to date on every deposit, charge, refund and withdrawal, so no scan is needed.
+pub fn get_balance_sheet(env: &Env) -> Result<BalanceSheet, Error> {
+    let prepaid_liabilities = crate::subscription::get_total_prepaid(env);
+    let merchant_liabilities = crate::merchant::get_total_merchant_balance(env);
+
+    let token_addr: Address = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "token"))
+        .ok_or(Error::NotInitialized)?;
+    let token_balance =
+        soroban_sdk::token::Client::new(env, &token_addr).balance(&env.current_contract_address());
+
+    Ok(BalanceSheet {
+        prepaid_liabilities,
+        merchant_liabilities,
+        token_balance,
+    })
+}
+
+/// Returns IDs of `Active` subscriptions whose next charge falls within
+/// `[now, now + window_seconds]`.
+///
+/// Scans IDs `start_id..start_id + limit`; `limit` is capped at [`MAX_SCAN_LIMIT`].
+/// Overdue subscriptions (next charge before `now`) are not included.
+pub fn get_due_within(env: &Env, window_seconds: u64, start_id: u32, limit: u32) -> Vec<u32> {
+    let next_id: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "next_id"))
+        .unwrap_or(0);
+    let now = env.ledger().timestamp();
+    let window_end = now.saturating_add(window_seconds);
+    let end_id = start_id
+        .saturating_add(limit.min(MAX_SCAN_LIMIT))
+        .min(next_id);
+
+    let mut result = Vec::new(env);
+    let mut id = start_id;
+    while id < end_id {
+        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+            let due = next_due_for(env, id, &sub).unwrap_or(u64::MAX);
+            if sub.status == SubscriptionStatus::Active && due >= now && due <= window_end {
+                result.push_back(id);
+            }
+        }
+        id += 1;
+    }
+    result
+}
+
+/// Returns IDs of subscriptions in `InsufficientBalance` or `GracePeriod` among IDs
+/// `start_id..start_id + limit` (`limit` capped at [`MAX_SCAN_LIMIT`]), for dunning.
+pub fn get_delinquent(env: &Env, start_id: u32, limit: u32) -> Vec<u32> {
+    let next_id: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "next_id"))
+        .unwrap_or(0);
+    let end_id = start_id
+        .saturating_add(limit.min(MAX_SCAN_LIMIT))
+        .min(next_id);
+
+    let mut result = Vec::new(env);
+    let mut id = start_id;
+    while id < end_id {
+        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+            if sub.status == SubscriptionStatus::InsufficientBalance
+                || sub.status == SubscriptionStatus::GracePeriod
+            {
+                result.push_back(id);
+            }
+        }
+        id += 1;
+    }
+    result
+}
+
+/// Checks internal invariants over subscription IDs `start_id..start_id + limit`
+/// (`limit` capped at [`MAX_SCAN_LIMIT`]), returning `InvariantViolation` on the first
+/// record that does not decode as a `Subscription` (e.g. an out-of-range status) or
+/// has a negative `prepaid_balance`.
+///
+/// Cancelled subscriptions may still hold a balance until the subscriber withdraws it,
+/// so a non-zero cancelled balance is not treated as a violation.
+pub fn verify_invariants(env: &Env, start_id: u32, limit: u32) -> Result<(), Error> {
+    let next_id: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "next_id"))
+        .unwrap_or(0);
+    let end_id = start_id
+        .saturating_add(limit.min(MAX_SCAN_LIMIT))
+        .min(next_id);
+
+    let mut id = start_id;
+    while id < end_id {
+        if let Some(raw) = env.storage().instance().get::<u32, Val>(&id) {
+            let sub =
+                Subscription::try_from_val(env, &raw).map_err(|_| Error::InvariantViolation)?;
+            if sub.prepaid_balance < 0 {
+                return Err(Error::InvariantViolation);
+            }
+        }
+        id += 1;
+    }
+    Ok(())
+}
+
+/// Counts subscriptions tagged with `category` among IDs `start_id..start_id + limit`
+/// (`limit` capped at [`MAX_SCAN_LIMIT`]).
+pub fn count_by_category(env: &Env, category: Symbol, start_id: u32, limit: u32) -> u32 {
+    let next_id: u32 = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "next_id"))
+        .unwrap_or(0);
+    let end_id = start_id
+        .saturating_add(limit.min(MAX_SCAN_LIMIT))
+        .min(next_id);
+
+    let mut count = 0;
+    let mut id = start_id;
+    while id < end_id {
+        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+            if sub.category.as_ref() == Some(&category) {
+                count += 1;
+            }
+        }
+        id += 1;
+    }
+    count
+}
+
 /// Computes the estimated next charge timestamp for a subscription.
 ///
 /// This is a readonly helper that does not mutate contract state. It provides
 /// information for off-chain scheduling systems and UX displays.
 pub fn compute_next_charge_info(subscription: &Subscription) -> NextChargeInfo {
-    let next_charge_timestamp = subscription
-        .last_payment_timestamp
-        .saturating_add(subscription.interval_seconds);
+    let next_charge_timestamp = next_due_timestamp(subscription).unwrap_or(u64::MAX);
 
     let is_charge_expected = match subscription.status {
         SubscriptionStatus::Active => true,
@@ -106,7 +598,46 @@ pub fn compute_next_charge_info(subscription: &Subscription) -> NextChargeInfo {
     NextChargeInfo {
         next_charge_timestamp,
         is_charge_expected,
+        grace_deadline: None,
+    }
+}
+
+/// Runs [`compute_next_charge_info`] on a throwaway subscription built from the
+/// given schedule, so clients can preview subscriptions that do not exist yet.
+/// Only the global grace settings are read; nothing is written.
+pub fn preview_next_charge(
+    env: &Env,
+    last_payment: u64,
+    interval: u64,
+    status: SubscriptionStatus,
+) -> NextChargeInfo {
+    let placeholder = env.current_contract_address();
+    let subscription = Subscription {
+        subscriber: placeholder.clone(),
+        merchant: placeholder,
+        amount: 0,
+        interval_seconds: interval,
+        last_payment_timestamp: last_payment,
+        status,
+        prepaid_balance: 0,
+        usage_enabled: false,
+        category: None,
+        charge_count: 0,
+        metadata: None,
+        deposit_count: 0,
+    };
+    let mut info = compute_next_charge_info(&subscription);
+    if info.is_charge_expected
+        && matches!(
+            subscription.status,
+            SubscriptionStatus::Active | SubscriptionStatus::GracePeriod
+        )
+    {
+        info.grace_deadline = info
+            .next_charge_timestamp
+            .checked_add(crate::charge_core::grace_window(env, None, &subscription));
     }
+    info
 }
 
 /// Result of a paginated query for subscriptions by subscriber.
@@ -175,20 +706,16 @@ pub fn list_subscriptions_by_subscriber(
 
     // Iterate through all subscription IDs from start_from_id (inclusive) and filter by subscriber
     for id in start_from_id..next_id {
-        match env.storage().instance().get::<u32, Subscription>(&id) {
-            Some(sub) => {
-                if sub.subscriber == subscriber {
-                    subscription_ids.push_back(id);
-                    count += 1;
-                    last_found_id = id;
-                    if count >= limit {
-                        break;
-                    }
+        // Missing IDs (deleted or skipped) are simply passed over.
+        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&id) {
+            if sub.subscriber == subscriber {
+                subscription_ids.push_back(id);
+                count += 1;
+                last_found_id = id;
+                if count >= limit {
+                    break;
                 }
             }
-            None => {
-                // Subscription was deleted or ID skipped; continue to next
-            }
         }
     }
 
@@ -6,6 +6,7 @@
 //! with PRs touching billing, batch charge, or top-up estimation.
 
 use crate::types::{Error, SubscriptionStatus};
+use soroban_sdk::{Env, Vec};
 
 /// Validates if a status transition is allowed by the state machine.
 ///
@@ -78,7 +79,9 @@ pub fn validate_status_transition(
 
 /// Returns all valid target statuses for a given current status.
 ///
-/// This is useful for UI/documentation to show available actions.
+/// This is useful for UI/documentation to show available actions. Targets are always
+/// listed in ascending order of their `SubscriptionStatus` discriminant, so the output is
+/// stable across versions and safe for golden tests and client-side diffing.
 pub fn get_allowed_transitions(status: &SubscriptionStatus) -> &'static [SubscriptionStatus] {
     match status {
         SubscriptionStatus::Active => &[
@@ -106,3 +109,17 @@ pub fn get_allowed_transitions(status: &SubscriptionStatus) -> &'static [Subscri
 pub fn can_transition(from: &SubscriptionStatus, to: &SubscriptionStatus) -> bool {
     validate_status_transition(from, to).is_ok()
 }
+
+/// Batch form of [`can_transition`]: one validity flag per `(from, to)` pair, in order.
+///
+/// Pure; reads no storage. Lets callers check a bulk state change up front.
+pub fn validate_transitions(
+    env: &Env,
+    pairs: Vec<(SubscriptionStatus, SubscriptionStatus)>,
+) -> Vec<bool> {
+    let mut result = Vec::new(env);
+    for (from, to) in pairs.iter() {
+        result.push_back(can_transition(&from, &to));
+    }
+    result
+}
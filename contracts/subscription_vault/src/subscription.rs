@@ -5,10 +5,14 @@
 //! **PRs that only change subscription lifecycle or billing should edit this file only.**
 
 use crate::queries::get_subscription;
-use crate::safe_math::{safe_add_balance, validate_non_negative};
+use crate::safe_math::{safe_add, safe_add_balance, safe_sub_balance, validate_non_negative};
 use crate::state_machine::validate_status_transition;
-use crate::types::{DataKey, Error, Subscription, SubscriptionStatus};
-use soroban_sdk::{Address, Env, Symbol, Vec};
+use crate::types::{
+    AutoTopupConfig, BillingMode, CancellationReason, DataKey, Error, FundsDepositedEvent,
+    GiftDepositEvent, Subscription, SubscriptionCancelledEvent, SubscriptionPausedEvent,
+    SubscriptionStatus,
+};
+use soroban_sdk::{symbol_short, Address, Bytes, BytesN, Env, Symbol, Vec};
 
 pub fn next_id(env: &Env) -> u32 {
     let key = Symbol::new(env, "next_id");
@@ -27,7 +31,160 @@ pub fn do_create_subscription(
     usage_enabled: bool,
 ) -> Result<u32, Error> {
     subscriber.require_auth();
+    create_subscription_record(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        interval_seconds,
+        usage_enabled,
+        env.ledger().timestamp(),
+    )
+}
+
+/// Creates a subscription whose first charge is due at `start_timestamp` rather than
+/// one interval after creation. Rejects start times in the past with `InvalidInput`.
+pub fn do_create_subscription_starting(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    start_timestamp: u64,
+) -> Result<u32, Error> {
+    subscriber.require_auth();
+    if start_timestamp < env.ledger().timestamp() {
+        return Err(Error::InvalidInput);
+    }
+    create_subscription_record(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        interval_seconds,
+        usage_enabled,
+        start_timestamp,
+    )
+}
+
+/// Deferred start time of a subscription created with [`do_create_subscription_starting`],
+/// or `None` if it was chargeable from creation.
+pub fn get_start_timestamp(env: &Env, subscription_id: u32) -> Option<u64> {
+    env.storage()
+        .instance()
+        .get(&DataKey::StartTimestamp(subscription_id))
+}
+
+/// Sum of every subscription's `prepaid_balance`, kept as a running total.
+pub fn get_total_prepaid(env: &Env) -> i128 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "total_prepaid"))
+        .unwrap_or(0)
+}
+
+/// Moves the running total of prepaid balances by `delta`. Called wherever a
+/// subscription's `prepaid_balance` changes other than through a merchant credit.
+pub fn adjust_total_prepaid(env: &Env, delta: i128) -> Result<(), Error> {
+    let total = safe_add(get_total_prepaid(env), delta)?;
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "total_prepaid"), &total);
+    Ok(())
+}
+
+/// Maximum length in bytes of a subscription's `metadata` blob.
+pub const MAX_METADATA_LEN: u32 = 256;
+
+pub fn do_create_subscription_with_meta(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    metadata: Bytes,
+) -> Result<u32, Error> {
+    subscriber.require_auth();
+    if metadata.len() > MAX_METADATA_LEN {
+        return Err(Error::InvalidInput);
+    }
+    let id = create_subscription_record(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        interval_seconds,
+        usage_enabled,
+        env.ledger().timestamp(),
+    )?;
+    let mut sub = get_subscription(env, id)?;
+    sub.metadata = Some(metadata);
+    env.storage().instance().set(&id, &sub);
+    Ok(id)
+}
+
+/// Creates a subscription and makes its initial deposit in one call, so a subscriber is
+/// never left with an unfunded subscription. Any error (including a deposit below
+/// `min_topup`) reverts the creation as well.
+pub fn do_create_and_fund(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    deposit: i128,
+) -> Result<u32, Error> {
+    subscriber.require_auth();
+    let id = create_subscription_record(
+        env,
+        subscriber.clone(),
+        merchant,
+        amount,
+        interval_seconds,
+        usage_enabled,
+        env.ledger().timestamp(),
+    )?;
+    subscriber_deposit(env, id, subscriber, deposit, None)?;
+    Ok(id)
+}
+
+/// Validates and stores a new `Active` subscription and updates the merchant index.
+///
+/// Performs no auth; callers must have authorized the subscriber already.
+pub fn create_subscription_record(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    start_timestamp: u64,
+) -> Result<u32, Error> {
+    crate::admin::require_not_paused(env)?;
     validate_non_negative(amount)?;
+    // Purely metered subscriptions (usage-enabled with no flat amount) are exempt.
+    if (amount > 0 || !usage_enabled) && amount < crate::admin::get_min_charge_amount(env) {
+        return Err(Error::InvalidAmount);
+    }
+    if merchant == env.current_contract_address() {
+        return Err(Error::InvalidInput);
+    }
+    if crate::admin::is_address_blocked(env, &merchant) {
+        return Err(Error::Forbidden);
+    }
+    let subscriber_key = DataKey::SubscriberSubs(subscriber.clone());
+    let mut subscriber_ids: Vec<u32> = env
+        .storage()
+        .instance()
+        .get(&subscriber_key)
+        .unwrap_or(Vec::new(env));
+    let max_subs = crate::admin::get_max_subs_per_subscriber(env);
+    if max_subs > 0 && subscriber_ids.len() >= max_subs {
+        return Err(Error::SubscriptionLimitReached);
+    }
     let sub = Subscription {
         subscriber: subscriber.clone(),
         merchant: merchant.clone(),
@@ -37,9 +194,23 @@ pub fn do_create_subscription(
         status: SubscriptionStatus::Active,
         prepaid_balance: 0i128,
         usage_enabled,
+        category: None,
+        charge_count: 0,
+        metadata: None,
+        deposit_count: 0,
     };
     let id = next_id(env);
     env.storage().instance().set(&id, &sub);
+    if start_timestamp > sub.last_payment_timestamp {
+        env.storage()
+            .instance()
+            .set(&DataKey::StartTimestamp(id), &start_timestamp);
+    }
+    if crate::admin::require_merchant_confirmation(env) {
+        env.storage()
+            .instance()
+            .set(&DataKey::PendingMerchant(id), &true);
+    }
 
     // Maintain merchant → subscription-ID index
     let key = DataKey::MerchantSubs(sub.merchant.clone());
@@ -47,6 +218,101 @@ pub fn do_create_subscription(
     ids.push_back(id);
     env.storage().instance().set(&key, &ids);
 
+    // Maintain subscriber → subscription-ID index (used for the per-subscriber cap)
+    subscriber_ids.push_back(id);
+    env.storage()
+        .instance()
+        .set(&subscriber_key, &subscriber_ids);
+    adjust_active_count(env, true);
+
+    Ok(id)
+}
+
+/// Moves the non-cancelled subscription counter up on creation or down when a
+/// subscription first reaches `Cancelled`. Saturates at zero.
+pub fn adjust_active_count(env: &Env, created: bool) {
+    let storage = env.storage().instance();
+    let count: u32 = storage.get(&DataKey::TotalActive).unwrap_or(0);
+    let count = if created {
+        count.saturating_add(1)
+    } else {
+        count.saturating_sub(1)
+    };
+    storage.set(&DataKey::TotalActive, &count);
+}
+
+/// Removes `subscription_id` from the merchant's subscription index.
+///
+/// Called when a subscription reaches the terminal `Cancelled` state so the index
+/// only tracks live subscriptions. A no-op if the ID is not present.
+pub fn remove_from_merchant_index(env: &Env, merchant: &Address, subscription_id: u32) {
+    let key = DataKey::MerchantSubs(merchant.clone());
+    let ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
+    if let Some(pos) = ids.first_index_of(subscription_id) {
+        let mut ids = ids;
+        ids.remove(pos);
+        env.storage().instance().set(&key, &ids);
+    }
+}
+
+/// Adds `subscription_id` to the subscriber's subscription index. A no-op if it is
+/// already present, so `migrate` can backfill records created before the index existed.
+pub fn add_to_subscriber_index(env: &Env, subscriber: &Address, subscription_id: u32) {
+    let key = DataKey::SubscriberSubs(subscriber.clone());
+    let mut ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
+    if !ids.contains(subscription_id) {
+        ids.push_back(subscription_id);
+        env.storage().instance().set(&key, &ids);
+    }
+}
+
+/// Removes `subscription_id` from the subscriber's subscription index once it is
+/// `Cancelled`, freeing a slot under the per-subscriber cap. A no-op if absent.
+pub fn remove_from_subscriber_index(env: &Env, subscriber: &Address, subscription_id: u32) {
+    let key = DataKey::SubscriberSubs(subscriber.clone());
+    let ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
+    if let Some(pos) = ids.first_index_of(subscription_id) {
+        let mut ids = ids;
+        ids.remove(pos);
+        env.storage().instance().set(&key, &ids);
+    }
+}
+
+/// Creates a subscription keyed by a caller-supplied `reference`, deduplicating retries.
+///
+/// The first call stores `DataKey::RefToId(reference)` and returns the fresh ID. Any later
+/// call with the same reference returns the existing ID without creating a second record.
+/// A reference already bound to another subscriber's subscription is rejected with `Forbidden`.
+pub fn do_create_subscription_with_ref(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    reference: BytesN<32>,
+) -> Result<u32, Error> {
+    subscriber.require_auth();
+
+    let ref_key = DataKey::RefToId(reference);
+    if let Some(existing) = env.storage().instance().get::<_, u32>(&ref_key) {
+        let sub = get_subscription(env, existing)?;
+        if sub.subscriber != subscriber {
+            return Err(Error::Forbidden);
+        }
+        return Ok(existing);
+    }
+
+    let id = create_subscription_record(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        interval_seconds,
+        usage_enabled,
+        env.ledger().timestamp(),
+    )?;
+    env.storage().instance().set(&ref_key, &id);
     Ok(id)
 }
 
@@ -55,8 +321,52 @@ pub fn do_deposit_funds(
     subscription_id: u32,
     subscriber: Address,
     amount: i128,
+    memo: Option<BytesN<32>>,
 ) -> Result<(), Error> {
     subscriber.require_auth();
+    subscriber_deposit(env, subscription_id, subscriber, amount, memo)
+}
+
+/// Deposit paid by `subscriber` that emits `deposited`. Unless paying for their own
+/// subscription, rejected with `Forbidden` while deposits are restricted to owners.
+///
+/// Performs no auth; callers must have authorized the subscriber already.
+fn subscriber_deposit(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    amount: i128,
+    memo: Option<BytesN<32>>,
+) -> Result<(), Error> {
+    let owner = get_subscription(env, subscription_id)?.subscriber;
+    if subscriber != owner && crate::admin::restrict_deposits_to_owner(env) {
+        return Err(Error::Forbidden);
+    }
+    let sub = deposit_record(env, subscription_id, &subscriber, amount)?;
+    env.events().publish(
+        (Symbol::new(env, "deposited"), subscription_id),
+        FundsDepositedEvent {
+            subscription_id,
+            subscriber,
+            amount,
+            new_balance: sub.prepaid_balance,
+            memo: memo.unwrap_or_else(|| BytesN::from_array(env, &[0u8; 32])),
+        },
+    );
+    Ok(())
+}
+
+/// Applies a deposit paid by `payer`: validation, token pull and balance update.
+/// Returns the updated subscription so the caller can emit its event.
+///
+/// Performs no auth; callers must have authorized the payer already.
+fn deposit_record(
+    env: &Env,
+    subscription_id: u32,
+    payer: &Address,
+    amount: i128,
+) -> Result<Subscription, Error> {
+    crate::admin::require_not_paused(env)?;
 
     let min_topup: i128 = crate::admin::get_min_topup(env)?;
     if amount < min_topup {
@@ -65,7 +375,6 @@ pub fn do_deposit_funds(
     validate_non_negative(amount)?;
 
     let mut sub = get_subscription(env, subscription_id)?;
-    sub.prepaid_balance = safe_add_balance(sub.prepaid_balance, amount)?;
     let token_addr: Address = env
         .storage()
         .instance()
@@ -73,50 +382,215 @@ pub fn do_deposit_funds(
         .ok_or(Error::NotInitialized)?;
     let token_client = soroban_sdk::token::Client::new(env, &token_addr);
 
-    token_client.transfer(&subscriber, &env.current_contract_address(), &amount);
+    // Pull the tokens before touching any state so a failed transfer leaves nothing behind.
+    token_client.transfer(payer, &env.current_contract_address(), &amount);
+
+    sub.prepaid_balance = safe_add_balance(sub.prepaid_balance, amount)?;
+    sub.deposit_count = sub.deposit_count.saturating_add(1);
     env.storage().instance().set(&subscription_id, &sub);
+    adjust_total_prepaid(env, amount)?;
+    Ok(sub)
+}
+
+/// Sponsor funds another user's subscription. Same rules as [`do_deposit_funds`]
+/// (minimum top-up, pause), except that an explicit gift is allowed even while
+/// deposits are restricted to owners. The sponsor is recorded in a `gift_deposit`
+/// event instead of the `deposited` event.
+pub fn do_gift_deposit(
+    env: &Env,
+    subscription_id: u32,
+    sponsor: Address,
+    amount: i128,
+) -> Result<(), Error> {
+    sponsor.require_auth();
+    let sub = deposit_record(env, subscription_id, &sponsor, amount)?;
     env.events().publish(
-        (Symbol::new(env, "deposited"), subscription_id),
-        (subscriber, amount, sub.prepaid_balance),
+        (Symbol::new(env, "gift_deposit"), subscription_id),
+        GiftDepositEvent {
+            subscription_id,
+            subscriber: sub.subscriber,
+            sponsor,
+            amount,
+            new_balance: sub.prepaid_balance,
+        },
     );
     Ok(())
 }
 
+/// Cancels the subscription on behalf of its subscriber or merchant. The first
+/// cancellation emits `cancelled` with a [`SubscriptionCancelledEvent`] carrying `reason`.
+///
+/// If the subscription has a cancellation notice period, the first call only schedules
+/// the cancellation for `now + notice` (event `cancel_scheduled`) and the subscription
+/// keeps being charged; once that time has passed, a further call or
+/// `sweep_scheduled_cancellations` performs the cancellation.
 pub fn do_cancel_subscription(
     env: &Env,
     subscription_id: u32,
     authorizer: Address,
+    reason: CancellationReason,
 ) -> Result<(), Error> {
     authorizer.require_auth();
 
-    let mut sub = get_subscription(env, subscription_id)?;
+    let sub = get_subscription(env, subscription_id)?;
 
     if authorizer != sub.subscriber && authorizer != sub.merchant {
         return Err(Error::Forbidden);
     }
 
     validate_status_transition(&sub.status, &SubscriptionStatus::Cancelled)?;
+    let already_cancelled = sub.status == SubscriptionStatus::Cancelled;
+
+    // Under a notice period the first request only schedules the cancellation; charges
+    // continue until it takes effect, and a cancel after that point completes it.
+    let notice = get_cancellation_notice(env, subscription_id);
+    if !already_cancelled && notice > 0 {
+        let now = env.ledger().timestamp();
+        match get_scheduled_cancellation(env, subscription_id) {
+            Some(effective_at) if now >= effective_at => {}
+            Some(_) => return Ok(()),
+            None => {
+                let effective_at = now.saturating_add(notice);
+                env.storage().instance().set(
+                    &DataKey::ScheduledCancellation(subscription_id),
+                    &(effective_at, authorizer.clone(), reason),
+                );
+                env.events().publish(
+                    (Symbol::new(env, "cancel_scheduled"), subscription_id),
+                    (authorizer, effective_at),
+                );
+                return Ok(());
+            }
+        }
+    }
+    finalize_cancellation(env, subscription_id, sub, authorizer, reason)
+}
+
+/// Moves the subscription to `Cancelled` for `authorizer`: settles pending usage,
+/// charges the early-cancellation fee when the subscriber walks away, drops it from the
+/// indexes and emits `cancelled`. Also completes a cancellation scheduled under a notice
+/// period and clears the pause bookkeeping. Repeat cancels only re-store the record.
+pub fn finalize_cancellation(
+    env: &Env,
+    subscription_id: u32,
+    mut sub: Subscription,
+    authorizer: Address,
+    reason: CancellationReason,
+) -> Result<(), Error> {
+    validate_status_transition(&sub.status, &SubscriptionStatus::Cancelled)?;
+    // Repeat cancels are accepted but must not re-apply one-time side effects.
+    let already_cancelled = sub.status == SubscriptionStatus::Cancelled;
+    let storage = env.storage().instance();
+    storage.remove(&DataKey::ScheduledCancellation(subscription_id));
+    storage.remove(&DataKey::PausedAt(subscription_id));
+    storage.remove(&DataKey::PauseReason(subscription_id));
     sub.status = SubscriptionStatus::Cancelled;
 
+    // The merchant is paid for recorded usage before the remainder becomes refundable.
+    crate::charge_core::settle_pending_usage(env, subscription_id, &mut sub)?;
+
+    // Early-cancellation fee applies only when the subscriber walks away.
+    if authorizer == sub.subscriber && !already_cancelled {
+        let fee = cancellation_fee(env, subscription_id, sub.prepaid_balance)?;
+        if fee > 0 {
+            sub.prepaid_balance = safe_sub_balance(sub.prepaid_balance, fee)?;
+            crate::merchant::credit_merchant(env, subscription_id, &sub.merchant, fee)?;
+            env.events().publish(
+                (Symbol::new(env, "cancellation_fee"), subscription_id),
+                (fee, sub.prepaid_balance),
+            );
+        }
+    }
+
     env.storage().instance().set(&subscription_id, &sub);
+    remove_from_merchant_index(env, &sub.merchant, subscription_id);
+    remove_from_subscriber_index(env, &sub.subscriber, subscription_id);
+    if !already_cancelled {
+        adjust_active_count(env, false);
+        env.events().publish(
+            (symbol_short!("cancelled"), subscription_id),
+            SubscriptionCancelledEvent {
+                subscription_id,
+                authorizer,
+                refund_amount: sub.prepaid_balance,
+                reason,
+            },
+        );
+    }
     Ok(())
 }
 
+/// Interval charge on behalf of the billing engine: requires its auth, then runs
+/// [`crate::charge_core::charge_one`] at the current ledger time, which also emits the
+/// `charged` event. `expected_amount` is passed through as a price guard.
+pub fn do_charge_subscription(
+    env: &Env,
+    subscription_id: u32,
+    expected_amount: Option<i128>,
+) -> Result<(), Error> {
+    crate::admin::require_billing_engine_auth(env)?;
+    crate::charge_core::charge_one(
+        env,
+        subscription_id,
+        env.ledger().timestamp(),
+        None,
+        expected_amount,
+    )
+}
+
 pub fn do_pause_subscription(
     env: &Env,
     subscription_id: u32,
     authorizer: Address,
+    reason: Option<Symbol>,
 ) -> Result<(), Error> {
     authorizer.require_auth();
 
     let mut sub = get_subscription(env, subscription_id)?;
     validate_status_transition(&sub.status, &SubscriptionStatus::Paused)?;
+
+    // Record when the pause started; repeated pauses keep the original start.
+    if sub.status != SubscriptionStatus::Paused {
+        env.storage().instance().set(
+            &DataKey::PausedAt(subscription_id),
+            &env.ledger().timestamp(),
+        );
+    }
     sub.status = SubscriptionStatus::Paused;
 
     env.storage().instance().set(&subscription_id, &sub);
+    let reason_key = DataKey::PauseReason(subscription_id);
+    match &reason {
+        Some(r) => env.storage().instance().set(&reason_key, r),
+        None => env.storage().instance().remove(&reason_key),
+    }
+    env.events().publish(
+        (symbol_short!("paused"), subscription_id),
+        SubscriptionPausedEvent {
+            subscription_id,
+            authorizer,
+            reason,
+        },
+    );
     Ok(())
 }
 
+/// Reason recorded for the subscription's current pause, if any.
+pub fn get_pause_reason(env: &Env, subscription_id: u32) -> Option<Symbol> {
+    env.storage()
+        .instance()
+        .get(&DataKey::PauseReason(subscription_id))
+}
+
+/// Seconds spent paused since the last interval charge, added to the next due time so
+/// subscribers are not billed for paused time (0 if none).
+pub fn get_paused_seconds(env: &Env, subscription_id: u32) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::PausedSeconds(subscription_id))
+        .unwrap_or(0)
+}
+
 pub fn do_resume_subscription(
     env: &Env,
     subscription_id: u32,
@@ -126,6 +600,20 @@ pub fn do_resume_subscription(
 
     let mut sub = get_subscription(env, subscription_id)?;
     validate_status_transition(&sub.status, &SubscriptionStatus::Active)?;
+
+    // Credit the time spent paused so the next charge shifts forward by it.
+    let paused_key = DataKey::PausedAt(subscription_id);
+    if let Some(paused_at) = env.storage().instance().get::<_, u64>(&paused_key) {
+        let paused_for = env.ledger().timestamp().saturating_sub(paused_at);
+        let total = get_paused_seconds(env, subscription_id).saturating_add(paused_for);
+        env.storage()
+            .instance()
+            .set(&DataKey::PausedSeconds(subscription_id), &total);
+        env.storage().instance().remove(&paused_key);
+    }
+    env.storage()
+        .instance()
+        .remove(&DataKey::PauseReason(subscription_id));
     sub.status = SubscriptionStatus::Active;
 
     env.storage().instance().set(&subscription_id, &sub);
@@ -142,6 +630,7 @@ pub fn do_charge_one_off(
     amount: i128,
 ) -> Result<(), Error> {
     merchant.require_auth();
+    crate::admin::require_not_paused(env)?;
 
     let mut sub = get_subscription(env, subscription_id)?;
     if sub.merchant != merchant {
@@ -157,13 +646,558 @@ pub fn do_charge_one_off(
         return Err(Error::InsufficientPrepaidBalance);
     }
 
-    sub.prepaid_balance = sub
-        .prepaid_balance
-        .checked_sub(amount)
-        .ok_or(Error::Overflow)?;
+    sub.prepaid_balance = safe_sub_balance(sub.prepaid_balance, amount)?;
 
     env.storage().instance().set(&subscription_id, &sub);
+    crate::merchant::credit_merchant(env, subscription_id, &merchant, amount)?;
+
+    Ok(())
+}
+
+/// Enables or disables partial charging for a subscription. Requires auth from
+/// the subscription's merchant.
+pub fn do_set_allow_partial_charge(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    allow: bool,
+) -> Result<(), Error> {
+    merchant.require_auth();
 
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    let key = DataKey::AllowPartialCharge(subscription_id);
+    if allow {
+        env.storage().instance().set(&key, &true);
+    } else {
+        env.storage().instance().remove(&key);
+    }
+    Ok(())
+}
+
+/// Whether the merchant has enabled partial charging for a subscription.
+pub fn allows_partial_charge(env: &Env, subscription_id: u32) -> bool {
+    env.storage()
+        .instance()
+        .has(&DataKey::AllowPartialCharge(subscription_id))
+}
+
+/// Switches a usage-enabled subscription between prepaid and post-paid billing.
+/// Requires auth from the subscription's merchant.
+///
+/// A post-paid interval charge bills the usage recorded with `record_usage` during the
+/// period that just ended instead of `amount`; the prepaid balance still funds it.
+pub fn do_set_postpaid(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    postpaid: bool,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if postpaid && !sub.usage_enabled {
+        return Err(Error::UsageNotEnabled);
+    }
+
+    let key = DataKey::Postpaid(subscription_id);
+    if postpaid {
+        env.storage().instance().set(&key, &true);
+    } else {
+        env.storage().instance().remove(&key);
+    }
+    Ok(())
+}
+
+/// Whether a subscription is billed in arrears for recorded usage.
+pub fn is_postpaid(env: &Env, subscription_id: u32) -> bool {
+    env.storage()
+        .instance()
+        .has(&DataKey::Postpaid(subscription_id))
+}
+
+/// Upper bound for [`do_set_cancellation_fee_bps`]: 100% of the remaining balance.
+pub const MAX_FEE_BPS: u32 = 10_000;
+
+/// Merchant sets the share of the remaining prepaid balance (in basis points) kept as a
+/// fee when the subscriber cancels. 0 removes the fee.
+pub fn do_set_cancellation_fee_bps(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    bps: u32,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if bps > MAX_FEE_BPS {
+        return Err(Error::InvalidInput);
+    }
+
+    env.storage()
+        .instance()
+        .set(&DataKey::CancellationFeeBps(subscription_id), &bps);
+    Ok(())
+}
+
+/// Cancellation fee in basis points for a subscription (0 if unset).
+pub fn get_cancellation_fee_bps(env: &Env, subscription_id: u32) -> u32 {
+    env.storage()
+        .instance()
+        .get(&DataKey::CancellationFeeBps(subscription_id))
+        .unwrap_or(0)
+}
+
+/// Merchant sets the token units charged per usage unit by `charge_usage`.
+/// `rate` must be positive.
+pub fn do_set_usage_rate(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    rate: i128,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if rate <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    env.storage()
+        .instance()
+        .set(&DataKey::UsageRate(subscription_id), &rate);
+    Ok(())
+}
+
+/// Cancellation notice period in seconds a subscription inherited from its plan (0 if none).
+pub fn get_cancellation_notice(env: &Env, subscription_id: u32) -> u64 {
+    env.storage()
+        .instance()
+        .get(&DataKey::CancellationNotice(subscription_id))
+        .unwrap_or(0)
+}
+
+/// When a cancellation requested under a notice period takes effect, if one is pending.
+pub fn get_scheduled_cancellation(env: &Env, subscription_id: u32) -> Option<u64> {
+    get_cancellation_request(env, subscription_id).map(|(effective_at, _, _)| effective_at)
+}
+
+/// The pending cancellation request: when it takes effect, who asked and why.
+pub fn get_cancellation_request(
+    env: &Env,
+    subscription_id: u32,
+) -> Option<(u64, Address, CancellationReason)> {
+    env.storage()
+        .instance()
+        .get(&DataKey::ScheduledCancellation(subscription_id))
+}
+
+/// Token units per usage unit for a subscription (1 if unset).
+pub fn get_usage_rate(env: &Env, subscription_id: u32) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::UsageRate(subscription_id))
+        .unwrap_or(1)
+}
+
+/// `balance * fee_bps / 10_000`, rounded down. Split so it cannot overflow, and never
+/// more than `balance` since the rate is capped at [`MAX_FEE_BPS`].
+fn cancellation_fee(env: &Env, subscription_id: u32, balance: i128) -> Result<i128, Error> {
+    let bps = get_cancellation_fee_bps(env, subscription_id) as i128;
+    if bps == 0 || balance <= 0 {
+        return Ok(0);
+    }
+    let scale = MAX_FEE_BPS as i128;
+    let whole = (balance / scale).checked_mul(bps).ok_or(Error::Overflow)?;
+    let part = (balance % scale) * bps / scale;
+    Ok(whole + part)
+}
+
+/// Returns the merchant's pending amount proposal for a subscription, if any.
+pub fn get_pending_amount_change(env: &Env, subscription_id: u32) -> Option<i128> {
+    env.storage()
+        .instance()
+        .get(&DataKey::PendingAmount(subscription_id))
+}
+
+/// Merchant proposes a new per-interval `amount`. It only applies once the subscriber
+/// accepts; until then charges keep using the current amount. Replaces any earlier proposal.
+pub fn do_propose_amount_change(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    new_amount: i128,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if new_amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    if sub.status == SubscriptionStatus::Cancelled {
+        return Err(Error::NotActive);
+    }
+
+    env.storage()
+        .instance()
+        .set(&DataKey::PendingAmount(subscription_id), &new_amount);
+    env.events().publish(
+        (Symbol::new(env, "amount_change_proposed"), subscription_id),
+        (merchant, sub.amount, new_amount),
+    );
+    Ok(())
+}
+
+/// Merchant pins the subscription to a fixed schedule: charges become due at
+/// `anchor + k * interval_seconds` instead of drifting from the last payment time.
+/// An anchor earlier than one interval after the last payment is stored moved forward
+/// to its first slot at or after that time.
+pub fn do_set_billing_anchor(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    anchor: u64,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if sub.status == SubscriptionStatus::Cancelled {
+        return Err(Error::NotActive);
+    }
+
+    // The first anchored due time is never earlier than a full interval after the last
+    // payment (or the deferred start), so anchoring cannot pull the next charge forward.
+    let earliest = match get_start_timestamp(env, subscription_id) {
+        Some(start) if start > sub.last_payment_timestamp => start,
+        _ => sub
+            .last_payment_timestamp
+            .checked_add(sub.interval_seconds)
+            .ok_or(Error::Overflow)?,
+    };
+    let anchor = if earliest <= anchor {
+        anchor
+    } else {
+        crate::queries::compute_anchored_next(anchor, sub.interval_seconds, earliest - 1)
+            .ok_or(Error::Overflow)?
+    };
+    env.storage()
+        .instance()
+        .set(&DataKey::BillingAnchor(subscription_id), &anchor);
+    crate::charge_core::realign_charged_period(env, subscription_id, &sub);
+    env.events().publish(
+        (Symbol::new(env, "billing_anchor_set"), subscription_id),
+        (merchant, anchor),
+    );
+    Ok(())
+}
+
+/// Merchant switches how the next charge time is derived. `MonthlyCalendar` bills on
+/// the day of the month of the last payment (or of the deferred start), clamped in
+/// shorter months; `Interval` restores `interval_seconds` spacing.
+pub fn do_set_billing_mode(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    mode: BillingMode,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    if sub.status == SubscriptionStatus::Cancelled {
+        return Err(Error::NotActive);
+    }
+
+    let key = DataKey::CalendarDay(subscription_id);
+    match mode {
+        BillingMode::Interval => env.storage().instance().remove(&key),
+        BillingMode::MonthlyCalendar => {
+            let reference = get_start_timestamp(env, subscription_id)
+                .map_or(sub.last_payment_timestamp, |start| {
+                    start.max(sub.last_payment_timestamp)
+                });
+            let day = crate::queries::day_of_month(reference);
+            env.storage().instance().set(&key, &day);
+        }
+    }
+    crate::charge_core::realign_charged_period(env, subscription_id, &sub);
+    env.events().publish(
+        (Symbol::new(env, "billing_mode_set"), subscription_id),
+        (merchant, mode),
+    );
+    Ok(())
+}
+
+/// The subscription's billing mode (`Interval` unless set otherwise).
+pub fn get_billing_mode(env: &Env, subscription_id: u32) -> BillingMode {
+    if crate::queries::get_calendar_day(env, subscription_id).is_some() {
+        BillingMode::MonthlyCalendar
+    } else {
+        BillingMode::Interval
+    }
+}
+
+/// Subscriber accepts the pending proposal, which becomes the subscription's `amount`.
+pub fn do_accept_amount_change(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let mut sub = get_subscription(env, subscription_id)?;
+    if sub.subscriber != subscriber {
+        return Err(Error::Forbidden);
+    }
+    let new_amount = get_pending_amount_change(env, subscription_id).ok_or(Error::NotFound)?;
+
+    let old_amount = sub.amount;
+    sub.amount = new_amount;
+    env.storage().instance().set(&subscription_id, &sub);
+    env.storage()
+        .instance()
+        .remove(&DataKey::PendingAmount(subscription_id));
+    env.events().publish(
+        (Symbol::new(env, "amount_change_accepted"), subscription_id),
+        (old_amount, new_amount),
+    );
+    Ok(())
+}
+
+/// Subscriber rejects and clears the pending proposal; the amount is unchanged.
+pub fn do_reject_amount_change(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.subscriber != subscriber {
+        return Err(Error::Forbidden);
+    }
+    let proposed = get_pending_amount_change(env, subscription_id).ok_or(Error::NotFound)?;
+
+    env.storage()
+        .instance()
+        .remove(&DataKey::PendingAmount(subscription_id));
+    env.events().publish(
+        (Symbol::new(env, "amount_change_rejected"), subscription_id),
+        proposed,
+    );
+    Ok(())
+}
+
+/// Sets the merchant-defined reporting category. Merchant auth required.
+pub fn do_set_subscription_category(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+    category: Symbol,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let mut sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    sub.category = Some(category);
+    env.storage().instance().set(&subscription_id, &sub);
+    Ok(())
+}
+
+/// Replaces the subscription's opaque metadata. Subscriber or merchant auth required;
+/// blobs over [`MAX_METADATA_LEN`] bytes are rejected with `InvalidInput`.
+pub fn do_set_subscription_metadata(
+    env: &Env,
+    subscription_id: u32,
+    authorizer: Address,
+    data: Bytes,
+) -> Result<(), Error> {
+    authorizer.require_auth();
+
+    let mut sub = get_subscription(env, subscription_id)?;
+    if authorizer != sub.subscriber && authorizer != sub.merchant {
+        return Err(Error::Forbidden);
+    }
+    if data.len() > MAX_METADATA_LEN {
+        return Err(Error::InvalidInput);
+    }
+    sub.metadata = Some(data);
+    env.storage().instance().set(&subscription_id, &sub);
+    Ok(())
+}
+
+/// Switches interval and usage charges on or off independently, a finer-grained
+/// alternative to pausing. Subscriber or merchant auth required. A disabled charge type
+/// fails with `NotActive`; the status is unchanged.
+pub fn do_set_charge_flags(
+    env: &Env,
+    subscription_id: u32,
+    authorizer: Address,
+    interval_enabled: bool,
+    usage_enabled: bool,
+) -> Result<(), Error> {
+    authorizer.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if authorizer != sub.subscriber && authorizer != sub.merchant {
+        return Err(Error::Forbidden);
+    }
+
+    let storage = env.storage().instance();
+    for (key, enabled) in [
+        (
+            DataKey::IntervalChargesDisabled(subscription_id),
+            interval_enabled,
+        ),
+        (
+            DataKey::UsageChargesDisabled(subscription_id),
+            usage_enabled,
+        ),
+    ] {
+        if enabled {
+            storage.remove(&key);
+        } else {
+            storage.set(&key, &true);
+        }
+    }
+    env.events().publish(
+        (Symbol::new(env, "charge_flags"), subscription_id),
+        (authorizer, interval_enabled, usage_enabled),
+    );
+    Ok(())
+}
+
+/// Merchant accepts a subscription created while confirmation was required, allowing
+/// it to be charged. Confirming an already-confirmed subscription is a no-op.
+pub fn do_confirm_subscription(
+    env: &Env,
+    subscription_id: u32,
+    merchant: Address,
+) -> Result<(), Error> {
+    merchant.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.merchant != merchant {
+        return Err(Error::Unauthorized);
+    }
+    let key = DataKey::PendingMerchant(subscription_id);
+    if env.storage().instance().has(&key) {
+        env.storage().instance().remove(&key);
+        env.events().publish(
+            (Symbol::new(env, "sub_confirmed"), subscription_id),
+            merchant,
+        );
+    }
+    Ok(())
+}
+
+/// Whether a subscription still awaits merchant confirmation.
+pub fn is_pending_merchant(env: &Env, subscription_id: u32) -> bool {
+    env.storage()
+        .instance()
+        .has(&DataKey::PendingMerchant(subscription_id))
+}
+
+/// Whether interval charges are enabled for a subscription (default `true`).
+pub fn interval_charges_enabled(env: &Env, subscription_id: u32) -> bool {
+    !env.storage()
+        .instance()
+        .has(&DataKey::IntervalChargesDisabled(subscription_id))
+}
+
+/// Whether usage charges are enabled for a subscription (default `true`).
+pub fn usage_charges_enabled(env: &Env, subscription_id: u32) -> bool {
+    !env.storage()
+        .instance()
+        .has(&DataKey::UsageChargesDisabled(subscription_id))
+}
+
+/// Subscriber configures automatic top-up. After a successful interval charge leaves the
+/// balance below `threshold`, `amount` is pulled from the subscriber's wallet using a
+/// standing token allowance granted to the vault. `amount == 0` disables it.
+pub fn do_set_auto_topup(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    amount: i128,
+    threshold: i128,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Forbidden);
+    }
+    validate_non_negative(amount)?;
+    validate_non_negative(threshold)?;
+
+    let key = DataKey::AutoTopup(subscription_id);
+    if amount == 0 {
+        env.storage().instance().remove(&key);
+    } else {
+        env.storage()
+            .instance()
+            .set(&key, &AutoTopupConfig { amount, threshold });
+    }
+    Ok(())
+}
+
+/// Auto top-up settings for a subscription, if enabled.
+pub fn get_auto_topup(env: &Env, subscription_id: u32) -> Option<AutoTopupConfig> {
+    env.storage()
+        .instance()
+        .get(&DataKey::AutoTopup(subscription_id))
+}
+
+/// Refund address set by the subscriber, if any.
+pub fn get_refund_address(env: &Env, subscription_id: u32) -> Option<Address> {
+    env.storage()
+        .instance()
+        .get(&DataKey::RefundAddress(subscription_id))
+}
+
+/// Address that receives prepaid refunds: the refund address if set, else the subscriber.
+pub fn refund_destination(env: &Env, subscription_id: u32, sub: &Subscription) -> Address {
+    get_refund_address(env, subscription_id).unwrap_or_else(|| sub.subscriber.clone())
+}
+
+pub fn do_set_refund_address(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    refund_address: Address,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+
+    let sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Forbidden);
+    }
+
+    env.storage()
+        .instance()
+        .set(&DataKey::RefundAddress(subscription_id), &refund_address);
     Ok(())
 }
 
@@ -184,24 +1218,106 @@ pub fn do_withdraw_subscriber_funds(
         return Err(Error::InvalidStatusTransition); // Or Unauthorized/InvalidState
     }
 
-    let amount_to_refund = sub.prepaid_balance;
-    if amount_to_refund > 0 {
-        sub.prepaid_balance = 0;
-        env.storage().instance().set(&subscription_id, &sub);
+    refund_prepaid(env, subscription_id, &mut sub)?;
+    Ok(())
+}
+
+/// Pays the whole prepaid balance to the refund destination and stores the zeroed
+/// record. Returns the amount refunded.
+pub fn refund_prepaid(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+) -> Result<i128, Error> {
+    let amount = sub.prepaid_balance;
+    if amount <= 0 {
+        return Ok(0);
+    }
+    sub.prepaid_balance = 0;
+    env.storage().instance().set(&subscription_id, &*sub);
+    adjust_total_prepaid(env, -amount)?;
 
+    let token_addr: Address = env
+        .storage()
+        .instance()
+        .get(&Symbol::new(env, "token"))
+        .ok_or(Error::NotInitialized)?;
+    let token_client = soroban_sdk::token::Client::new(env, &token_addr);
+    token_client.transfer(
+        &env.current_contract_address(),
+        &refund_destination(env, subscription_id, sub),
+        &amount,
+    );
+    Ok(amount)
+}
+
+/// Emergency exit while the contract is paused: the subscriber pulls the full prepaid
+/// balance regardless of status, and the subscription is cancelled as a side effect.
+///
+/// Rejected with `Forbidden` when the contract is not paused; use
+/// [`do_withdraw_subscriber_funds`] after a normal cancellation instead.
+pub fn do_emergency_withdraw(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+) -> Result<(), Error> {
+    subscriber.require_auth();
+    if !crate::admin::is_contract_paused(env) {
+        return Err(Error::Forbidden);
+    }
+    // A record the migration has not reached may still be in a legacy layout, which
+    // `get_subscription` cannot decode.
+    if !crate::admin::record_layout_current(env, subscription_id) {
+        return Err(Error::MigrationRequired);
+    }
+
+    let mut sub = get_subscription(env, subscription_id)?;
+    if subscriber != sub.subscriber {
+        return Err(Error::Forbidden);
+    }
+
+    let amount = sub.prepaid_balance;
+    if sub.status != SubscriptionStatus::Cancelled {
+        sub.status = SubscriptionStatus::Cancelled;
+        remove_from_merchant_index(env, &sub.merchant, subscription_id);
+        remove_from_subscriber_index(env, &sub.subscriber, subscription_id);
+        adjust_active_count(env, false);
+        env.events().publish(
+            (symbol_short!("cancelled"), subscription_id),
+            SubscriptionCancelledEvent {
+                subscription_id,
+                authorizer: subscriber.clone(),
+                refund_amount: amount,
+                reason: CancellationReason::UserRequested,
+            },
+        );
+    }
+
+    sub.prepaid_balance = 0;
+    env.storage().instance().set(&subscription_id, &sub);
+    // Allowed mid-migration: a record the migration has not reached yet is added to
+    // the running total later, with its balance by then at zero.
+    if crate::admin::prepaid_total_includes(env, subscription_id) {
+        adjust_total_prepaid(env, -amount)?;
+    }
+
+    if amount > 0 {
         let token_addr: Address = env
             .storage()
             .instance()
             .get(&Symbol::new(env, "token"))
             .ok_or(Error::NotInitialized)?;
         let token_client = soroban_sdk::token::Client::new(env, &token_addr);
-
         token_client.transfer(
             &env.current_contract_address(),
-            &subscriber,
-            &amount_to_refund,
+            &refund_destination(env, subscription_id, &sub),
+            &amount,
         );
     }
 
+    env.events().publish(
+        (Symbol::new(env, "emergency_withdraw"), subscription_id),
+        (subscriber, amount),
+    );
     Ok(())
 }
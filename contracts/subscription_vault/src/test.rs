@@ -1,9 +1,12 @@
 use crate::{
-    can_transition, get_allowed_transitions, validate_status_transition, Error, RecoveryReason,
-    Subscription, SubscriptionStatus, SubscriptionVault, SubscriptionVaultClient,
+    can_transition, get_allowed_transitions, validate_status_transition, AutoTopupConfig,
+    CancellationReason, Error, FundsDepositedEvent, GiftDepositEvent, InsufficientBalanceError,
+    MaintenanceWindow, MigrationExportEvent, RecoveryReason, Subscription,
+    SubscriptionCancelledEvent, SubscriptionStatus, SubscriptionV1, SubscriptionV2, SubscriptionV3,
+    SubscriptionV4, SubscriptionVault, SubscriptionVaultClient,
 };
 use soroban_sdk::testutils::{Address as _, Events as _, Ledger as _};
-use soroban_sdk::{Address, Env, IntoVal, Vec as SorobanVec};
+use soroban_sdk::{Address, BytesN, Env, IntoVal, TryFromVal, Vec as SorobanVec};
 
 /// Baseline creation timestamp used by test helpers.
 const T0: u64 = 1_000;
@@ -166,29 +169,29 @@ fn test_can_transition_helper() {
 
 #[test]
 fn test_get_allowed_transitions() {
-    // Active
-    let active_targets = get_allowed_transitions(&SubscriptionStatus::Active);
-    assert_eq!(active_targets.len(), 4);
-    assert!(active_targets.contains(&SubscriptionStatus::Paused));
-    assert!(active_targets.contains(&SubscriptionStatus::Cancelled));
-    assert!(active_targets.contains(&SubscriptionStatus::InsufficientBalance));
-    assert!(active_targets.contains(&SubscriptionStatus::GracePeriod));
-
-    // Paused
-    let paused_targets = get_allowed_transitions(&SubscriptionStatus::Paused);
-    assert_eq!(paused_targets.len(), 2);
-    assert!(paused_targets.contains(&SubscriptionStatus::Active));
-    assert!(paused_targets.contains(&SubscriptionStatus::Cancelled));
-
-    // Cancelled
-    let cancelled_targets = get_allowed_transitions(&SubscriptionStatus::Cancelled);
-    assert_eq!(cancelled_targets.len(), 0);
+    use SubscriptionStatus::*;
+    assert_eq!(
+        get_allowed_transitions(&Active),
+        &[Paused, Cancelled, InsufficientBalance, GracePeriod]
+    );
+    assert_eq!(get_allowed_transitions(&Paused), &[Active, Cancelled]);
+    assert_eq!(get_allowed_transitions(&Cancelled), &[]);
+    assert_eq!(
+        get_allowed_transitions(&InsufficientBalance),
+        &[Active, Cancelled]
+    );
+    assert_eq!(
+        get_allowed_transitions(&GracePeriod),
+        &[Active, Cancelled, InsufficientBalance]
+    );
 
-    // InsufficientBalance
-    let ib_targets = get_allowed_transitions(&SubscriptionStatus::InsufficientBalance);
-    assert_eq!(ib_targets.len(), 2);
-    assert!(ib_targets.contains(&SubscriptionStatus::Active));
-    assert!(ib_targets.contains(&SubscriptionStatus::Cancelled));
+    // Every list is sorted by discriminant.
+    for from in [Active, Paused, Cancelled, InsufficientBalance, GracePeriod] {
+        let targets = get_allowed_transitions(&from);
+        for pair in targets.windows(2) {
+            assert!((pair[0].clone() as u32) < (pair[1].clone() as u32));
+        }
+    }
 }
 
 // =============================================================================
@@ -253,7 +256,7 @@ fn test_pause_subscription_from_active() {
     let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
 
     // Pause from Active should succeed
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
 
     let sub = client.get_subscription(&id);
     assert_eq!(sub.status, SubscriptionStatus::Paused);
@@ -269,7 +272,7 @@ fn test_pause_subscription_from_cancelled_should_fail() {
     client.cancel_subscription(&id, &subscriber);
 
     // Then try to pause (should fail)
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
 }
 
 #[test]
@@ -279,14 +282,14 @@ fn test_pause_subscription_from_paused_is_idempotent() {
     let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
 
     // First pause
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
     assert_eq!(
         client.get_subscription(&id).status,
         SubscriptionStatus::Paused
     );
 
     // Pausing again should succeed (idempotent)
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
     assert_eq!(
         client.get_subscription(&id).status,
         SubscriptionStatus::Paused
@@ -311,7 +314,7 @@ fn test_cancel_subscription_from_paused() {
     let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
 
     // First pause
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
 
     // Then cancel
     client.cancel_subscription(&id, &subscriber);
@@ -347,7 +350,7 @@ fn test_resume_subscription_from_paused() {
     let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
 
     // First pause
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
 
     // Then resume
     client.resume_subscription(&id, &subscriber);
@@ -391,7 +394,7 @@ fn test_full_lifecycle_active_pause_resume() {
     let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
 
     // Active -> Paused
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
     let sub = client.get_subscription(&id);
     assert_eq!(sub.status, SubscriptionStatus::Paused);
 
@@ -401,7 +404,7 @@ fn test_full_lifecycle_active_pause_resume() {
     assert_eq!(sub.status, SubscriptionStatus::Active);
 
     // Can pause again
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
     let sub = client.get_subscription(&id);
     assert_eq!(sub.status, SubscriptionStatus::Paused);
 }
@@ -429,7 +432,7 @@ fn test_all_valid_transitions_coverage() {
         let (env, client, _, _) = setup_test_env();
         let (id, subscriber, _) =
             create_test_subscription(&env, &client, SubscriptionStatus::Active);
-        client.pause_subscription(&id, &subscriber);
+        client.pause_subscription(&id, &subscriber, &None);
         assert_eq!(
             client.get_subscription(&id).status,
             SubscriptionStatus::Paused
@@ -472,7 +475,7 @@ fn test_all_valid_transitions_coverage() {
         let (env, client, _, _) = setup_test_env();
         let (id, subscriber, _) =
             create_test_subscription(&env, &client, SubscriptionStatus::Active);
-        client.pause_subscription(&id, &subscriber);
+        client.pause_subscription(&id, &subscriber, &None);
         client.resume_subscription(&id, &subscriber);
         assert_eq!(
             client.get_subscription(&id).status,
@@ -485,7 +488,7 @@ fn test_all_valid_transitions_coverage() {
         let (env, client, _, _) = setup_test_env();
         let (id, subscriber, _) =
             create_test_subscription(&env, &client, SubscriptionStatus::Active);
-        client.pause_subscription(&id, &subscriber);
+        client.pause_subscription(&id, &subscriber, &None);
         client.cancel_subscription(&id, &subscriber);
         assert_eq!(
             client.get_subscription(&id).status,
@@ -565,7 +568,7 @@ fn test_invalid_insufficient_balance_to_paused() {
 
     // Can't pause from InsufficientBalance - only resume to Active or cancel
     // Since pause_subscription validates Active -> Paused, this should fail
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
 }
 
 #[test]
@@ -580,6 +583,10 @@ fn test_subscription_struct_status_field() {
         status: SubscriptionStatus::Active,
         prepaid_balance: 500_000_000,
         usage_enabled: false,
+        category: None,
+        charge_count: 0,
+        metadata: None,
+        deposit_count: 0,
     };
     assert_eq!(sub.status, SubscriptionStatus::Active);
 }
@@ -640,7 +647,7 @@ fn test_min_topup_below_threshold() {
 
     client.cancel_subscription(&id, &merchant);
 
-    let result = client.try_deposit_funds(&id, &subscriber, &4_999999);
+    let result = client.try_deposit_funds(&id, &subscriber, &4_999999, &None);
     assert!(result.is_err());
 }
 #[test]
@@ -670,7 +677,7 @@ fn test_min_topup_exactly_at_threshold() {
         &None,
     );
 
-    let result = client.try_deposit_funds(&id, &subscriber, &min_topup);
+    let result = client.try_deposit_funds(&id, &subscriber, &min_topup, &None);
     assert!(result.is_ok());
 }
 
@@ -702,7 +709,7 @@ fn test_min_topup_above_threshold() {
         &None,
     );
 
-    let result = client.try_deposit_funds(&id, &subscriber, &deposit_amount);
+    let result = client.try_deposit_funds(&id, &subscriber, &deposit_amount, &None);
     assert!(result.is_ok());
 }
 
@@ -909,6 +916,10 @@ fn test_compute_next_charge_info_active_subscription() {
         status: SubscriptionStatus::Active,
         prepaid_balance: 100_000_000i128,
         usage_enabled: false,
+        category: None,
+        charge_count: 0,
+        metadata: None,
+        deposit_count: 0,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -939,6 +950,10 @@ fn test_compute_next_charge_info_paused_subscription() {
         status: SubscriptionStatus::Paused,
         prepaid_balance: 50_000_000i128,
         usage_enabled: false,
+        category: None,
+        charge_count: 0,
+        metadata: None,
+        deposit_count: 0,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -969,6 +984,10 @@ fn test_compute_next_charge_info_cancelled_subscription() {
         status: SubscriptionStatus::Cancelled,
         prepaid_balance: 0i128,
         usage_enabled: false,
+        category: None,
+        charge_count: 0,
+        metadata: None,
+        deposit_count: 0,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -999,6 +1018,10 @@ fn test_compute_next_charge_info_insufficient_balance_subscription() {
         status: SubscriptionStatus::InsufficientBalance,
         prepaid_balance: 1_000_000i128, // Not enough for next charge
         usage_enabled: false,
+        category: None,
+        charge_count: 0,
+        metadata: None,
+        deposit_count: 0,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1029,6 +1052,10 @@ fn test_compute_next_charge_info_short_interval() {
         status: SubscriptionStatus::Active,
         prepaid_balance: 10_000i128,
         usage_enabled: true,
+        category: None,
+        charge_count: 0,
+        metadata: None,
+        deposit_count: 0,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1057,6 +1084,10 @@ fn test_compute_next_charge_info_long_interval() {
         status: SubscriptionStatus::Active,
         prepaid_balance: 1_000_000_000i128,
         usage_enabled: false,
+        category: None,
+        charge_count: 0,
+        metadata: None,
+        deposit_count: 0,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1086,6 +1117,10 @@ fn test_compute_next_charge_info_overflow_protection() {
         status: SubscriptionStatus::Active,
         prepaid_balance: 100_000_000i128,
         usage_enabled: false,
+        category: None,
+        charge_count: 0,
+        metadata: None,
+        deposit_count: 0,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1152,7 +1187,7 @@ fn test_get_next_charge_info_all_statuses() {
     assert_eq!(info.next_charge_timestamp, 5000 + interval_seconds);
 
     // Test Paused status
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
     let info = client.get_next_charge_info(&id);
     assert!(!info.is_charge_expected);
     assert_eq!(info.next_charge_timestamp, 5000 + interval_seconds);
@@ -1299,6 +1334,10 @@ fn test_get_next_charge_info_zero_interval() {
         status: SubscriptionStatus::Active,
         prepaid_balance: 10_000_000i128,
         usage_enabled: false,
+        category: None,
+        charge_count: 0,
+        metadata: None,
+        deposit_count: 0,
     };
 
     let info = compute_next_charge_info(&subscription);
@@ -1330,6 +1369,20 @@ fn test_recover_stranded_funds_successful() {
     assert!(!events.is_empty());
 }
 
+#[test]
+fn test_recover_stranded_funds_rejects_contract_as_recipient() {
+    let (env, client, _, admin) = setup_test_env();
+
+    let result = client.try_recover_stranded_funds(
+        &admin,
+        &client.address,
+        &1_000_000i128,
+        &RecoveryReason::AccidentalTransfer,
+    );
+    assert_eq!(result, Err(Ok(Error::InvalidInput)));
+    assert!(env.events().all().is_empty());
+}
+
 #[test]
 fn test_cancel_subscription_unauthorized() {
     let env = Env::default();
@@ -1378,7 +1431,7 @@ fn test_withdraw_subscriber_funds() {
     let sub_id = client.create_subscription(&subscriber, &merchant, &1000, &86400, &true, &None);
 
     // Deposit funds to increase prepaid balance
-    client.deposit_funds(&sub_id, &subscriber, &5000);
+    client.deposit_funds(&sub_id, &subscriber, &5000, &None);
 
     // Cancel subscription
     client.cancel_subscription(&sub_id, &subscriber);
@@ -1719,7 +1772,7 @@ fn setup_batch_env(env: &Env) -> (SubscriptionVaultClient<'static>, Address, u32
     mint_for_subscriber(&env, &token_addr, &subscriber, BATCH_MINT);
     let id0 =
         client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
-    client.deposit_funds(&id0, &subscriber, &10_000000i128);
+    client.deposit_funds(&id0, &subscriber, &10_000000i128, &None);
     let id1 =
         client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
     env.ledger().set_timestamp(T0 + INTERVAL);
@@ -1778,7 +1831,7 @@ fn test_batch_charge_small_batch_5_subscriptions() {
     for _ in 0..5 {
         let id =
             client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
-        client.deposit_funds(&id, &subscriber, &10_000000i128);
+        client.deposit_funds(&id, &subscriber, &10_000000i128, &None);
         ids.push_back(id as u32);
     }
 
@@ -1809,7 +1862,7 @@ fn test_batch_charge_medium_batch_20_subscriptions() {
     for _ in 0..20 {
         let id =
             client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
-        client.deposit_funds(&id, &subscriber, &10_000000i128);
+        client.deposit_funds(&id, &subscriber, &10_000000i128, &None);
         ids.push_back(id as u32);
     }
 
@@ -1839,7 +1892,7 @@ fn test_batch_charge_large_batch_50_subscriptions() {
     for _ in 0..50 {
         let id =
             client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
-        client.deposit_funds(&id, &subscriber, &10_000000i128);
+        client.deposit_funds(&id, &subscriber, &10_000000i128, &None);
         ids.push_back(id as u32);
     }
 
@@ -1874,7 +1927,7 @@ fn test_batch_charge_mixed_success_and_insufficient_balance() {
         let id =
             client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
         if i % 2 == 0 {
-            client.deposit_funds(&id, &subscriber, &10_000000i128);
+            client.deposit_funds(&id, &subscriber, &10_000000i128, &None);
         }
         // Odd indices have no funds
         ids.push_back(id as u32);
@@ -1918,8 +1971,8 @@ fn test_batch_charge_mixed_interval_not_elapsed() {
     let id_long =
         client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None); // 30 days
 
-    client.deposit_funds(&id_short, &subscriber, &10_000000i128);
-    client.deposit_funds(&id_long, &subscriber, &10_000000i128);
+    client.deposit_funds(&id_short, &subscriber, &10_000000i128, &None);
+    client.deposit_funds(&id_long, &subscriber, &10_000000i128, &None);
 
     // Advance time only enough for short interval
     env.ledger().set_timestamp(T0 + 1800);
@@ -1953,12 +2006,12 @@ fn test_batch_charge_mixed_paused_and_active() {
 
     let id0 =
         client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
-    client.deposit_funds(&id0, &subscriber, &10_000000i128);
+    client.deposit_funds(&id0, &subscriber, &10_000000i128, &None);
 
     let id1 =
         client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
-    client.deposit_funds(&id1, &subscriber, &10_000000i128);
-    client.pause_subscription(&id1, &subscriber); // Pause this one
+    client.deposit_funds(&id1, &subscriber, &10_000000i128, &None);
+    client.pause_subscription(&id1, &subscriber, &None); // Pause this one
 
     env.ledger().set_timestamp(T0 + INTERVAL);
 
@@ -1991,11 +2044,11 @@ fn test_batch_charge_mixed_cancelled_and_active() {
 
     let id0 =
         client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
-    client.deposit_funds(&id0, &subscriber, &10_000000i128);
+    client.deposit_funds(&id0, &subscriber, &10_000000i128, &None);
 
     let id1 =
         client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
-    client.deposit_funds(&id1, &subscriber, &10_000000i128);
+    client.deposit_funds(&id1, &subscriber, &10_000000i128, &None);
     client.cancel_subscription(&id1, &subscriber); // Cancel this one
 
     env.ledger().set_timestamp(T0 + INTERVAL);
@@ -2056,7 +2109,7 @@ fn test_batch_charge_all_different_error_types() {
     // Sub 0: Success case
     let id_success =
         client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
-    client.deposit_funds(&id_success, &subscriber, &10_000000i128);
+    client.deposit_funds(&id_success, &subscriber, &10_000000i128, &None);
 
     // Sub 1: Insufficient balance
     let id_no_funds =
@@ -2065,8 +2118,8 @@ fn test_batch_charge_all_different_error_types() {
     // Sub 2: Paused
     let id_paused =
         client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
-    client.deposit_funds(&id_paused, &subscriber, &10_000000i128);
-    client.pause_subscription(&id_paused, &subscriber);
+    client.deposit_funds(&id_paused, &subscriber, &10_000000i128, &None);
+    client.pause_subscription(&id_paused, &subscriber, &None);
 
     // Advance time for eligible subscriptions
     env.ledger().set_timestamp(T0 + INTERVAL);
@@ -2130,7 +2183,7 @@ fn test_batch_charge_successful_charges_update_state() {
         &None,
     );
     let initial_balance = 10_000_000i128;
-    client.deposit_funds(&id, &subscriber, &initial_balance);
+    client.deposit_funds(&id, &subscriber, &initial_balance, &None);
 
     let sub_before = client.get_subscription(&id);
     assert_eq!(sub_before.prepaid_balance, initial_balance);
@@ -2198,13 +2251,13 @@ fn test_batch_charge_partial_batch_correct_final_state() {
     let amount = 1_000_000i128;
 
     let id0 = client.create_subscription(&subscriber, &merchant, &amount, &INTERVAL, &false, &None);
-    client.deposit_funds(&id0, &subscriber, &10_000_000i128);
+    client.deposit_funds(&id0, &subscriber, &10_000_000i128, &None);
 
     let id1 = client.create_subscription(&subscriber, &merchant, &amount, &INTERVAL, &false, &None);
     // id1 has no funds - will fail
 
     let id2 = client.create_subscription(&subscriber, &merchant, &amount, &INTERVAL, &false, &None);
-    client.deposit_funds(&id2, &subscriber, &10_000_000i128);
+    client.deposit_funds(&id2, &subscriber, &10_000_000i128, &None);
 
     env.ledger().set_timestamp(T0 + INTERVAL);
 
@@ -2248,7 +2301,7 @@ fn test_batch_charge_multiple_rounds_state_consistency() {
     let amount = 1_000_000i128;
 
     let id = client.create_subscription(&subscriber, &merchant, &amount, &INTERVAL, &false, &None);
-    client.deposit_funds(&id, &subscriber, &10_000_000i128);
+    client.deposit_funds(&id, &subscriber, &10_000_000i128, &None);
 
     let mut ids = SorobanVec::<u32>::new(&env);
     ids.push_back(id as u32);
@@ -2347,7 +2400,7 @@ fn test_batch_charge_exhausts_balance_exactly() {
     let amount = 5_000_000i128;
 
     let id = client.create_subscription(&subscriber, &merchant, &amount, &INTERVAL, &false, &None);
-    client.deposit_funds(&id, &subscriber, &amount); // Exact amount for one charge
+    client.deposit_funds(&id, &subscriber, &amount, &None); // Exact amount for one charge
 
     env.ledger().set_timestamp(T0 + INTERVAL);
 
@@ -2375,7 +2428,7 @@ fn test_batch_charge_balance_off_by_one_insufficient() {
     let amount = 5_000_000i128;
 
     let id = client.create_subscription(&subscriber, &merchant, &amount, &INTERVAL, &false, &None);
-    client.deposit_funds(&id, &subscriber, &(amount - 1)); // One stroops short
+    client.deposit_funds(&id, &subscriber, &(amount - 1), &None); // One stroops short
 
     env.ledger().set_timestamp(T0 + INTERVAL);
 
@@ -2404,7 +2457,7 @@ fn test_batch_charge_result_indices_match_input_order() {
 
     let id0 =
         client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
-    client.deposit_funds(&id0, &subscriber, &10_000000i128);
+    client.deposit_funds(&id0, &subscriber, &10_000000i128, &None);
 
     let id1 =
         client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
@@ -2412,7 +2465,7 @@ fn test_batch_charge_result_indices_match_input_order() {
 
     let id2 =
         client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
-    client.deposit_funds(&id2, &subscriber, &10_000000i128);
+    client.deposit_funds(&id2, &subscriber, &10_000000i128, &None);
 
     env.ledger().set_timestamp(T0 + INTERVAL);
 
@@ -2666,7 +2719,7 @@ fn test_usage_flag_persists_through_state_transitions() {
     assert!(client.get_subscription(&id).usage_enabled);
 
     // Pause subscription
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
     assert!(client.get_subscription(&id).usage_enabled);
     assert_eq!(
         client.get_subscription(&id).status,
@@ -2891,7 +2944,7 @@ fn test_usage_enabled_immutable_after_creation() {
     assert!(!client.get_subscription(&id).usage_enabled);
 
     // Perform various operations
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
     assert!(!client.get_subscription(&id).usage_enabled);
 
     client.resume_subscription(&id, &subscriber);
@@ -2928,7 +2981,7 @@ fn test_usage_enabled_with_all_subscription_statuses() {
     );
 
     // Test Paused status
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
     assert!(client.get_subscription(&id).usage_enabled);
     assert_eq!(
         client.get_subscription(&id).status,
@@ -2982,7 +3035,7 @@ fn test_usage_enabled_true_semantics() {
     assert_eq!(subscription.status, SubscriptionStatus::Active);
 
     // All standard operations work
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
     client.resume_subscription(&id, &subscriber);
     client.cancel_subscription(&id, &subscriber);
 }
@@ -3017,7 +3070,7 @@ fn test_usage_enabled_false_semantics() {
     assert_eq!(subscription.amount, 10_000_000i128);
 
     // All standard operations work
-    client.pause_subscription(&id, &subscriber);
+    client.pause_subscription(&id, &subscriber, &None);
     client.resume_subscription(&id, &subscriber);
     client.cancel_subscription(&id, &subscriber);
 }
@@ -3358,6 +3411,21 @@ fn test_multiple_admin_rotations() {
     assert!(client.try_set_min_topup(&admin3, &1_000000).is_err());
 }
 
+#[test]
+fn test_previous_admin_tracks_last_rotation() {
+    let (env, client, _, admin1) = setup_test_env();
+    assert_eq!(client.try_get_previous_admin(), Err(Ok(Error::NotFound)));
+
+    let admin2 = Address::generate(&env);
+    let admin3 = Address::generate(&env);
+    client.rotate_admin(&admin1, &admin2);
+    assert_eq!(client.get_previous_admin(), admin1);
+
+    client.rotate_admin(&admin2, &admin3);
+    assert_eq!(client.get_previous_admin(), admin2);
+    assert_eq!(client.get_admin(), admin3);
+}
+
 #[test]
 fn test_admin_rotation_does_not_affect_subscriptions() {
     let (env, client, _, old_admin) = setup_test_env();
@@ -3607,7 +3675,7 @@ fn test_admin_rotation_with_subscriptions_active() {
     );
 
     // Perform state changes
-    client.pause_subscription(&id1, &subscriber1);
+    client.pause_subscription(&id1, &subscriber1, &None);
 
     // Rotate admin
     let new_admin = Address::generate(&env);
@@ -4030,3 +4098,5686 @@ fn test_list_subscriptions_multiple_merchants() {
         );
     }
 }
+
+// =============================================================================
+// View Function Tests: get_due_within
+// =============================================================================
+
+#[test]
+fn test_get_due_within_returns_only_subscriptions_inside_window() {
+    let (env, client, _, _) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let day = 24 * 60 * 60;
+
+    // Due in 1 day, 3 days, 10 days respectively.
+    let mut ids = SorobanVec::new(&env);
+    for interval in [day, 3 * day, 10 * day] {
+        let id =
+            client.create_subscription(&subscriber, &merchant, &1000i128, &interval, &false, &None);
+        ids.push_back(id);
+    }
+
+    let due = client.get_due_within(&(5 * day), &0, &10);
+    assert_eq!(due.len(), 2);
+    assert_eq!(due.get(0).unwrap(), ids.get(0).unwrap());
+    assert_eq!(due.get(1).unwrap(), ids.get(1).unwrap());
+
+    // Window boundary is inclusive.
+    let due = client.get_due_within(&(3 * day), &0, &10);
+    assert_eq!(due.len(), 2);
+
+    // Zero window only matches subscriptions due exactly now.
+    assert_eq!(client.get_due_within(&0, &0, &10).len(), 0);
+}
+
+#[test]
+fn test_get_due_within_excludes_overdue_and_inactive() {
+    let (env, client, _, _) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+    let day = 24 * 60 * 60;
+
+    let (overdue, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    create_test_subscription(&env, &client, SubscriptionStatus::Paused);
+
+    // Both were created with a 30-day interval; move past the due time.
+    env.ledger().set_timestamp(T0 + 31 * day);
+    assert_eq!(client.get_due_within(&(30 * day), &0, &10).len(), 0);
+
+    // Before the due time only the active one is reported.
+    env.ledger().set_timestamp(T0 + 29 * day);
+    let due = client.get_due_within(&(2 * day), &0, &10);
+    assert_eq!(due.len(), 1);
+    assert_eq!(due.get(0).unwrap(), overdue);
+}
+
+#[test]
+fn test_get_due_within_respects_range_and_cap() {
+    let (env, client, _, _) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    for _ in 0..5 {
+        client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
+    }
+
+    let due = client.get_due_within(&INTERVAL, &2, &2);
+    assert_eq!(due.len(), 2);
+    assert_eq!(due.get(0).unwrap(), 2);
+    assert_eq!(due.get(1).unwrap(), 3);
+
+    // A limit beyond the cap is clamped rather than rejected.
+    assert_eq!(client.get_due_within(&INTERVAL, &0, &u32::MAX).len(), 5);
+}
+
+// =============================================================================
+// Merchant Refund Tests
+// =============================================================================
+
+#[test]
+fn test_merchant_refund_credits_prepaid_balance() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 10_000_000
+    );
+
+    client.merchant_refund(&id, &merchant, &4_000_000i128);
+
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.prepaid_balance, PREPAID - 6_000_000);
+    env.as_contract(&client.address, || {
+        assert_eq!(
+            crate::merchant::get_subscription_accrued(&env, id),
+            6_000_000
+        );
+        assert_eq!(
+            crate::merchant::get_merchant_balance(&env, &merchant),
+            6_000_000
+        );
+    });
+}
+
+#[test]
+fn test_merchant_refund_rejects_over_refund() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
+
+    // Nothing accrued yet.
+    let res = client.try_merchant_refund(&id, &merchant, &1i128);
+    assert_eq!(res, Err(Ok(Error::ExceedsAccruedBalance)));
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+
+    let res = client.try_merchant_refund(&id, &merchant, &10_000_001i128);
+    assert_eq!(res, Err(Ok(Error::ExceedsAccruedBalance)));
+
+    // Full refund succeeds once, then nothing remains to refund.
+    client.merchant_refund(&id, &merchant, &10_000_000i128);
+    let res = client.try_merchant_refund(&id, &merchant, &1i128);
+    assert_eq!(res, Err(Ok(Error::ExceedsAccruedBalance)));
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID);
+}
+
+#[test]
+fn test_merchant_refund_rejects_other_merchant_and_bad_amount() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+
+    let stranger = Address::generate(&env);
+    let res = client.try_merchant_refund(&id, &stranger, &1i128);
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+
+    let res = client.try_merchant_refund(&id, &merchant, &0i128);
+    assert_eq!(res, Err(Ok(Error::InvalidAmount)));
+}
+
+// =============================================================================
+// Idempotent Creation by Reference Tests
+// =============================================================================
+
+#[test]
+fn test_create_subscription_with_ref_returns_fresh_id() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let existing =
+        client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
+
+    let reference = soroban_sdk::BytesN::from_array(&env, &[7u8; 32]);
+    let id = client.create_subscription_with_ref(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &reference,
+    );
+
+    assert_ne!(id, existing);
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.subscriber, subscriber);
+    assert_eq!(sub.merchant, merchant);
+}
+
+#[test]
+fn test_create_subscription_with_repeated_ref_returns_same_id() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let reference = soroban_sdk::BytesN::from_array(&env, &[1u8; 32]);
+
+    let first = client.create_subscription_with_ref(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &reference,
+    );
+    let second = client.create_subscription_with_ref(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &reference,
+    );
+
+    assert_eq!(first, second);
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 1);
+    let page = client.list_subscriptions_by_subscriber(&subscriber, &0, &10);
+    assert_eq!(page.subscription_ids.len(), 1);
+
+    // A different reference creates a new record.
+    let other = soroban_sdk::BytesN::from_array(&env, &[2u8; 32]);
+    let third = client.create_subscription_with_ref(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &other,
+    );
+    assert_ne!(third, first);
+}
+
+#[test]
+fn test_create_subscription_with_ref_rejects_other_subscriber() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let reference = soroban_sdk::BytesN::from_array(&env, &[3u8; 32]);
+
+    client.create_subscription_with_ref(
+        &Address::generate(&env),
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &reference,
+    );
+    let res = client.try_create_subscription_with_ref(
+        &Address::generate(&env),
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &reference,
+    );
+    assert_eq!(res, Err(Ok(Error::Forbidden)));
+}
+
+// =============================================================================
+// Charge Outcome Prediction Tests
+// =============================================================================
+
+#[test]
+fn test_will_next_charge_succeed_when_covered() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert_eq!(client.will_next_charge_succeed(&id), None);
+
+    // Pure view: nothing changed.
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.prepaid_balance, PREPAID);
+    assert_eq!(sub.last_payment_timestamp, T0);
+}
+
+#[test]
+fn test_will_next_charge_succeed_reports_shortfall() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    let mut sub = client.get_subscription(&id);
+    sub.prepaid_balance = 4_000_000;
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &sub);
+    });
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    let detail = client.will_next_charge_succeed(&id).unwrap();
+    assert_eq!(detail.available, 4_000_000);
+    assert_eq!(detail.required, 10_000_000);
+    assert_eq!(detail.shortfall(), 6_000_000);
+    assert_eq!(
+        client.get_subscription(&id).status,
+        SubscriptionStatus::Active
+    );
+}
+
+#[test]
+fn test_will_next_charge_succeed_not_yet_due() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    env.ledger().set_timestamp(T0 + INTERVAL - 1);
+    assert_eq!(
+        client.will_next_charge_succeed(&id),
+        Some(InsufficientBalanceError::new(PREPAID, 10_000_000))
+    );
+
+    assert_eq!(
+        client.will_next_charge_succeed(&999),
+        Some(InsufficientBalanceError::new(0, 0))
+    );
+}
+
+#[test]
+fn test_will_next_charge_succeed_follows_charge_rules() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let subscriber = client.get_subscription(&id).subscriber;
+    env.ledger().set_timestamp(T0 + INTERVAL);
+
+    // Due and funded, but a paused subscription is not charged.
+    client.pause_subscription(&id, &subscriber, &None);
+    assert_eq!(
+        client.will_next_charge_succeed(&id),
+        Some(InsufficientBalanceError::new(PREPAID, 10_000_000))
+    );
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::NotActive))
+    );
+}
+
+// =============================================================================
+// Plan Template Tests
+// =============================================================================
+
+#[test]
+fn test_plan_interval_presets() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let day = 24 * 60 * 60u64;
+
+    let weekly = client.create_weekly_plan(&merchant, &1_000_000i128, &false);
+    let monthly = client.create_monthly_plan(&merchant, &4_000_000i128, &false);
+    let annual = client.create_annual_plan(&merchant, &40_000_000i128, &true);
+
+    assert_eq!(client.get_plan_template(&weekly).interval_seconds, 7 * day);
+    assert_eq!(
+        client.get_plan_template(&monthly).interval_seconds,
+        30 * day
+    );
+    let annual_plan = client.get_plan_template(&annual);
+    assert_eq!(annual_plan.interval_seconds, 365 * day);
+    assert_eq!(annual_plan.amount, 40_000_000);
+    assert_eq!(annual_plan.merchant, merchant);
+    assert!(annual_plan.usage_enabled);
+}
+
+#[test]
+fn test_create_subscription_from_plan_copies_terms() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let subscriber = Address::generate(&env);
+
+    let plan_id = client.create_plan_template(&merchant, &2_500_000i128, &3600, &true);
+    let id = client.create_subscription_from_plan(&subscriber, &plan_id);
+
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.subscriber, subscriber);
+    assert_eq!(sub.merchant, merchant);
+    assert_eq!(sub.amount, 2_500_000);
+    assert_eq!(sub.interval_seconds, 3600);
+    assert!(sub.usage_enabled);
+    assert_eq!(sub.status, SubscriptionStatus::Active);
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 1);
+
+    let res = client.try_create_subscription_from_plan(&subscriber, &99);
+    assert_eq!(res, Err(Ok(Error::NotFound)));
+}
+
+#[test]
+fn test_create_plan_template_validates_terms() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+
+    assert_eq!(
+        client.try_create_plan_template(&merchant, &0i128, &3600, &false),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_create_plan_template(&merchant, &-5i128, &3600, &false),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_create_plan_template(&merchant, &1_000i128, &0, &false),
+        Err(Ok(Error::InvalidInput))
+    );
+    assert_eq!(
+        client.try_create_plan_template(
+            &merchant,
+            &1_000i128,
+            &(crate::plan::MAX_PLAN_INTERVAL_SECONDS + 1),
+            &false
+        ),
+        Err(Ok(Error::InvalidInput))
+    );
+    assert_eq!(client.get_merchant_plans(&merchant).len(), 0);
+
+    let plan_id = client.create_plan_template(
+        &merchant,
+        &1_000i128,
+        &crate::plan::MAX_PLAN_INTERVAL_SECONDS,
+        &false,
+    );
+    assert_eq!(client.get_plan_template(&plan_id).amount, 1_000);
+}
+
+#[test]
+fn test_get_merchant_plans_lists_all_templates() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let other = Address::generate(&env);
+
+    let weekly = client.create_weekly_plan(&merchant, &1_000_000i128, &false);
+    client.create_monthly_plan(&other, &9_000_000i128, &false);
+    let monthly = client.create_monthly_plan(&merchant, &4_000_000i128, &false);
+    let annual = client.create_annual_plan(&merchant, &40_000_000i128, &true);
+
+    let ids = client.get_merchant_plans(&merchant);
+    assert_eq!(ids.len(), 3);
+    assert_eq!(ids.get(0).unwrap(), weekly);
+    assert_eq!(ids.get(1).unwrap(), monthly);
+    assert_eq!(ids.get(2).unwrap(), annual);
+
+    let plans = client.get_merchant_plan_templates(&merchant);
+    assert_eq!(plans.len(), 3);
+    for i in 0..3 {
+        assert_eq!(
+            plans.get(i).unwrap(),
+            client.get_plan_template(&ids.get(i).unwrap())
+        );
+        assert_eq!(plans.get(i).unwrap().merchant, merchant);
+    }
+
+    assert_eq!(client.get_merchant_plans(&other).len(), 1);
+    assert_eq!(
+        client
+            .get_merchant_plan_templates(&Address::generate(&env))
+            .len(),
+        0
+    );
+}
+
+#[test]
+fn test_deactivated_plan_blocks_new_subscriptions_only() {
+    let (env, client, _, _) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+    let merchant = Address::generate(&env);
+    let subscriber = Address::generate(&env);
+
+    let plan_id = client.create_plan_template(&merchant, &1000i128, &INTERVAL, &false);
+    assert!(client.get_plan_template(&plan_id).active);
+    let existing = client.create_subscription_from_plan(&subscriber, &plan_id);
+
+    client.deactivate_plan_template(&merchant, &plan_id);
+    assert!(!client.get_plan_template(&plan_id).active);
+
+    let res = client.try_create_subscription_from_plan(&subscriber, &plan_id);
+    assert_eq!(res, Err(Ok(Error::PlanInactive)));
+
+    // The existing subscription keeps billing normally.
+    let mut sub = client.get_subscription(&existing);
+    sub.prepaid_balance = 5000;
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&existing, &sub);
+    });
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&existing);
+    let sub = client.get_subscription(&existing);
+    assert_eq!(sub.prepaid_balance, 4000);
+    assert_eq!(sub.status, SubscriptionStatus::Active);
+}
+
+#[test]
+fn test_deactivate_plan_requires_owning_merchant() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let plan_id = client.create_monthly_plan(&merchant, &1000i128, &false);
+
+    let res = client.try_deactivate_plan_template(&Address::generate(&env), &plan_id);
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+    assert!(client.get_plan_template(&plan_id).active);
+
+    let res = client.try_deactivate_plan_template(&merchant, &42);
+    assert_eq!(res, Err(Ok(Error::NotFound)));
+}
+
+// =============================================================================
+// Merchant Index Bounding Tests
+// =============================================================================
+
+#[test]
+fn test_cancel_removes_id_from_merchant_index() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+
+    let id0 =
+        client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
+    client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 2);
+
+    client.cancel_subscription(&id0, &subscriber);
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 1);
+    let subs = client.get_subscriptions_by_merchant(&merchant, &0, &10);
+    assert_eq!(subs.len(), 1);
+    assert_eq!(subs.get(0).unwrap().status, SubscriptionStatus::Active);
+
+    // Idempotent cancel leaves the index untouched.
+    client.cancel_subscription(&id0, &subscriber);
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 1);
+
+    // The cancelled record itself is still readable.
+    assert_eq!(
+        client.get_subscription(&id0).status,
+        SubscriptionStatus::Cancelled
+    );
+}
+
+#[test]
+fn test_merchant_index_stays_bounded_across_churn() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+
+    for _ in 0..20 {
+        let id =
+            client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
+        client.cancel_subscription(&id, &subscriber);
+    }
+    let live =
+        client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
+
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 1);
+    let subs = client.get_subscriptions_by_merchant(&merchant, &0, &10);
+    assert_eq!(subs.len(), 1);
+    assert_eq!(client.get_subscription(&live).merchant, merchant);
+}
+
+#[test]
+fn test_compact_merchant_index_drops_terminal_ids() {
+    let (env, client, _, admin) = setup_test_env();
+    let (id, _, merchant) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let (_, _, other_merchant) =
+        create_test_subscription(&env, &client, SubscriptionStatus::Active);
+
+    // Simulate a legacy index entry for a subscription cancelled without pruning.
+    let mut sub = client.get_subscription(&id);
+    sub.status = SubscriptionStatus::Cancelled;
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &sub);
+    });
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 1);
+
+    assert_eq!(client.compact_merchant_index(&admin, &merchant), 1);
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 0);
+
+    // Other merchants are untouched and a second compaction is a no-op.
+    assert_eq!(client.get_merchant_subscription_count(&other_merchant), 1);
+    assert_eq!(client.compact_merchant_index(&admin, &merchant), 0);
+
+    let stranger = Address::generate(&env);
+    let res = client.try_compact_merchant_index(&stranger, &merchant);
+    assert_eq!(res, Err(Ok(Error::Forbidden)));
+}
+
+// =============================================================================
+// Safe Math Tests
+// =============================================================================
+
+#[test]
+fn test_safe_sub_balance_boundaries() {
+    use crate::safe_math::safe_sub_balance;
+
+    // Zero amount leaves the balance unchanged, including an empty balance.
+    assert_eq!(safe_sub_balance(1_000, 0), Ok(1_000));
+    assert_eq!(safe_sub_balance(0, 0), Ok(0));
+    // Exact debit drains to zero.
+    assert_eq!(safe_sub_balance(1_000, 1_000), Ok(0));
+    // One unit past the balance underflows rather than going negative.
+    assert_eq!(safe_sub_balance(1_000, 1_001), Err(Error::Underflow));
+    assert_eq!(safe_sub_balance(0, 1), Err(Error::Underflow));
+    // Negative amounts are rejected.
+    assert_eq!(safe_sub_balance(1_000, -1), Err(Error::Underflow));
+    // i128::MIN boundary reports Underflow, not Overflow.
+    assert_eq!(safe_sub_balance(i128::MIN, 1), Err(Error::Underflow));
+}
+
+#[test]
+fn test_safe_add_balance_boundaries() {
+    use crate::safe_math::safe_add_balance;
+
+    assert_eq!(safe_add_balance(0, 0), Ok(0));
+    assert_eq!(safe_add_balance(i128::MAX - 1, 1), Ok(i128::MAX));
+    assert_eq!(safe_add_balance(i128::MAX, 1), Err(Error::Overflow));
+    assert_eq!(safe_add_balance(10, -1), Err(Error::Underflow));
+}
+
+#[test]
+fn test_one_off_charge_exact_balance_uses_safe_math() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
+
+    client.charge_one_off(&id, &merchant, &PREPAID);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 0);
+
+    let res = client.try_charge_one_off(&id, &merchant, &1i128);
+    assert_eq!(res, Err(Ok(Error::InsufficientPrepaidBalance)));
+}
+
+// =============================================================================
+// Paused Duration Accounting Tests
+// =============================================================================
+
+#[test]
+fn test_pause_duration_shifts_next_charge() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let subscriber = client.get_subscription(&id).subscriber;
+    let paused_for = 5 * 24 * 60 * 60;
+
+    env.ledger().set_timestamp(T0 + 10);
+    client.pause_subscription(&id, &subscriber, &None);
+    env.ledger().set_timestamp(T0 + 10 + paused_for);
+    client.resume_subscription(&id, &subscriber);
+
+    assert_eq!(client.get_paused_seconds(&id), paused_for);
+    assert_eq!(
+        client.get_next_charge_info(&id).next_charge_timestamp,
+        T0 + INTERVAL + paused_for
+    );
+
+    // The original due time is no longer chargeable.
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    let res = client.try_charge_subscription(&id);
+    assert_eq!(res, Err(Ok(Error::IntervalNotElapsed)));
+
+    // The shifted due time is.
+    env.ledger().set_timestamp(T0 + INTERVAL + paused_for);
+    client.charge_subscription(&id);
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.prepaid_balance, PREPAID - 10_000_000);
+    assert_eq!(sub.last_payment_timestamp, T0 + INTERVAL + paused_for);
+    // Credit is consumed by the charge.
+    assert_eq!(client.get_paused_seconds(&id), 0);
+}
+
+#[test]
+fn test_repeated_pauses_accumulate() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let subscriber = client.get_subscription(&id).subscriber;
+
+    env.ledger().set_timestamp(T0 + 100);
+    client.pause_subscription(&id, &subscriber, &None);
+    // Idempotent re-pause does not restart the clock.
+    env.ledger().set_timestamp(T0 + 150);
+    client.pause_subscription(&id, &subscriber, &None);
+    env.ledger().set_timestamp(T0 + 200);
+    client.resume_subscription(&id, &subscriber);
+
+    env.ledger().set_timestamp(T0 + 1_000);
+    client.pause_subscription(&id, &subscriber, &None);
+    env.ledger().set_timestamp(T0 + 1_300);
+    client.resume_subscription(&id, &subscriber);
+
+    assert_eq!(client.get_paused_seconds(&id), 400);
+}
+
+// =============================================================================
+// Partial Charge Tests
+// =============================================================================
+
+#[test]
+fn test_partial_charge_debits_remaining_balance_and_records_arrears() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
+
+    let mut sub = client.get_subscription(&id);
+    sub.prepaid_balance = 4_000_000;
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &sub);
+    });
+    client.set_allow_partial_charge(&id, &merchant, &true);
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.prepaid_balance, 0);
+    assert_eq!(sub.status, SubscriptionStatus::GracePeriod);
+    assert_eq!(sub.last_payment_timestamp, T0 + INTERVAL);
+    env.as_contract(&client.address, || {
+        assert_eq!(crate::charge_core::get_arrears(&env, id), 6_000_000);
+        assert_eq!(
+            crate::merchant::get_merchant_balance(&env, &merchant),
+            4_000_000
+        );
+    });
+}
+
+#[test]
+fn test_partial_charge_disabled_fails_whole_charge() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    let mut sub = client.get_subscription(&id);
+    sub.prepaid_balance = 4_000_000;
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &sub);
+    });
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    let res = client.try_charge_subscription(&id);
+    assert_eq!(res, Err(Ok(Error::InsufficientBalance)));
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 4_000_000);
+    assert_eq!(client.get_arrears(&id), 0);
+}
+
+#[test]
+fn test_set_allow_partial_charge_requires_merchant() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let subscriber = client.get_subscription(&id).subscriber;
+
+    let res = client.try_set_allow_partial_charge(&id, &subscriber, &true);
+    assert_eq!(res, Err(Ok(Error::Unauthorized)));
+    assert!(!client.get_allow_partial_charge(&id));
+}
+
+/// Helper: funded subscription whose first charge is partial (4 collected, 6 in arrears).
+fn setup_arrears_subscription() -> (Env, SubscriptionVaultClient<'static>, u32, Address) {
+    let (env, client, token, _) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    mint_for_subscriber(&env, &token, &subscriber, BATCH_MINT);
+
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &10_000_000i128,
+        &INTERVAL,
+        &false,
+        &None,
+    );
+    client.set_allow_partial_charge(&id, &merchant, &true);
+    client.deposit_funds(&id, &subscriber, &4_000_000i128, &None);
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+    assert_eq!(client.get_arrears(&id), 6_000_000);
+
+    (env, client, id, subscriber)
+}
+
+#[test]
+fn test_arrears_collected_before_interval_charge() {
+    let (env, client, id, subscriber) = setup_arrears_subscription();
+    let merchant = client.get_subscription(&id).merchant;
+
+    // Enough for the arrears plus one interval.
+    client.deposit_funds(&id, &subscriber, &16_000_000i128, &None);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id);
+
+    let sub = client.get_subscription(&id);
+    assert_eq!(client.get_arrears(&id), 0);
+    assert_eq!(sub.prepaid_balance, 0);
+    assert_eq!(sub.status, SubscriptionStatus::Active);
+    assert_eq!(sub.last_payment_timestamp, T0 + 2 * INTERVAL);
+    env.as_contract(&client.address, || {
+        // 4 (partial) + 6 (arrears) + 10 (interval)
+        assert_eq!(
+            crate::merchant::get_merchant_balance(&env, &merchant),
+            20_000_000
+        );
+    });
+}
+
+#[test]
+fn test_arrears_must_clear_before_normal_billing_resumes() {
+    let (env, client, id, subscriber) = setup_arrears_subscription();
+
+    let merchant = client.get_subscription(&id).merchant;
+    client.set_allow_partial_charge(&id, &merchant, &false);
+
+    // Enough to cover the arrears but not the interval: the charge fails and,
+    // being atomic, leaves the arrears and balance untouched.
+    client.deposit_funds(&id, &subscriber, &12_000_000i128, &None);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    let res = client.try_charge_subscription(&id);
+    assert_eq!(res, Err(Ok(Error::InsufficientBalance)));
+    assert_eq!(client.get_arrears(&id), 6_000_000);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 12_000_000);
+
+    // Once both are covered, arrears clear first and billing resumes.
+    client.deposit_funds(&id, &subscriber, &4_000_000i128, &None);
+    client.charge_subscription(&id);
+    assert_eq!(client.get_arrears(&id), 0);
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.prepaid_balance, 0);
+    assert_eq!(sub.status, SubscriptionStatus::Active);
+}
+
+// =============================================================================
+// Deposit Memo Tests
+// =============================================================================
+
+fn last_deposit_event(env: &Env) -> FundsDepositedEvent {
+    let (_, _, data) = env.events().all().last().unwrap();
+    FundsDepositedEvent::try_from_val(env, &data).unwrap()
+}
+
+#[test]
+fn test_deposit_memo_included_in_event() {
+    let (env, client, token, _) = setup_test_env();
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    mint_for_subscriber(&env, &token, &subscriber, 20_000_000);
+
+    let memo = BytesN::from_array(&env, &[7u8; 32]);
+    client.deposit_funds(&id, &subscriber, &5_000_000i128, &Some(memo.clone()));
+
+    let event = last_deposit_event(&env);
+    assert_eq!(event.subscription_id, id);
+    assert_eq!(event.subscriber, subscriber);
+    assert_eq!(event.amount, 5_000_000);
+    assert_eq!(event.new_balance, 5_000_000);
+    assert_eq!(event.memo, memo);
+}
+
+#[test]
+fn test_deposit_without_memo_emits_zero_memo() {
+    let (env, client, token, _) = setup_test_env();
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    mint_for_subscriber(&env, &token, &subscriber, 20_000_000);
+
+    client.deposit_funds(&id, &subscriber, &5_000_000i128, &None);
+
+    let event = last_deposit_event(&env);
+    assert_eq!(event.memo, BytesN::from_array(&env, &[0u8; 32]));
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 5_000_000);
+}
+
+// =============================================================================
+// is_charge_due Tests
+// =============================================================================
+
+#[test]
+fn test_is_charge_due_boundary() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    env.ledger().set_timestamp(T0 + INTERVAL - 1);
+    assert!(!client.is_charge_due(&id));
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert!(client.is_charge_due(&id));
+
+    env.ledger().set_timestamp(T0 + INTERVAL + 1);
+    assert!(client.is_charge_due(&id));
+}
+
+#[test]
+fn test_is_charge_due_ignores_balance_and_requires_active() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let subscriber = client.get_subscription(&id).subscriber;
+
+    // Balance is irrelevant: due even when empty.
+    let contract_id = client.address.clone();
+    let mut sub = client.get_subscription(&id);
+    sub.prepaid_balance = 0;
+    env.as_contract(&contract_id, || {
+        env.storage().instance().set(&id, &sub);
+    });
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert!(client.is_charge_due(&id));
+
+    client.pause_subscription(&id, &subscriber, &None);
+    assert!(!client.is_charge_due(&id));
+}
+
+#[test]
+fn test_is_charge_due_not_found() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup(&env, INTERVAL);
+    assert_eq!(client.try_is_charge_due(&999), Err(Ok(Error::NotFound)));
+}
+
+// =============================================================================
+// Admin Force-Cancel Tests
+// =============================================================================
+
+#[test]
+fn test_admin_force_cancel_refunds_subscriber() {
+    let (env, client, token, admin) = setup_test_env();
+    let (id, subscriber, merchant) =
+        create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    mint_for_subscriber(&env, &token, &subscriber, 20_000_000);
+    client.deposit_funds(&id, &subscriber, &20_000_000i128, &None);
+
+    client.admin_force_cancel(&admin, &id, &RecoveryReason::DeprecatedFlow);
+
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.status, SubscriptionStatus::Cancelled);
+    assert_eq!(sub.prepaid_balance, 0);
+    let token_client = soroban_sdk::token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&subscriber), 20_000_000);
+    assert_eq!(token_client.balance(&client.address), 0);
+    assert_eq!(
+        client
+            .get_subscriptions_by_merchant(&merchant, &0, &10)
+            .len(),
+        0
+    );
+}
+
+#[test]
+fn test_admin_force_cancel_emits_cancelled_with_fraud_reason() {
+    let (env, client, token, admin) = setup_test_env();
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    mint_for_subscriber(&env, &token, &subscriber, 20_000_000);
+    client.deposit_funds(&id, &subscriber, &20_000_000i128, &None);
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(
+            &crate::types::DataKey::ScheduledCancellation(id),
+            &(
+                T0 + WEEK,
+                subscriber.clone(),
+                CancellationReason::UserRequested,
+            ),
+        );
+    });
+
+    client.admin_force_cancel(&admin, &id, &RecoveryReason::DeprecatedFlow);
+    let cancelled_topic: soroban_sdk::Vec<soroban_sdk::Val> =
+        (soroban_sdk::symbol_short!("cancelled"), id).into_val(&env);
+    let (_, _, data) = env
+        .events()
+        .all()
+        .iter()
+        .find(|(_, topics, _)| *topics == cancelled_topic)
+        .unwrap();
+    let event = SubscriptionCancelledEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(event.authorizer, admin);
+    assert_eq!(event.reason, CancellationReason::Fraud);
+    assert_eq!(client.get_scheduled_cancellation(&id), None);
+    assert_eq!(client.get_balance_sheet().prepaid_liabilities, 0);
+}
+
+#[test]
+fn test_admin_force_cancel_rejects_non_admin() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let stranger = Address::generate(&env);
+
+    let result = client.try_admin_force_cancel(&stranger, &id, &RecoveryReason::AccidentalTransfer);
+    assert_eq!(result, Err(Ok(Error::Forbidden)));
+    assert_eq!(
+        client.get_subscription(&id).status,
+        SubscriptionStatus::Active
+    );
+}
+
+// =============================================================================
+// Refund Address Tests
+// =============================================================================
+
+#[test]
+fn test_cancel_refund_goes_to_refund_address() {
+    let (env, client, token, _) = setup_test_env();
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let employer = Address::generate(&env);
+    mint_for_subscriber(&env, &token, &subscriber, 15_000_000);
+    client.deposit_funds(&id, &subscriber, &15_000_000i128, &None);
+
+    client.set_refund_address(&id, &subscriber, &employer);
+    assert_eq!(client.get_refund_address(&id), Some(employer.clone()));
+    client.cancel_subscription(&id, &subscriber);
+    client.withdraw_subscriber_funds(&id, &subscriber);
+
+    let token_client = soroban_sdk::token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&employer), 15_000_000);
+    assert_eq!(token_client.balance(&subscriber), 0);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 0);
+}
+
+#[test]
+fn test_cancel_refund_defaults_to_subscriber() {
+    let (env, client, token, _) = setup_test_env();
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    mint_for_subscriber(&env, &token, &subscriber, 15_000_000);
+    client.deposit_funds(&id, &subscriber, &15_000_000i128, &None);
+
+    assert_eq!(client.get_refund_address(&id), None);
+    client.cancel_subscription(&id, &subscriber);
+    client.withdraw_subscriber_funds(&id, &subscriber);
+
+    let token_client = soroban_sdk::token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&subscriber), 15_000_000);
+}
+
+#[test]
+fn test_admin_force_cancel_refunds_to_refund_address() {
+    let (env, client, token, admin) = setup_test_env();
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let employer = Address::generate(&env);
+    mint_for_subscriber(&env, &token, &subscriber, 15_000_000);
+    client.deposit_funds(&id, &subscriber, &15_000_000i128, &None);
+    client.set_refund_address(&id, &subscriber, &employer);
+
+    client.admin_force_cancel(&admin, &id, &RecoveryReason::DeprecatedFlow);
+
+    let token_client = soroban_sdk::token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&employer), 15_000_000);
+}
+
+#[test]
+fn test_admin_force_cancel_settles_pending_usage_first() {
+    let (env, client, token, admin) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &10_000_000i128,
+        &INTERVAL,
+        &true,
+        &None,
+    );
+    mint_for_subscriber(&env, &token, &subscriber, 15_000_000);
+    client.deposit_funds(&id, &subscriber, &15_000_000i128, &None);
+    client.record_usage(&id, &merchant, &4_000_000i128);
+
+    client.admin_force_cancel(&admin, &id, &RecoveryReason::DeprecatedFlow);
+
+    let token_client = soroban_sdk::token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&subscriber), 11_000_000);
+    assert_eq!(merchant_balance(&env, &client, &merchant), 4_000_000);
+    assert_eq!(client.get_pending_usage(&id), 0);
+    assert_eq!(client.get_balance_sheet().prepaid_liabilities, 0);
+}
+
+#[test]
+fn test_set_refund_address_rejects_non_subscriber() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, _, merchant) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let other = Address::generate(&env);
+
+    let result = client.try_set_refund_address(&id, &merchant, &other);
+    assert_eq!(result, Err(Ok(Error::Forbidden)));
+}
+
+// =============================================================================
+// charge_subscription_detailed Tests
+// =============================================================================
+
+#[test]
+fn test_charge_subscription_detailed_returns_next_charge() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    let now = T0 + INTERVAL + 50;
+    env.ledger().set_timestamp(now);
+    let info = client.charge_subscription_detailed(&id);
+
+    assert_eq!(info.next_charge_timestamp, now + INTERVAL);
+    assert!(info.is_charge_expected);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 10_000_000
+    );
+}
+
+#[test]
+fn test_charge_subscription_detailed_propagates_errors() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    env.ledger().set_timestamp(T0 + INTERVAL - 1);
+    assert_eq!(
+        client.try_charge_subscription_detailed(&id),
+        Err(Ok(Error::IntervalNotElapsed))
+    );
+}
+
+// =============================================================================
+// Deferred Start Tests
+// =============================================================================
+
+fn setup_starting_subscription(env: &Env, start: u64) -> (SubscriptionVaultClient<'_>, u32) {
+    let contract_id = env.register(SubscriptionVault, ());
+    let client = SubscriptionVaultClient::new(env, &contract_id);
+    client.init(
+        &Address::generate(env),
+        &7,
+        &Address::generate(env),
+        &1_000000i128,
+        &43200,
+    );
+
+    env.ledger().set_timestamp(T0);
+    let id = client.create_subscription_starting(
+        &Address::generate(env),
+        &Address::generate(env),
+        &10_000_000i128,
+        &INTERVAL,
+        &false,
+        &start,
+    );
+
+    let mut sub = client.get_subscription(&id);
+    sub.prepaid_balance = PREPAID;
+    env.as_contract(&contract_id, || {
+        env.storage().instance().set(&id, &sub);
+    });
+    (client, id)
+}
+
+#[test]
+fn test_charge_before_start_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let start = T0 + 2 * INTERVAL;
+    let (client, id) = setup_starting_subscription(&env, start);
+
+    assert_eq!(client.get_start_timestamp(&id), Some(start));
+    assert_eq!(
+        client.get_next_charge_info(&id).next_charge_timestamp,
+        start
+    );
+
+    // One interval after creation is still before the start time.
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::NotStarted))
+    );
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID);
+}
+
+#[test]
+fn test_charge_at_and_after_start() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let start = T0 + 2 * INTERVAL;
+    let (client, id) = setup_starting_subscription(&env, start);
+
+    env.ledger().set_timestamp(start);
+    client.charge_subscription(&id);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 10_000_000
+    );
+
+    // Normal interval billing resumes from the first charge.
+    assert_eq!(
+        client.get_next_charge_info(&id).next_charge_timestamp,
+        start + INTERVAL
+    );
+    env.ledger().set_timestamp(start + INTERVAL - 1);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::IntervalNotElapsed))
+    );
+    env.ledger().set_timestamp(start + INTERVAL);
+    client.charge_subscription(&id);
+}
+
+#[test]
+fn test_create_subscription_starting_rejects_past_start() {
+    let (env, client, _, _) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+
+    let result = client.try_create_subscription_starting(
+        &Address::generate(&env),
+        &Address::generate(&env),
+        &10_000_000i128,
+        &INTERVAL,
+        &false,
+        &(T0 - 1),
+    );
+    assert_eq!(result, Err(Ok(Error::InvalidInput)));
+}
+
+// =============================================================================
+// Merchant Payout Address Tests
+// =============================================================================
+
+/// Creates a funded subscription and charges it once, accruing 10 USDC to the merchant.
+fn setup_merchant_earnings(
+    env: &Env,
+    client: &SubscriptionVaultClient,
+    token: &Address,
+) -> Address {
+    let (id, subscriber, merchant) =
+        create_test_subscription(env, client, SubscriptionStatus::Active);
+    mint_for_subscriber(env, token, &subscriber, 20_000_000);
+    client.deposit_funds(&id, &subscriber, &20_000_000i128, &None);
+    env.ledger().set_timestamp(INTERVAL);
+    client.charge_subscription(&id);
+    merchant
+}
+
+#[test]
+fn test_withdraw_merchant_funds_routes_to_payout_address() {
+    let (env, client, token, _) = setup_test_env();
+    let merchant = setup_merchant_earnings(&env, &client, &token);
+    let cold_wallet = Address::generate(&env);
+
+    client.set_payout_address(&merchant, &cold_wallet);
+    assert_eq!(client.get_payout_address(&merchant), cold_wallet);
+    client.withdraw_merchant_funds(&merchant, &6_000_000i128);
+
+    let token_client = soroban_sdk::token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&cold_wallet), 6_000_000);
+    assert_eq!(token_client.balance(&merchant), 0);
+    env.as_contract(&client.address, || {
+        assert_eq!(
+            crate::merchant::get_merchant_balance(&env, &merchant),
+            4_000_000
+        );
+    });
+}
+
+#[test]
+fn test_withdraw_merchant_funds_defaults_to_merchant() {
+    let (env, client, token, _) = setup_test_env();
+    let merchant = setup_merchant_earnings(&env, &client, &token);
+
+    assert_eq!(client.get_payout_address(&merchant), merchant);
+    client.withdraw_merchant_funds(&merchant, &10_000_000i128);
+
+    let token_client = soroban_sdk::token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&merchant), 10_000_000);
+}
+
+#[test]
+fn test_withdraw_merchant_funds_cannot_exceed_balance() {
+    let (env, client, token, _) = setup_test_env();
+    let merchant = setup_merchant_earnings(&env, &client, &token);
+
+    let result = client.try_withdraw_merchant_funds(&merchant, &10_000_001i128);
+    assert_eq!(result, Err(Ok(Error::InsufficientBalance)));
+}
+
+// =============================================================================
+// Deposit Ordering Tests
+// =============================================================================
+
+/// Token stub whose `transfer` always traps.
+#[soroban_sdk::contract]
+struct TrappingToken;
+
+#[soroban_sdk::contractimpl]
+impl TrappingToken {
+    pub fn transfer(_env: Env, _from: Address, _to: Address, _amount: i128) {
+        panic!("transfer disabled");
+    }
+}
+
+#[test]
+fn test_deposit_failed_transfer_leaves_balance_unchanged() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let token = env.register(TrappingToken, ());
+    let contract_id = env.register(SubscriptionVault, ());
+    let client = SubscriptionVaultClient::new(&env, &contract_id);
+    client.init(&token, &7, &Address::generate(&env), &1_000000i128, &43200);
+
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+
+    let result = client.try_deposit_funds(&id, &subscriber, &5_000_000i128, &None);
+    assert!(result.is_err());
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 0);
+}
+
+// =============================================================================
+// simulate_batch_charge Tests
+// =============================================================================
+
+#[test]
+fn test_simulate_batch_charge_matches_batch_charge() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id0).subscriber;
+    let merchant = client.get_subscription(&id0).merchant;
+
+    // Paused and not-yet-due subscriptions, both funded.
+    let id_paused =
+        client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
+    client.deposit_funds(&id_paused, &subscriber, &10_000000i128, &None);
+    client.pause_subscription(&id_paused, &subscriber, &None);
+    let id_early =
+        client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
+    client.deposit_funds(&id_early, &subscriber, &10_000000i128, &None);
+
+    let ids = SorobanVec::from_array(&env, [id0, id1, id_paused, id_early, 999]);
+    let simulated = client.simulate_batch_charge(&ids);
+
+    // The dry run leaves state untouched.
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 10_000000);
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::Active
+    );
+
+    let actual = client.batch_charge(&ids);
+    assert_eq!(simulated.len(), actual.len());
+    for i in 0..actual.len() {
+        let (sim, act) = (simulated.get(i).unwrap(), actual.get(i).unwrap());
+        assert_eq!(sim.success, act.success);
+        assert_eq!(sim.error_code, act.error_code);
+    }
+    assert!(simulated.get(0).unwrap().success);
+    assert_eq!(
+        simulated.get(1).unwrap().error_code,
+        Error::InsufficientBalance.to_code()
+    );
+    assert_eq!(
+        simulated.get(2).unwrap().error_code,
+        Error::NotActive.to_code()
+    );
+    assert_eq!(
+        simulated.get(3).unwrap().error_code,
+        Error::IntervalNotElapsed.to_code()
+    );
+    assert_eq!(
+        simulated.get(4).unwrap().error_code,
+        Error::NotFound.to_code()
+    );
+}
+
+#[test]
+fn test_batch_charge_failure_leaves_arrears_uncollected() {
+    let (env, client, id, subscriber) = setup_arrears_subscription();
+    let merchant = client.get_subscription(&id).merchant;
+    client.set_allow_partial_charge(&id, &merchant, &false);
+    let merchant_before = env.as_contract(&client.address, || {
+        crate::merchant::get_merchant_balance(&env, &merchant)
+    });
+
+    // Covers the arrears but not the interval. batch_charge keeps a failed slot's
+    // writes, so the arrears must not have been collected on the way.
+    client.deposit_funds(&id, &subscriber, &12_000_000i128, &None);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    let results = client.batch_charge(&soroban_sdk::vec![&env, id]);
+    assert!(!results.get(0).unwrap().success);
+
+    assert_eq!(client.get_arrears(&id), 6_000_000);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 12_000_000);
+    env.as_contract(&client.address, || {
+        assert_eq!(
+            crate::merchant::get_merchant_balance(&env, &merchant),
+            merchant_before
+        );
+    });
+}
+
+#[test]
+fn test_simulate_batch_charge_accounts_for_partial_and_arrears() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
+    let ids = SorobanVec::from_array(&env, [id]);
+
+    // 5M prepaid against a 10M amount: only a partial charge can succeed.
+    let contract_id = client.address.clone();
+    let mut sub = client.get_subscription(&id);
+    sub.prepaid_balance = 5_000_000;
+    env.as_contract(&contract_id, || {
+        env.storage().instance().set(&id, &sub);
+    });
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert!(!client.simulate_batch_charge(&ids).get(0).unwrap().success);
+
+    client.set_allow_partial_charge(&id, &merchant, &true);
+    assert!(client.simulate_batch_charge(&ids).get(0).unwrap().success);
+    assert!(client.batch_charge(&ids).get(0).unwrap().success);
+    assert_eq!(client.get_arrears(&id), 5_000_000);
+}
+
+// =============================================================================
+// Storage Version Gate Tests
+// =============================================================================
+
+/// Simulates a deployment initialized before the storage version was recorded.
+fn clear_storage_version(env: &Env, client: &SubscriptionVaultClient) {
+    env.as_contract(&client.address, || {
+        env.storage()
+            .instance()
+            .remove(&soroban_sdk::Symbol::new(env, "storage_version"));
+    });
+}
+
+/// Rewrites subscription `id` in the version 1 (pre-versioning) layout.
+fn write_v1_record(env: &Env, client: &SubscriptionVaultClient, id: u32) {
+    let sub = client.get_subscription(&id);
+    let v1 = SubscriptionV1 {
+        subscriber: sub.subscriber,
+        merchant: sub.merchant,
+        amount: sub.amount,
+        interval_seconds: sub.interval_seconds,
+        last_payment_timestamp: sub.last_payment_timestamp,
+        status: sub.status,
+        prepaid_balance: sub.prepaid_balance,
+        usage_enabled: sub.usage_enabled,
+    };
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &v1);
+    });
+}
+
+#[test]
+fn test_init_records_storage_version() {
+    let (_, client, _, _) = setup_test_env();
+    assert_eq!(client.get_storage_version(), 6);
+}
+
+#[test]
+fn test_old_storage_version_blocks_charge_until_migrated() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+    env.ledger().set_timestamp(T0 + INTERVAL);
+
+    write_v1_record(&env, &client, id);
+    clear_storage_version(&env, &client);
+    assert_eq!(client.get_storage_version(), 0);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::MigrationRequired))
+    );
+    // Reads stay available.
+    assert_eq!(client.get_admin(), admin);
+
+    assert_eq!(client.migrate(&admin, &100), 0);
+    assert_eq!(client.get_storage_version(), 6);
+    client.charge_subscription(&id);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 10_000_000
+    );
+}
+
+#[test]
+fn test_migrate_rejects_non_admin() {
+    let (env, client, _, _) = setup_test_env();
+    clear_storage_version(&env, &client);
+
+    let result = client.try_migrate(&Address::generate(&env), &100);
+    assert_eq!(result, Err(Ok(Error::Forbidden)));
+    assert_eq!(client.get_storage_version(), 0);
+}
+
+#[test]
+fn test_migrate_resumes_in_batches() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id0) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+    let sub = client.get_subscription(&id0);
+    let id1 = client.create_subscription(
+        &sub.subscriber,
+        &sub.merchant,
+        &sub.amount,
+        &INTERVAL,
+        &false,
+        &None,
+    );
+    let id2 = client.create_subscription(
+        &sub.subscriber,
+        &sub.merchant,
+        &sub.amount,
+        &INTERVAL,
+        &false,
+        &None,
+    );
+    for id in [id0, id1, id2] {
+        write_v1_record(&env, &client, id);
+    }
+    clear_storage_version(&env, &client);
+
+    // Three records to rewrite, then three to clean up after.
+    assert_eq!(client.migrate(&admin, &2), 4);
+    // Not stamped until every record is rewritten.
+    assert_eq!(client.get_storage_version(), 0);
+    assert_eq!(
+        client.try_charge_subscription(&id0),
+        Err(Ok(Error::MigrationRequired))
+    );
+
+    assert_eq!(client.migrate(&admin, &2), 2);
+    assert_eq!(client.migrate(&admin, &2), 0);
+    assert_eq!(client.get_storage_version(), 6);
+    for id in [id0, id1, id2] {
+        assert_eq!(client.get_subscription(&id).charge_count, 0);
+    }
+    // Already current: a further call is a no-op.
+    assert_eq!(client.migrate(&admin, &2), 0);
+}
+
+#[test]
+fn test_migrate_fails_on_undecodable_record() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+    // The record is still in the current layout, which does not decode as version 1.
+    clear_storage_version(&env, &client);
+
+    assert_eq!(
+        client.try_migrate(&admin, &100),
+        Err(Ok(Error::InvariantViolation))
+    );
+    assert_eq!(client.get_storage_version(), 0);
+}
+
+// =============================================================================
+// Amount Change Consent Tests
+// =============================================================================
+
+#[test]
+fn test_amount_change_applies_only_after_acceptance() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+
+    client.propose_amount_change(&id, &sub.merchant, &15_000_000i128);
+    assert_eq!(client.get_pending_amount_change(&id), Some(15_000_000));
+
+    // Unaccepted proposal: the charge uses the old amount.
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 10_000_000
+    );
+
+    client.accept_amount_change(&id, &sub.subscriber);
+    assert_eq!(client.get_subscription(&id).amount, 15_000_000);
+    assert_eq!(client.get_pending_amount_change(&id), None);
+
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 25_000_000
+    );
+}
+
+#[test]
+fn test_subscriber_can_reject_amount_change() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+
+    client.propose_amount_change(&id, &sub.merchant, &15_000_000i128);
+    client.reject_amount_change(&id, &sub.subscriber);
+
+    assert_eq!(client.get_pending_amount_change(&id), None);
+    assert_eq!(client.get_subscription(&id).amount, 10_000_000);
+    assert_eq!(
+        client.try_accept_amount_change(&id, &sub.subscriber),
+        Err(Ok(Error::NotFound))
+    );
+}
+
+#[test]
+fn test_amount_change_requires_matching_parties() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+    let other = Address::generate(&env);
+
+    assert_eq!(
+        client.try_propose_amount_change(&id, &other, &15_000_000i128),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.propose_amount_change(&id, &sub.merchant, &15_000_000i128);
+    assert_eq!(
+        client.try_accept_amount_change(&id, &sub.merchant),
+        Err(Ok(Error::Forbidden))
+    );
+    assert_eq!(client.get_subscription(&id).amount, 10_000_000);
+}
+
+// =============================================================================
+// Suspension Event Tests
+// =============================================================================
+
+/// Returns the `sub_suspended` events published for `id` in the last invocation.
+fn suspended_events(env: &Env, id: u32) -> SorobanVec<(Address, InsufficientBalanceError)> {
+    let topic: SorobanVec<soroban_sdk::Val> =
+        (soroban_sdk::Symbol::new(env, "sub_suspended"), id).into_val(env);
+    let mut out = SorobanVec::new(env);
+    for (_, topics, data) in env.events().all().iter() {
+        if topics == topic {
+            out.push_back(<(Address, InsufficientBalanceError)>::try_from_val(env, &data).unwrap());
+        }
+    }
+    out
+}
+
+#[test]
+fn test_sub_suspended_emitted_only_on_transition() {
+    let env = Env::default();
+    let (client, _admin, _id0, id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id1).subscriber;
+    let ids = SorobanVec::from_array(&env, [id1]);
+
+    // First failure enters the grace period: no suspension yet.
+    client.batch_charge(&ids);
+    assert_eq!(suspended_events(&env, id1).len(), 0);
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::GracePeriod
+    );
+
+    // Grace expired: the subscription is suspended and the event fires once.
+    env.ledger().set_timestamp(T0 + INTERVAL + 43200);
+    client.batch_charge(&ids);
+    let events = suspended_events(&env, id1);
+    assert_eq!(events.len(), 1);
+    let (who, detail) = events.get(0).unwrap();
+    assert_eq!(who, subscriber);
+    assert_eq!(detail, InsufficientBalanceError::new(0, 1000));
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::InsufficientBalance
+    );
+
+    // Further attempts fail with NotActive and emit nothing.
+    client.batch_charge(&ids);
+    assert_eq!(suspended_events(&env, id1).len(), 0);
+}
+
+#[test]
+fn test_sub_suspended_persisted_by_batch_after_direct_charge() {
+    let env = Env::default();
+    let (client, _admin, _id0, id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id1).subscriber;
+
+    // Past the grace window a direct charge still reports the failure, which rolls
+    // back the suspension and its event.
+    env.ledger().set_timestamp(T0 + INTERVAL + 43200);
+    assert_eq!(
+        client.try_charge_subscription(&id1),
+        Err(Ok(Error::InsufficientBalance))
+    );
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::Active
+    );
+
+    // A batch charge does not revert per item, so it records the suspension and
+    // keeps the event.
+    let ids = SorobanVec::from_array(&env, [id1]);
+    client.batch_charge(&ids);
+    let events = suspended_events(&env, id1);
+    assert_eq!(events.len(), 1);
+    assert_eq!(
+        events.get(0).unwrap(),
+        (subscriber, InsufficientBalanceError::new(0, 1000))
+    );
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::InsufficientBalance
+    );
+}
+
+// =============================================================================
+// Withdrawal Cooldown Tests
+// =============================================================================
+
+#[test]
+fn test_withdrawal_within_cooldown_rejected() {
+    let (env, client, token, admin) = setup_test_env();
+    let merchant = setup_merchant_earnings(&env, &client, &token);
+    client.set_withdrawal_cooldown(&admin, &3600);
+    assert_eq!(client.get_withdrawal_cooldown(), 3600);
+
+    client.withdraw_merchant_funds(&merchant, &4_000_000i128);
+    assert_eq!(client.get_last_withdrawal(&merchant), INTERVAL);
+
+    env.ledger().set_timestamp(INTERVAL + 3599);
+    assert_eq!(
+        client.try_withdraw_merchant_funds(&merchant, &1_000_000i128),
+        Err(Ok(Error::WithdrawalTooSoon))
+    );
+}
+
+#[test]
+fn test_withdrawal_after_cooldown_succeeds() {
+    let (env, client, token, admin) = setup_test_env();
+    let merchant = setup_merchant_earnings(&env, &client, &token);
+    client.set_withdrawal_cooldown(&admin, &3600);
+
+    client.withdraw_merchant_funds(&merchant, &4_000_000i128);
+    env.ledger().set_timestamp(INTERVAL + 3600);
+    client.withdraw_merchant_funds(&merchant, &6_000_000i128);
+
+    assert_eq!(client.get_last_withdrawal(&merchant), INTERVAL + 3600);
+    let token_client = soroban_sdk::token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&merchant), 10_000_000);
+}
+
+#[test]
+fn test_set_withdrawal_cooldown_requires_admin() {
+    let (env, client, _, _) = setup_test_env();
+    let result = client.try_set_withdrawal_cooldown(&Address::generate(&env), &3600);
+    assert_eq!(result, Err(Ok(Error::Forbidden)));
+}
+
+// =============================================================================
+// Invariant Self-Check Tests
+// =============================================================================
+
+#[test]
+fn test_verify_invariants_healthy_contract_passes() {
+    let (env, client, _, _) = setup_test_env();
+    for status in [
+        SubscriptionStatus::Active,
+        SubscriptionStatus::Paused,
+        SubscriptionStatus::Cancelled,
+    ] {
+        create_test_subscription(&env, &client, status);
+    }
+    client.verify_invariants(&0, &10);
+}
+
+#[test]
+fn test_verify_invariants_detects_negative_balance() {
+    let (env, client, _, _) = setup_test_env();
+    create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let (id, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+
+    let mut sub = client.get_subscription(&id);
+    sub.prepaid_balance = -1;
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &sub);
+    });
+
+    assert_eq!(
+        client.try_verify_invariants(&0, &10),
+        Err(Ok(Error::InvariantViolation))
+    );
+    // The corrupt record lies outside this range.
+    client.verify_invariants(&0, &1);
+}
+
+#[test]
+fn test_verify_invariants_detects_undecodable_record() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &99u32);
+    });
+
+    assert_eq!(
+        client.try_verify_invariants(&0, &10),
+        Err(Ok(Error::InvariantViolation))
+    );
+}
+
+// =============================================================================
+// Batch Create From Plan Tests
+// =============================================================================
+
+#[test]
+fn test_batch_create_from_plan_creates_one_per_subscriber() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let plan_id = client.create_monthly_plan(&merchant, &4_000_000i128, &false);
+
+    let mut subscribers = SorobanVec::new(&env);
+    for _ in 0..5 {
+        subscribers.push_back(Address::generate(&env));
+    }
+    let ids = client.batch_create_from_plan(&subscribers, &plan_id);
+
+    assert_eq!(ids.len(), 5);
+    for i in 0..5 {
+        let id = ids.get(i).unwrap();
+        assert!(!ids.slice(i + 1..).contains(id));
+        let sub = client.get_subscription(&id);
+        assert_eq!(sub.subscriber, subscribers.get(i).unwrap());
+        assert_eq!(sub.merchant, merchant);
+        assert_eq!(sub.amount, 4_000_000);
+    }
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 5);
+}
+
+#[test]
+fn test_batch_create_from_inactive_plan_rejected() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let plan_id = client.create_monthly_plan(&merchant, &4_000_000i128, &false);
+    client.deactivate_plan_template(&merchant, &plan_id);
+
+    let subscribers = SorobanVec::from_array(&env, [Address::generate(&env)]);
+    assert_eq!(
+        client.try_batch_create_from_plan(&subscribers, &plan_id),
+        Err(Ok(Error::PlanInactive))
+    );
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 0);
+}
+
+// =============================================================================
+// Self-Merchant Guard Tests
+// =============================================================================
+
+#[test]
+fn test_create_subscription_with_contract_as_merchant_rejected() {
+    let (env, client, _, _) = setup_test_env();
+    let result = client.try_create_subscription(
+        &Address::generate(&env),
+        &client.address,
+        &10_000_000i128,
+        &INTERVAL,
+        &false,
+        &None,
+    );
+    assert_eq!(result, Err(Ok(Error::InvalidInput)));
+}
+
+#[test]
+fn test_charge_with_contract_as_merchant_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    // Corrupt the stored merchant to simulate misconfiguration.
+    let mut sub = client.get_subscription(&id);
+    sub.merchant = client.address.clone();
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &sub);
+    });
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::InvalidInput))
+    );
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID);
+}
+
+// =============================================================================
+// Event Verbosity Tests
+// =============================================================================
+
+/// Partially charges a 5M balance against a 10M amount and returns the number of
+/// events the charge emitted.
+fn partial_charge_event_count(verbose: bool) -> u32 {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
+
+    let mut sub = client.get_subscription(&id);
+    sub.prepaid_balance = 5_000_000;
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &sub);
+    });
+    client.set_allow_partial_charge(&id, &merchant, &true);
+    client.set_events_verbose(&client.get_admin(), &verbose);
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+    let count = env.events().all().len();
+    assert_eq!(client.get_arrears(&id), 5_000_000);
+    count
+}
+
+#[test]
+fn test_charge_event_counts_verbose_vs_minimal() {
+    // Verbose: `charged` plus the informational `partial_charge`.
+    assert_eq!(partial_charge_event_count(true), 2);
+    // Minimal: only `charged`.
+    assert_eq!(partial_charge_event_count(false), 1);
+}
+
+#[test]
+fn test_events_verbose_defaults_on_and_requires_admin() {
+    let (env, client, _, _) = setup_test_env();
+    assert!(client.get_events_verbose());
+    assert_eq!(
+        client.try_set_events_verbose(&Address::generate(&env), &false),
+        Err(Ok(Error::Forbidden))
+    );
+    assert!(client.get_events_verbose());
+}
+
+// =============================================================================
+// Subscription Summary Tests
+// =============================================================================
+
+#[test]
+fn test_get_subscription_summary_matches_subscription() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+
+    let sub = client.get_subscription(&id);
+    let summary = client.get_subscription_summary(&id);
+    assert_eq!(summary.subscription_id, id);
+    assert_eq!(summary.subscriber, sub.subscriber);
+    assert_eq!(summary.merchant, sub.merchant);
+    assert_eq!(summary.amount, sub.amount);
+    assert_eq!(summary.interval_seconds, sub.interval_seconds);
+    assert_eq!(summary.last_payment_timestamp, sub.last_payment_timestamp);
+    assert_eq!(summary.status, sub.status);
+    assert_eq!(summary.prepaid_balance, sub.prepaid_balance);
+    assert_eq!(summary.usage_enabled, sub.usage_enabled);
+
+    assert_eq!(
+        client.try_get_subscription_summary(&999).err(),
+        Some(Ok(Error::NotFound))
+    );
+}
+
+// =============================================================================
+// Billing Engine Authorization Tests
+// =============================================================================
+
+/// Mocks `address`'s auth for `charge_subscription(id)` only.
+fn mock_charge_auth(env: &Env, client: &SubscriptionVaultClient, address: &Address, id: u32) {
+    env.mock_auths(&[soroban_sdk::testutils::MockAuth {
+        address,
+        invoke: &soroban_sdk::testutils::MockAuthInvoke {
+            contract: &client.address,
+            fn_name: "charge_subscription",
+            args: (id,).into_val(env),
+            sub_invokes: &[],
+        },
+    }]);
+}
+
+#[test]
+fn test_billing_engine_defaults_to_admin() {
+    let (_, client, _, admin) = setup_test_env();
+    assert_eq!(client.get_billing_engine(), admin);
+}
+
+#[test]
+fn test_configured_billing_engine_can_charge() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let engine = Address::generate(&env);
+    client.set_billing_engine(&client.get_admin(), &engine);
+    assert_eq!(client.get_billing_engine(), engine);
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    mock_charge_auth(&env, &client, &engine, id);
+    client.charge_subscription(&id);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 10_000_000
+    );
+}
+
+#[test]
+fn test_other_address_cannot_charge() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+    client.set_billing_engine(&admin, &Address::generate(&env));
+
+    // Even the admin is rejected once a dedicated engine is configured.
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    mock_charge_auth(&env, &client, &admin, id);
+    assert!(client.try_charge_subscription(&id).is_err());
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID);
+}
+
+#[test]
+fn test_charge_subscription_entrypoint_charges_and_emits() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    mock_charge_auth(&env, &client, &admin, id);
+    client.charge_subscription(&id);
+
+    // Exactly one `charged` event: the entrypoint does not duplicate charge_one's.
+    let charged = env
+        .events()
+        .all()
+        .iter()
+        .filter(|(_, topics, _)| *topics == (soroban_sdk::symbol_short!("charged"),).into_val(&env))
+        .count();
+    assert_eq!(charged, 1);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 10_000_000
+    );
+}
+
+#[test]
+fn test_charge_subscription_entrypoint_requires_engine_auth() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    // Only the subscriber signs: not enough to charge.
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    let subscriber = client.get_subscription(&id).subscriber;
+    mock_charge_auth(&env, &client, &subscriber, id);
+    assert!(client.try_charge_subscription(&id).is_err());
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID);
+}
+
+#[test]
+fn test_set_billing_engine_requires_admin() {
+    let (env, client, _, _) = setup_test_env();
+    let other = Address::generate(&env);
+    assert_eq!(
+        client.try_set_billing_engine(&other, &other),
+        Err(Ok(Error::Forbidden))
+    );
+}
+
+// =============================================================================
+// Remaining Intervals Tests
+// =============================================================================
+
+fn set_prepaid(env: &Env, client: &SubscriptionVaultClient, id: u32, balance: i128) {
+    let mut sub = client.get_subscription(&id);
+    sub.prepaid_balance = balance;
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &sub);
+    });
+}
+
+#[test]
+fn test_remaining_intervals_exact_division() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    // PREPAID (50M) / 10M amount.
+    assert_eq!(client.remaining_intervals(&id), 5);
+}
+
+#[test]
+fn test_remaining_intervals_floors_partial_cycles() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    set_prepaid(&env, &client, id, 29_999_999);
+    assert_eq!(client.remaining_intervals(&id), 2);
+}
+
+#[test]
+fn test_remaining_intervals_zero_balance_and_usage() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    set_prepaid(&env, &client, id, 0);
+    assert_eq!(client.remaining_intervals(&id), 0);
+
+    let mut sub = client.get_subscription(&id);
+    sub.prepaid_balance = PREPAID;
+    sub.usage_enabled = true;
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &sub);
+    });
+    assert_eq!(client.remaining_intervals(&id), 0);
+    assert_eq!(
+        client.try_remaining_intervals(&999),
+        Err(Ok(Error::NotFound))
+    );
+}
+
+// =============================================================================
+// Subscription Category Tests
+// =============================================================================
+
+#[test]
+fn test_set_and_count_by_category() {
+    let (env, client, _, _) = setup_test_env();
+    let pro = soroban_sdk::symbol_short!("pro");
+    let legacy = soroban_sdk::symbol_short!("legacy");
+
+    let mut ids = SorobanVec::new(&env);
+    for _ in 0..4 {
+        let (id, _, merchant) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+        ids.push_back((id, merchant));
+    }
+    assert_eq!(client.get_subscription(&0).category, None);
+
+    for i in 0..3 {
+        let (id, merchant) = ids.get(i).unwrap();
+        client.set_subscription_category(&id, &merchant, &pro);
+    }
+    let (id, merchant) = ids.get(3).unwrap();
+    client.set_subscription_category(&id, &merchant, &legacy);
+
+    assert_eq!(client.get_subscription(&0).category, Some(pro.clone()));
+    assert_eq!(client.count_by_category(&pro, &0, &10), 3);
+    assert_eq!(client.count_by_category(&legacy, &0, &10), 1);
+    assert_eq!(client.count_by_category(&pro, &1, &1), 1);
+
+    // Re-tagging moves the subscription between categories.
+    let (id, merchant) = ids.get(0).unwrap();
+    client.set_subscription_category(&id, &merchant, &legacy);
+    assert_eq!(client.count_by_category(&pro, &0, &10), 2);
+}
+
+#[test]
+fn test_set_subscription_category_requires_merchant() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    assert_eq!(
+        client.try_set_subscription_category(&id, &subscriber, &soroban_sdk::symbol_short!("pro")),
+        Err(Ok(Error::Unauthorized))
+    );
+}
+
+#[test]
+fn test_category_requires_migration_from_v1_records() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+
+    // Rewrite the record in the version 1 layout and mark storage as version 1.
+    let v1 = SubscriptionV1 {
+        subscriber: sub.subscriber,
+        merchant: sub.merchant.clone(),
+        amount: sub.amount,
+        interval_seconds: sub.interval_seconds,
+        last_payment_timestamp: sub.last_payment_timestamp,
+        status: sub.status,
+        prepaid_balance: sub.prepaid_balance,
+        usage_enabled: sub.usage_enabled,
+    };
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &v1);
+        env.storage()
+            .instance()
+            .set(&soroban_sdk::Symbol::new(&env, "storage_version"), &1u32);
+    });
+
+    let pro = soroban_sdk::symbol_short!("pro");
+    assert_eq!(
+        client.try_set_subscription_category(&id, &sub.merchant, &pro),
+        Err(Ok(Error::MigrationRequired))
+    );
+
+    client.migrate(&client.get_admin(), &100);
+    assert_eq!(client.get_storage_version(), 6);
+    let upgraded = client.get_subscription(&id);
+    assert_eq!(upgraded.category, None);
+    assert_eq!(upgraded.prepaid_balance, PREPAID);
+
+    client.set_subscription_category(&id, &sub.merchant, &pro);
+    assert_eq!(client.count_by_category(&pro, &0, &10), 1);
+}
+
+// =============================================================================
+// Deposit Ownership Restriction Tests
+// =============================================================================
+
+#[test]
+fn test_third_party_deposit_allowed_by_default() {
+    let (env, client, token, _) = setup_test_env();
+    let (id, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let sponsor = Address::generate(&env);
+    mint_for_subscriber(&env, &token, &sponsor, 5_000_000);
+
+    assert!(!client.get_restrict_deposits_to_owner());
+    client.deposit_funds(&id, &sponsor, &5_000_000i128, &None);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 5_000_000);
+}
+
+#[test]
+fn test_restricted_deposits_reject_third_party() {
+    let (env, client, token, admin) = setup_test_env();
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let sponsor = Address::generate(&env);
+    mint_for_subscriber(&env, &token, &sponsor, 5_000_000);
+    mint_for_subscriber(&env, &token, &subscriber, 5_000_000);
+
+    client.set_restrict_deposits_to_owner(&admin, &true);
+    assert_eq!(
+        client.try_deposit_funds(&id, &sponsor, &5_000_000i128, &None),
+        Err(Ok(Error::Forbidden))
+    );
+
+    // The owner can still deposit.
+    client.deposit_funds(&id, &subscriber, &5_000_000i128, &None);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 5_000_000);
+}
+
+// =============================================================================
+// Pending Usage Settlement Tests
+// =============================================================================
+
+#[test]
+fn test_cancel_settles_pending_usage_before_refund() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+    let sub = client.get_subscription(&id);
+
+    client.record_usage(&id, &sub.merchant, &8_000_000i128);
+    client.record_usage(&id, &sub.merchant, &4_000_000i128);
+    assert_eq!(client.get_pending_usage(&id), 12_000_000);
+    // Recording does not debit the balance.
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID);
+
+    client.cancel_subscription(&id, &sub.subscriber);
+
+    let cancelled = client.get_subscription(&id);
+    assert_eq!(cancelled.status, SubscriptionStatus::Cancelled);
+    assert_eq!(cancelled.prepaid_balance, PREPAID - 12_000_000);
+    assert_eq!(client.get_pending_usage(&id), 0);
+    env.as_contract(&client.address, || {
+        assert_eq!(
+            crate::merchant::get_merchant_balance(&env, &sub.merchant),
+            12_000_000
+        );
+    });
+}
+
+#[test]
+fn test_cancel_pays_available_balance_when_usage_exceeds_it() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+    let sub = client.get_subscription(&id);
+
+    client.record_usage(&id, &sub.merchant, &(PREPAID + 7_000_000));
+    client.cancel_subscription(&id, &sub.merchant);
+
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 0);
+    assert_eq!(client.get_pending_usage(&id), 0);
+    env.as_contract(&client.address, || {
+        assert_eq!(
+            crate::merchant::get_merchant_balance(&env, &sub.merchant),
+            PREPAID
+        );
+    });
+}
+
+#[test]
+fn test_record_usage_validation() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+    let sub = client.get_subscription(&id);
+
+    assert_eq!(
+        client.try_record_usage(&id, &sub.subscriber, &1000i128),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(
+        client.try_record_usage(&id, &sub.merchant, &0i128),
+        Err(Ok(Error::InvalidAmount))
+    );
+
+    let (client2, id2) = setup(&env, INTERVAL);
+    let merchant2 = client2.get_subscription(&id2).merchant;
+    assert_eq!(
+        client2.try_record_usage(&id2, &merchant2, &1000i128),
+        Err(Ok(Error::UsageNotEnabled))
+    );
+}
+
+// =============================================================================
+// Admin Reactivation Tests
+// =============================================================================
+
+#[test]
+fn test_admin_reactivate_creates_linked_active_subscription() {
+    let (env, client, _, admin) = setup_test_env();
+    let (old_id, subscriber, merchant) =
+        create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    client.cancel_subscription(&old_id, &subscriber);
+
+    let new_id = client.admin_reactivate(&admin, &old_id);
+    assert_ne!(new_id, old_id);
+
+    let new_sub = client.get_subscription(&new_id);
+    let old_sub = client.get_subscription(&old_id);
+    assert_eq!(new_sub.status, SubscriptionStatus::Active);
+    assert_eq!(new_sub.subscriber, subscriber);
+    assert_eq!(new_sub.merchant, merchant);
+    assert_eq!(new_sub.amount, old_sub.amount);
+    assert_eq!(new_sub.interval_seconds, old_sub.interval_seconds);
+    assert_eq!(new_sub.prepaid_balance, 0);
+    assert_eq!(old_sub.status, SubscriptionStatus::Cancelled);
+
+    assert_eq!(client.get_reactivated_from(&new_id), Some(old_id));
+    assert_eq!(client.get_reactivated_from(&old_id), None);
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 1);
+}
+
+#[test]
+fn test_admin_reactivate_rejects_live_subscription_and_non_admin() {
+    let (env, client, _, admin) = setup_test_env();
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+
+    assert_eq!(
+        client.try_admin_reactivate(&admin, &id),
+        Err(Ok(Error::InvalidStatusTransition))
+    );
+    client.cancel_subscription(&id, &subscriber);
+    assert_eq!(
+        client.try_admin_reactivate(&Address::generate(&env), &id),
+        Err(Ok(Error::Forbidden))
+    );
+}
+
+// =============================================================================
+// Top-up Estimate Overflow Tests
+// =============================================================================
+
+fn set_amount(env: &Env, client: &SubscriptionVaultClient, id: u32, amount: i128) {
+    let mut sub = client.get_subscription(&id);
+    sub.amount = amount;
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &sub);
+    });
+}
+
+#[test]
+fn test_estimate_topup_overflow_returns_error() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    set_amount(&env, &client, id, i128::MAX / 2);
+
+    assert_eq!(
+        client.try_estimate_topup_for_intervals(&id, &u32::MAX),
+        Err(Ok(Error::Overflow))
+    );
+    // Still representable: exactly two intervals of MAX / 2.
+    assert_eq!(
+        client.estimate_topup_for_intervals(&id, &2),
+        (i128::MAX / 2) * 2 - PREPAID
+    );
+}
+
+#[test]
+fn test_estimate_topup_clamps_surplus_and_extreme_balances() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    assert_eq!(
+        client.estimate_topup_for_intervals(&id, &u32::MAX),
+        10_000_000 * u32::MAX as i128 - PREPAID
+    );
+    assert_eq!(client.estimate_topup_for_intervals(&id, &1), 0);
+
+    set_prepaid(&env, &client, id, i128::MIN);
+    assert_eq!(client.estimate_topup_for_intervals(&id, &1), i128::MAX);
+}
+
+// =============================================================================
+// Billing Anchor Tests
+// =============================================================================
+
+const WEEK: u64 = 7 * 24 * 60 * 60;
+/// 1970-01-05T00:00:00Z, a Monday.
+const MONDAY: u64 = 4 * 24 * 60 * 60;
+
+#[test]
+fn test_compute_anchored_next() {
+    use crate::compute_anchored_next;
+
+    assert_eq!(compute_anchored_next(MONDAY, WEEK, 0), Some(MONDAY));
+    assert_eq!(
+        compute_anchored_next(MONDAY, WEEK, MONDAY),
+        Some(MONDAY + WEEK)
+    );
+    assert_eq!(
+        compute_anchored_next(MONDAY, WEEK, MONDAY + WEEK + 1),
+        Some(MONDAY + 2 * WEEK)
+    );
+    assert_eq!(compute_anchored_next(MONDAY, 0, MONDAY), None);
+    assert_eq!(compute_anchored_next(u64::MAX - 1, WEEK, u64::MAX), None);
+}
+
+#[test]
+fn test_billing_anchor_keeps_schedule_aligned_across_cycles() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, WEEK);
+    let merchant = client.get_subscription(&id).merchant;
+
+    // MONDAY is less than a week after creation, so the first slot is a week later.
+    let first = MONDAY + WEEK;
+    client.set_billing_anchor(&id, &merchant, &MONDAY);
+    assert_eq!(client.get_billing_anchor(&id), Some(first));
+    assert_eq!(
+        client.get_next_charge_info(&id).next_charge_timestamp,
+        first
+    );
+
+    env.ledger().set_timestamp(first - 1);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::IntervalNotElapsed))
+    );
+
+    // Charge each cycle a little late; the next due time stays on the Monday slot.
+    for (cycle, delay) in [(0u64, 0u64), (1, 3_600), (2, 86_399), (3, 60)] {
+        env.ledger().set_timestamp(first + cycle * WEEK + delay);
+        let info = client.charge_subscription_detailed(&id);
+        assert_eq!(info.next_charge_timestamp, first + (cycle + 1) * WEEK);
+        assert_eq!(
+            client.get_next_charge_info(&id).next_charge_timestamp,
+            first + (cycle + 1) * WEEK
+        );
+    }
+
+    // Just before the next Monday we are still in the anchored period already charged.
+    env.ledger().set_timestamp(first + 4 * WEEK - 1);
+    assert!(!client.is_charge_due(&id));
+    assert_eq!(client.try_charge_subscription(&id), Err(Ok(Error::Replay)));
+}
+
+#[test]
+fn test_billing_anchor_after_charge_does_not_allow_second_charge() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, WEEK);
+    let merchant = client.get_subscription(&id).merchant;
+
+    let charged_at = T0 + WEEK;
+    env.ledger().set_timestamp(charged_at);
+    client.charge_subscription(&id);
+
+    // An anchor whose next slot is a day away must not make the sub due again.
+    client.set_billing_anchor(&id, &merchant, &(charged_at + DAY));
+    assert_eq!(
+        client.get_next_charge_info(&id).next_charge_timestamp,
+        charged_at + DAY + WEEK
+    );
+    env.ledger().set_timestamp(charged_at + DAY);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::IntervalNotElapsed))
+    );
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 10_000_000
+    );
+
+    env.ledger().set_timestamp(charged_at + DAY + WEEK);
+    client.charge_subscription(&id);
+}
+
+#[test]
+fn test_set_billing_anchor_requires_merchant() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, WEEK);
+
+    assert_eq!(
+        client.try_set_billing_anchor(&id, &Address::generate(&env), &MONDAY),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert_eq!(client.get_billing_anchor(&id), None);
+    assert_eq!(
+        client.get_next_charge_info(&id).next_charge_timestamp,
+        T0 + WEEK
+    );
+}
+
+#[test]
+fn test_subscription_exists() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+
+    assert!(client.subscription_exists(&id));
+    assert!(!client.subscription_exists(&(id + 1)));
+    assert!(!client.subscription_exists(&9999));
+
+    // Cancelled records are still stored.
+    client.cancel_subscription(&id, &subscriber);
+    assert!(client.subscription_exists(&id));
+}
+
+// =============================================================================
+// Per-Subscriber Subscription Cap Tests
+// =============================================================================
+
+#[test]
+fn test_max_subs_per_subscriber_enforced_and_freed_by_cancel() {
+    let (env, client, _, admin) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+
+    assert_eq!(client.get_max_subs_per_subscriber(), 0);
+    client.set_max_subs_per_subscriber(&admin, &2);
+    assert_eq!(client.get_max_subs_per_subscriber(), 2);
+
+    let first = client.create_subscription(&subscriber, &merchant, &1000, &INTERVAL, &false, &None);
+    client.create_subscription(&subscriber, &merchant, &1000, &INTERVAL, &false, &None);
+    assert_eq!(
+        client.try_create_subscription(&subscriber, &merchant, &1000, &INTERVAL, &false, &None),
+        Err(Ok(Error::SubscriptionLimitReached))
+    );
+
+    // Other subscribers have their own allowance.
+    client.create_subscription(
+        &Address::generate(&env),
+        &merchant,
+        &1000,
+        &INTERVAL,
+        &false,
+        &None,
+    );
+
+    // Paused subscriptions still count; cancelling frees a slot.
+    client.pause_subscription(&first, &subscriber, &None);
+    assert_eq!(
+        client.try_create_subscription(&subscriber, &merchant, &1000, &INTERVAL, &false, &None),
+        Err(Ok(Error::SubscriptionLimitReached))
+    );
+    client.cancel_subscription(&first, &subscriber);
+    client.create_subscription(&subscriber, &merchant, &1000, &INTERVAL, &false, &None);
+}
+
+#[test]
+fn test_max_subs_per_subscriber_zero_is_unlimited_and_admin_only() {
+    let (env, client, _, admin) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+
+    assert_eq!(
+        client.try_set_max_subs_per_subscriber(&Address::generate(&env), &1),
+        Err(Ok(Error::Forbidden))
+    );
+
+    client.set_max_subs_per_subscriber(&admin, &0);
+    for _ in 0..5 {
+        client.create_subscription(&subscriber, &merchant, &1000, &INTERVAL, &false, &None);
+    }
+}
+
+#[test]
+fn test_migrate_backfills_subscriber_index_for_cap() {
+    let (env, client, _, admin) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let live = client.create_subscription(&subscriber, &merchant, &1000, &INTERVAL, &false, &None);
+    let cancelled =
+        client.create_subscription(&subscriber, &merchant, &1000, &INTERVAL, &false, &None);
+    client.cancel_subscription(&cancelled, &subscriber);
+
+    // Simulate records created before the subscriber index existed.
+    for id in [live, cancelled] {
+        write_v1_record(&env, &client, id);
+    }
+    env.as_contract(&client.address, || {
+        env.storage()
+            .instance()
+            .remove(&crate::DataKey::SubscriberSubs(subscriber.clone()));
+    });
+    clear_storage_version(&env, &client);
+    client.migrate(&admin, &100);
+
+    // Only the live subscription is backfilled, so it holds the single slot.
+    client.set_max_subs_per_subscriber(&admin, &2);
+    client.create_subscription(&subscriber, &merchant, &1000, &INTERVAL, &false, &None);
+    assert_eq!(
+        client.try_create_subscription(&subscriber, &merchant, &1000, &INTERVAL, &false, &None),
+        Err(Ok(Error::SubscriptionLimitReached))
+    );
+}
+
+// =============================================================================
+// Charge Count Tests
+// =============================================================================
+
+#[test]
+fn test_charge_count_advances_on_each_charge() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    assert_eq!(client.get_subscription(&id).charge_count, 0);
+
+    for n in 1..=3u32 {
+        env.ledger().set_timestamp(T0 + n as u64 * INTERVAL);
+        client.charge_subscription(&id);
+        assert_eq!(client.get_subscription(&id).charge_count, n);
+    }
+    assert_eq!(client.get_subscription_summary(&id).charge_count, 3);
+
+    // Rejected charges do not count.
+    assert_eq!(client.try_charge_subscription(&id), Err(Ok(Error::Replay)));
+    assert_eq!(client.get_subscription(&id).charge_count, 3);
+}
+
+#[test]
+fn test_migration_from_v2_defaults_charge_count_to_zero() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+
+    let v2 = SubscriptionV2 {
+        subscriber: sub.subscriber,
+        merchant: sub.merchant,
+        amount: sub.amount,
+        interval_seconds: sub.interval_seconds,
+        last_payment_timestamp: sub.last_payment_timestamp,
+        status: sub.status,
+        prepaid_balance: sub.prepaid_balance,
+        usage_enabled: sub.usage_enabled,
+        category: Some(soroban_sdk::symbol_short!("pro")),
+    };
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &v2);
+        env.storage()
+            .instance()
+            .set(&soroban_sdk::Symbol::new(&env, "storage_version"), &2u32);
+    });
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::MigrationRequired))
+    );
+
+    client.migrate(&client.get_admin(), &100);
+    let upgraded = client.get_subscription(&id);
+    assert_eq!(upgraded.charge_count, 0);
+    assert_eq!(upgraded.category, Some(soroban_sdk::symbol_short!("pro")));
+
+    client.charge_subscription(&id);
+    assert_eq!(client.get_subscription(&id).charge_count, 1);
+}
+
+#[test]
+fn test_init_records_token_decimals() {
+    let (env, client, _, _) = setup_test_env();
+    assert_eq!(client.get_token_decimals(), 7);
+
+    let other = SubscriptionVaultClient::new(&env, &env.register(SubscriptionVault, ()));
+    assert_eq!(
+        other.try_get_token_decimals(),
+        Err(Ok(Error::NotInitialized))
+    );
+    other.init(
+        &Address::generate(&env),
+        &6,
+        &Address::generate(&env),
+        &0,
+        &0,
+    );
+    assert_eq!(other.get_token_decimals(), 6);
+}
+
+#[test]
+fn test_get_token_returns_init_address() {
+    let (env, client, token, _) = setup_test_env();
+    assert_eq!(client.get_token(), token);
+
+    let other = SubscriptionVaultClient::new(&env, &env.register(SubscriptionVault, ()));
+    assert_eq!(other.try_get_token(), Err(Ok(Error::NotInitialized)));
+}
+
+#[test]
+fn test_get_statuses_skips_missing_ids() {
+    let (env, client, _, _) = setup_test_env();
+    let (active, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let (paused, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Paused);
+    let (cancelled, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Cancelled);
+
+    let ids = SorobanVec::from_array(&env, [cancelled, 9999, active, 500, paused]);
+    let statuses = client.get_statuses(&ids);
+    assert_eq!(
+        statuses,
+        SorobanVec::from_array(
+            &env,
+            [
+                (cancelled, SubscriptionStatus::Cancelled),
+                (active, SubscriptionStatus::Active),
+                (paused, SubscriptionStatus::Paused),
+            ]
+        )
+    );
+    assert_eq!(client.get_statuses(&SorobanVec::new(&env)).len(), 0);
+}
+
+#[test]
+fn test_get_statuses_caps_input_length() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+
+    let mut ids = SorobanVec::new(&env);
+    for _ in 0..crate::queries::MAX_BATCH_SIZE + 5 {
+        ids.push_back(id);
+    }
+    assert_eq!(
+        client.get_statuses(&ids).len(),
+        crate::queries::MAX_BATCH_SIZE
+    );
+}
+
+// =============================================================================
+// Cancellation Fee Tests
+// =============================================================================
+
+fn merchant_balance(env: &Env, client: &SubscriptionVaultClient, merchant: &Address) -> i128 {
+    env.as_contract(&client.address, || {
+        crate::merchant::get_merchant_balance(env, merchant)
+    })
+}
+
+#[test]
+fn test_cancellation_without_fee_keeps_full_balance() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+
+    assert_eq!(client.get_cancellation_fee_bps(&id), 0);
+    client.cancel_subscription(&id, &sub.subscriber);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID);
+    assert_eq!(merchant_balance(&env, &client, &sub.merchant), 0);
+}
+
+#[test]
+fn test_cancellation_fee_splits_remaining_balance() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+
+    client.set_cancellation_fee_bps(&id, &sub.merchant, &250);
+    client.cancel_subscription(&id, &sub.subscriber);
+
+    let fee = PREPAID * 250 / 10_000;
+    assert_eq!(merchant_balance(&env, &client, &sub.merchant), fee);
+    env.as_contract(&client.address, || {
+        assert_eq!(crate::merchant::get_subscription_accrued(&env, id), fee);
+    });
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID - fee);
+}
+
+#[test]
+fn test_cancellation_fee_capped_at_refund() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+
+    assert_eq!(
+        client.try_set_cancellation_fee_bps(&id, &sub.merchant, &10_001),
+        Err(Ok(Error::InvalidInput))
+    );
+    assert_eq!(
+        client.try_set_cancellation_fee_bps(&id, &sub.subscriber, &100),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    client.set_cancellation_fee_bps(&id, &sub.merchant, &10_000);
+    set_prepaid(&env, &client, id, i128::MAX);
+    client.cancel_subscription(&id, &sub.subscriber);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 0);
+    assert_eq!(merchant_balance(&env, &client, &sub.merchant), i128::MAX);
+}
+
+#[test]
+fn test_cancellation_fee_not_applied_when_merchant_cancels() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+
+    client.set_cancellation_fee_bps(&id, &sub.merchant, &5_000);
+    client.cancel_subscription(&id, &sub.merchant);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID);
+    assert_eq!(merchant_balance(&env, &client, &sub.merchant), 0);
+}
+
+#[test]
+fn test_seconds_until_chargeable_around_boundary() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    assert_eq!(client.seconds_until_chargeable(&id), INTERVAL);
+    env.ledger().set_timestamp(T0 + INTERVAL - 10);
+    assert_eq!(client.seconds_until_chargeable(&id), 10);
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert_eq!(client.seconds_until_chargeable(&id), 0);
+    env.ledger().set_timestamp(T0 + INTERVAL + 500);
+    assert_eq!(client.seconds_until_chargeable(&id), 0);
+
+    // After a charge the countdown restarts from the charge time.
+    client.charge_subscription(&id);
+    assert_eq!(client.seconds_until_chargeable(&id), INTERVAL);
+    assert_eq!(
+        client.try_seconds_until_chargeable(&9999),
+        Err(Ok(Error::NotFound))
+    );
+}
+
+// =============================================================================
+// Default Plan Tests
+// =============================================================================
+
+#[test]
+fn test_subscribe_default_uses_configured_plan() {
+    let (env, client, _, admin) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let subscriber = Address::generate(&env);
+    let plan_id = client.create_plan_template(&merchant, &2_500_000i128, &INTERVAL, &false);
+
+    client.set_default_plan(&admin, &plan_id);
+    assert_eq!(client.get_default_plan(), Some(plan_id));
+
+    let id = client.subscribe_default(&subscriber);
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.subscriber, subscriber);
+    assert_eq!(sub.merchant, merchant);
+    assert_eq!(sub.amount, 2_500_000);
+    assert_eq!(sub.interval_seconds, INTERVAL);
+    assert_eq!(sub.status, SubscriptionStatus::Active);
+}
+
+#[test]
+fn test_subscribe_default_without_default_plan_rejected() {
+    let (env, client, _, admin) = setup_test_env();
+    assert_eq!(client.get_default_plan(), None);
+    assert_eq!(
+        client.try_subscribe_default(&Address::generate(&env)),
+        Err(Ok(Error::NotFound))
+    );
+
+    // Only the admin may set it, and only to an existing plan.
+    assert_eq!(
+        client.try_set_default_plan(&admin, &42),
+        Err(Ok(Error::NotFound))
+    );
+    let plan_id =
+        client.create_plan_template(&Address::generate(&env), &1000i128, &INTERVAL, &false);
+    assert_eq!(
+        client.try_set_default_plan(&Address::generate(&env), &plan_id),
+        Err(Ok(Error::Forbidden))
+    );
+}
+
+// =============================================================================
+// Pause Reason Tests
+// =============================================================================
+
+#[test]
+fn test_pause_reason_round_trips_and_appears_in_event() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, subscriber, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let reason = soroban_sdk::symbol_short!("vacation");
+
+    client.pause_subscription(&id, &subscriber, &Some(reason.clone()));
+
+    let (_, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (soroban_sdk::symbol_short!("paused"), id).into_val(&env)
+    );
+    let event = crate::SubscriptionPausedEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(event.subscription_id, id);
+    assert_eq!(event.authorizer, subscriber);
+    assert_eq!(event.reason, Some(reason.clone()));
+    assert_eq!(client.get_pause_reason(&id), Some(reason));
+
+    // Resuming clears the reason.
+    client.resume_subscription(&id, &subscriber);
+    assert_eq!(client.get_pause_reason(&id), None);
+}
+
+#[test]
+fn test_pause_without_reason() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, _, merchant) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+
+    client.pause_subscription(&id, &merchant, &None);
+    let (_, _, data) = env.events().all().last().unwrap();
+    let event = crate::SubscriptionPausedEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(event.reason, None);
+    assert_eq!(client.get_pause_reason(&id), None);
+}
+
+// =============================================================================
+// Atomic Bundle Charge Tests
+// =============================================================================
+
+#[test]
+fn test_charge_bundle_all_succeed() {
+    let env = Env::default();
+    let (client, _, id0, id1) = setup_batch_env(&env);
+    let subscriber = client.get_subscription(&id1).subscriber;
+    client.deposit_funds(&id1, &subscriber, &10_000000i128, &None);
+
+    client.charge_bundle(&SorobanVec::from_array(&env, [id0, id1]));
+
+    for id in [id0, id1] {
+        let sub = client.get_subscription(&id);
+        assert_eq!(sub.prepaid_balance, 10_000000 - 1000);
+        assert_eq!(sub.last_payment_timestamp, T0 + INTERVAL);
+    }
+}
+
+#[test]
+fn test_charge_bundle_reverts_when_one_underfunded() {
+    let env = Env::default();
+    let (client, _, id0, id1) = setup_batch_env(&env);
+
+    let result = client.try_charge_bundle(&SorobanVec::from_array(&env, [id0, id1]));
+    assert_eq!(result, Err(Ok(Error::InsufficientBalance)));
+
+    // Nothing was charged, and the underfunded subscription's status is untouched.
+    let funded = client.get_subscription(&id0);
+    assert_eq!(funded.prepaid_balance, 10_000000);
+    assert_eq!(funded.last_payment_timestamp, T0);
+    assert_eq!(
+        client.get_subscription(&id1).status,
+        SubscriptionStatus::Active
+    );
+}
+
+#[test]
+fn test_charge_bundle_rejects_partial_charge() {
+    let env = Env::default();
+    let (client, _, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id1);
+    client.set_allow_partial_charge(&id1, &sub.merchant, &true);
+    set_prepaid(&env, &client, id1, 500);
+
+    let result = client.try_charge_bundle(&SorobanVec::from_array(&env, [id0, id1]));
+    assert_eq!(result, Err(Ok(Error::InsufficientBalance)));
+    assert_eq!(client.get_subscription(&id1).prepaid_balance, 500);
+    assert_eq!(client.get_arrears(&id1), 0);
+}
+
+#[test]
+fn test_charge_bundle_rejects_mixed_subscribers_and_empty_input() {
+    let env = Env::default();
+    let (client, _, id0, _) = setup_batch_env(&env);
+    let other = client.create_subscription(
+        &Address::generate(&env),
+        &Address::generate(&env),
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+    );
+
+    assert_eq!(
+        client.try_charge_bundle(&SorobanVec::from_array(&env, [id0, other])),
+        Err(Ok(Error::InvalidInput))
+    );
+    assert_eq!(
+        client.try_charge_bundle(&SorobanVec::new(&env)),
+        Err(Ok(Error::InvalidInput))
+    );
+}
+
+// =============================================================================
+// Subscription Metadata Tests
+// =============================================================================
+
+#[test]
+fn test_metadata_set_at_creation_and_updated() {
+    let (env, client, _, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let data = soroban_sdk::Bytes::from_slice(&env, b"user-42");
+
+    let id = client.create_subscription_with_meta(
+        &subscriber,
+        &merchant,
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &data,
+    );
+    assert_eq!(client.get_subscription(&id).metadata, Some(data));
+
+    let updated = soroban_sdk::Bytes::from_slice(&env, b"plan:pro");
+    client.set_subscription_metadata(&id, &merchant, &updated);
+    assert_eq!(client.get_subscription(&id).metadata, Some(updated));
+
+    assert_eq!(
+        client.try_set_subscription_metadata(
+            &id,
+            &Address::generate(&env),
+            &soroban_sdk::Bytes::new(&env)
+        ),
+        Err(Ok(Error::Forbidden))
+    );
+}
+
+#[test]
+fn test_metadata_oversized_blob_rejected() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, subscriber, merchant) =
+        create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    assert_eq!(client.get_subscription(&id).metadata, None);
+
+    let max = soroban_sdk::Bytes::from_array(&env, &[1u8; 256]);
+    client.set_subscription_metadata(&id, &subscriber, &max);
+    assert_eq!(client.get_subscription(&id).metadata, Some(max));
+
+    let oversized = soroban_sdk::Bytes::from_array(&env, &[1u8; 257]);
+    assert_eq!(
+        client.try_set_subscription_metadata(&id, &subscriber, &oversized),
+        Err(Ok(Error::InvalidInput))
+    );
+    assert_eq!(
+        client.try_create_subscription_with_meta(
+            &subscriber,
+            &merchant,
+            &1000i128,
+            &INTERVAL,
+            &false,
+            &oversized
+        ),
+        Err(Ok(Error::InvalidInput))
+    );
+}
+
+#[test]
+fn test_migration_from_v3_keeps_charge_count() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+
+    let v3 = SubscriptionV3 {
+        subscriber: sub.subscriber,
+        merchant: sub.merchant,
+        amount: sub.amount,
+        interval_seconds: sub.interval_seconds,
+        last_payment_timestamp: sub.last_payment_timestamp,
+        status: sub.status,
+        prepaid_balance: sub.prepaid_balance,
+        usage_enabled: sub.usage_enabled,
+        category: sub.category,
+        charge_count: 7,
+    };
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &v3);
+        env.storage()
+            .instance()
+            .set(&soroban_sdk::Symbol::new(&env, "storage_version"), &3u32);
+    });
+
+    client.migrate(&client.get_admin(), &100);
+    let upgraded = client.get_subscription(&id);
+    assert_eq!(upgraded.charge_count, 7);
+    assert_eq!(upgraded.metadata, None);
+}
+
+#[test]
+fn test_validate_transitions_matches_single_pair_helper() {
+    let (env, client, _, _) = setup_test_env();
+    let all = [
+        SubscriptionStatus::Active,
+        SubscriptionStatus::Paused,
+        SubscriptionStatus::Cancelled,
+        SubscriptionStatus::InsufficientBalance,
+        SubscriptionStatus::GracePeriod,
+    ];
+
+    let mut pairs = SorobanVec::new(&env);
+    for from in all.iter() {
+        for to in all.iter() {
+            pairs.push_back((from.clone(), to.clone()));
+        }
+    }
+    let flags = client.validate_transitions(&pairs);
+    assert_eq!(flags.len(), pairs.len());
+    for (i, (from, to)) in pairs.iter().enumerate() {
+        assert_eq!(flags.get(i as u32).unwrap(), can_transition(&from, &to));
+    }
+
+    let mixed = SorobanVec::from_array(
+        &env,
+        [
+            (SubscriptionStatus::Active, SubscriptionStatus::Paused),
+            (SubscriptionStatus::Cancelled, SubscriptionStatus::Active),
+        ],
+    );
+    assert_eq!(
+        client.validate_transitions(&mixed),
+        SorobanVec::from_array(&env, [true, false])
+    );
+    assert_eq!(client.validate_transitions(&SorobanVec::new(&env)).len(), 0);
+}
+
+// =============================================================================
+// Low Balance Warning Tests
+// =============================================================================
+
+fn low_balance_events(env: &Env) -> u32 {
+    let topic = soroban_sdk::Symbol::new(env, "low_balance");
+    env.events()
+        .all()
+        .iter()
+        .filter(|(_, topics, _)| {
+            soroban_sdk::Symbol::try_from_val(env, &topics.get(0).unwrap()).ok()
+                == Some(topic.clone())
+        })
+        .count() as u32
+}
+
+#[test]
+fn test_low_balance_warning_when_charge_crosses_buffer() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    client.set_min_balance_buffer(&client.get_admin(), &25_000_000);
+    assert_eq!(client.get_min_balance_buffer(), 25_000_000);
+
+    // 50M -> 40M: still above the buffer.
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+    assert_eq!(low_balance_events(&env), 0);
+
+    // 40M -> 30M: still above.
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id);
+    assert_eq!(low_balance_events(&env), 0);
+
+    // 30M -> 20M: below the buffer, charged anyway with a warning.
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL);
+    client.charge_subscription(&id);
+    let events = env.events().all();
+    assert_eq!(low_balance_events(&env), 1);
+    let (_, topics, data) = events.last().unwrap();
+    assert_eq!(
+        topics,
+        (soroban_sdk::Symbol::new(&env, "low_balance"), id).into_val(&env)
+    );
+    let (subscriber, balance, buffer): (Address, i128, i128) =
+        TryFromVal::try_from_val(&env, &data).unwrap();
+    assert_eq!(subscriber, client.get_subscription(&id).subscriber);
+    assert_eq!(balance, 20_000_000);
+    assert_eq!(buffer, 25_000_000);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 20_000_000);
+
+    // Minimal event mode drops the warning; the charge still goes through.
+    client.set_events_verbose(&client.get_admin(), &false);
+    env.ledger().set_timestamp(T0 + 4 * INTERVAL);
+    client.charge_subscription(&id);
+    assert_eq!(low_balance_events(&env), 0);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 10_000_000);
+}
+
+#[test]
+fn test_low_balance_warning_disabled_by_default() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    assert_eq!(client.get_min_balance_buffer(), 0);
+    assert_eq!(
+        client.try_set_min_balance_buffer(&client.get_admin(), &-1),
+        Err(Ok(Error::InvalidAmount))
+    );
+
+    set_prepaid(&env, &client, id, 10_000_000);
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 0);
+    assert_eq!(low_balance_events(&env), 0);
+}
+
+// =============================================================================
+// Plan Switch Tests
+// =============================================================================
+
+#[test]
+fn test_switch_plan_monthly_to_annual_applies_next_cycle() {
+    let (env, client, token, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let subscriber = Address::generate(&env);
+    let monthly = client.create_monthly_plan(&merchant, &10_000_000i128, &false);
+    let annual = client.create_annual_plan(&merchant, &100_000_000i128, &false);
+
+    let id = client.create_subscription_from_plan(&subscriber, &monthly);
+    mint_for_subscriber(&env, &token, &subscriber, 200_000_000);
+    client.deposit_funds(&id, &subscriber, &200_000_000i128, &None);
+
+    client.switch_plan(&id, &subscriber, &annual);
+    assert_eq!(client.get_pending_plan(&id), Some(annual));
+    // The current cycle keeps the monthly terms.
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.amount, 10_000_000);
+    assert_eq!(sub.interval_seconds, crate::plan::MONTHLY_INTERVAL_SECONDS);
+    assert_eq!(
+        client.get_next_charge_info(&id).next_charge_timestamp,
+        crate::plan::MONTHLY_INTERVAL_SECONDS
+    );
+
+    // The next charge is due on the monthly schedule but billed at the annual price.
+    let first_due = crate::plan::MONTHLY_INTERVAL_SECONDS;
+    env.ledger().set_timestamp(first_due);
+    client.charge_subscription(&id);
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.amount, 100_000_000);
+    assert_eq!(sub.interval_seconds, crate::plan::ANNUAL_INTERVAL_SECONDS);
+    assert_eq!(sub.prepaid_balance, 100_000_000);
+    assert_eq!(client.get_pending_plan(&id), None);
+
+    // From then on the annual interval applies: a month later nothing is due.
+    env.ledger()
+        .set_timestamp(first_due + crate::plan::MONTHLY_INTERVAL_SECONDS);
+    assert!(!client.is_charge_due(&id));
+    assert!(client.try_charge_subscription(&id).is_err());
+    env.ledger()
+        .set_timestamp(first_due + crate::plan::ANNUAL_INTERVAL_SECONDS);
+    client.charge_subscription(&id);
+    assert_eq!(client.get_subscription(&id).prepaid_balance, 0);
+}
+
+#[test]
+fn test_switch_plan_rejections() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let subscriber = Address::generate(&env);
+    let monthly = client.create_monthly_plan(&merchant, &10_000_000i128, &false);
+    let foreign = client.create_annual_plan(&Address::generate(&env), &1_000i128, &false);
+    let id = client.create_subscription_from_plan(&subscriber, &monthly);
+
+    assert_eq!(
+        client.try_switch_plan(&id, &Address::generate(&env), &monthly),
+        Err(Ok(Error::Forbidden))
+    );
+    assert_eq!(
+        client.try_switch_plan(&id, &subscriber, &foreign),
+        Err(Ok(Error::InvalidInput))
+    );
+    assert_eq!(
+        client.try_switch_plan(&id, &subscriber, &999),
+        Err(Ok(Error::NotFound))
+    );
+    assert_eq!(client.get_pending_plan(&id), None);
+}
+
+#[test]
+fn test_create_subscription_before_init_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let client = SubscriptionVaultClient::new(&env, &env.register(SubscriptionVault, ()));
+
+    let result = client.try_create_subscription(
+        &Address::generate(&env),
+        &Address::generate(&env),
+        &1000i128,
+        &INTERVAL,
+        &false,
+        &None,
+    );
+    assert_eq!(result, Err(Ok(Error::NotInitialized)));
+    assert!(!client.subscription_exists(&0));
+}
+
+// =============================================================================
+// Active Count Tests
+// =============================================================================
+
+#[test]
+fn test_active_count_tracks_creation_and_cancellation() {
+    let (env, client, _, admin) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    assert_eq!(client.get_active_count(), 0);
+
+    let mut ids = [0u32; 3];
+    for id in ids.iter_mut() {
+        *id = client.create_subscription(&subscriber, &merchant, &1000, &INTERVAL, &false, &None);
+    }
+    assert_eq!(client.get_active_count(), 3);
+    assert_eq!(client.get_total_created(), 3);
+
+    client.cancel_subscription(&ids[0], &subscriber);
+    assert_eq!(client.get_active_count(), 2);
+
+    // Idempotent re-cancel does not decrement again.
+    client.cancel_subscription(&ids[0], &merchant);
+    client.cancel_subscription(&ids[0], &subscriber);
+    assert_eq!(client.get_active_count(), 2);
+
+    client.admin_force_cancel(&admin, &ids[1], &RecoveryReason::UnreachableSubscriber);
+    assert_eq!(
+        client.try_admin_force_cancel(&admin, &ids[1], &RecoveryReason::UnreachableSubscriber),
+        Err(Ok(Error::NotActive))
+    );
+    assert_eq!(client.get_active_count(), 1);
+
+    // Pausing is not terminal.
+    client.pause_subscription(&ids[2], &subscriber, &None);
+    assert_eq!(client.get_active_count(), 1);
+    assert_eq!(client.get_total_created(), 3);
+}
+
+#[test]
+fn test_repeat_cancel_does_not_reapply_cancellation_fee() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+
+    client.set_cancellation_fee_bps(&id, &sub.merchant, &1_000);
+    client.cancel_subscription(&id, &sub.subscriber);
+    client.cancel_subscription(&id, &sub.subscriber);
+
+    let fee = PREPAID / 10;
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID - fee);
+    assert_eq!(merchant_balance(&env, &client, &sub.merchant), fee);
+}
+
+// =============================================================================
+// Last Payment Correction Tests
+// =============================================================================
+
+#[test]
+fn test_admin_set_last_payment_corrects_schedule() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+
+    // A charge for the wrong cycle pushed the schedule out.
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+    assert!(!client.is_charge_due(&id));
+
+    // Roll it back so the next cycle is due now.
+    client.admin_set_last_payment(&admin, &id, &T0);
+    let (_, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (soroban_sdk::Symbol::new(&env, "last_payment_corrected"), id).into_val(&env)
+    );
+    let (by, old, new): (Address, u64, u64) = TryFromVal::try_from_val(&env, &data).unwrap();
+    assert_eq!((by, old, new), (admin, T0 + INTERVAL, T0));
+
+    assert_eq!(client.get_subscription(&id).last_payment_timestamp, T0);
+    assert!(client.is_charge_due(&id));
+    client.charge_subscription(&id);
+}
+
+#[test]
+fn test_admin_set_last_payment_rejects_future_and_non_admin() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    assert_eq!(
+        client.try_admin_set_last_payment(&client.get_admin(), &id, &(T0 + 1)),
+        Err(Ok(Error::InvalidInput))
+    );
+    assert_eq!(
+        client.try_admin_set_last_payment(&Address::generate(&env), &id, &0),
+        Err(Ok(Error::Forbidden))
+    );
+    assert_eq!(client.get_subscription(&id).last_payment_timestamp, T0);
+}
+
+// =============================================================================
+// Usage Rate Tests
+// =============================================================================
+
+#[test]
+fn test_usage_rate_defaults_to_one() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+
+    assert_eq!(client.get_usage_rate(&id), 1);
+    client.charge_usage(&id, &3_000_000i128);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 3_000_000
+    );
+}
+
+#[test]
+fn test_usage_rate_multiplies_cost() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+    let merchant = client.get_subscription(&id).merchant;
+
+    client.set_usage_rate(&id, &merchant, &250_000i128);
+    client.charge_usage(&id, &12i128);
+
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 3_000_000
+    );
+    assert_eq!(merchant_balance(&env, &client, &merchant), 3_000_000);
+
+    // 201 units cost more than the remaining balance.
+    assert_eq!(
+        client.try_charge_usage(&id, &201i128),
+        Err(Ok(Error::InsufficientPrepaidBalance))
+    );
+    client.set_usage_rate(&id, &merchant, &i128::MAX);
+    assert_eq!(
+        client.try_charge_usage(&id, &2i128),
+        Err(Ok(Error::Overflow))
+    );
+}
+
+#[test]
+fn test_set_usage_rate_validation() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+    let merchant = client.get_subscription(&id).merchant;
+
+    assert_eq!(
+        client.try_set_usage_rate(&id, &merchant, &0i128),
+        Err(Ok(Error::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_set_usage_rate(&id, &Address::generate(&env), &5i128),
+        Err(Ok(Error::Unauthorized))
+    );
+}
+
+#[test]
+fn test_plan_usage_rate_copied_to_subscription() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let subscriber = Address::generate(&env);
+
+    let plan_id = client.create_plan_template(&merchant, &2_500_000i128, &3600, &true);
+    assert_eq!(client.get_plan_usage_rate(&plan_id), 1);
+    client.set_plan_usage_rate(&merchant, &plan_id, &40i128);
+    assert_eq!(
+        client.try_set_plan_usage_rate(&subscriber, &plan_id, &40i128),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    let id = client.create_subscription_from_plan(&subscriber, &plan_id);
+    assert_eq!(client.get_usage_rate(&id), 40);
+}
+
+#[test]
+fn test_batch_charge_emits_summary_event() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+
+    let mut ids = SorobanVec::new(&env);
+    ids.push_back(id0);
+    ids.push_back(id1);
+    ids.push_back(999);
+    let results = client.batch_charge(&ids);
+
+    let (_, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (soroban_sdk::Symbol::new(&env, "batch_charge_summary"),).into_val(&env)
+    );
+    let summary: crate::BatchChargeSummaryEvent = TryFromVal::try_from_val(&env, &data).unwrap();
+    let succeeded = results.iter().filter(|r| r.success).count() as u32;
+    assert_eq!(
+        summary,
+        crate::BatchChargeSummaryEvent {
+            total: results.len(),
+            succeeded,
+            failed: results.len() - succeeded,
+            timestamp: T0 + INTERVAL,
+        }
+    );
+    assert_eq!((summary.succeeded, summary.failed), (1, 2));
+}
+
+// =============================================================================
+// Delinquent Cancellation Tests
+// =============================================================================
+
+#[test]
+fn test_cancel_if_delinquent_cancels_underfunded_subscription() {
+    let (env, client, _, admin) = setup_test_env();
+    let (id, _, merchant) =
+        create_test_subscription(&env, &client, SubscriptionStatus::InsufficientBalance);
+    set_prepaid(&env, &client, id, 500);
+    let (grace_id, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::GracePeriod);
+    let active_before = client.get_active_count();
+
+    client.cancel_if_delinquent(&admin, &id, &1_000i128);
+    client.cancel_if_delinquent(&admin, &grace_id, &1_000i128);
+
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.status, SubscriptionStatus::Cancelled);
+    // The remainder stays withdrawable by the subscriber.
+    assert_eq!(sub.prepaid_balance, 500);
+    assert_eq!(
+        client.get_subscription(&grace_id).status,
+        SubscriptionStatus::Cancelled
+    );
+    assert_eq!(client.get_active_count(), active_before - 2);
+    assert_eq!(client.get_merchant_subscription_count(&merchant), 0);
+}
+
+#[test]
+fn test_cancel_if_delinquent_emits_cancelled_and_clears_schedule() {
+    let (env, client, _, admin) = setup_test_env();
+    let (id, subscriber, _) =
+        create_test_subscription(&env, &client, SubscriptionStatus::InsufficientBalance);
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(
+            &crate::types::DataKey::ScheduledCancellation(id),
+            &(T0 + WEEK, subscriber, CancellationReason::UserRequested),
+        );
+    });
+
+    client.cancel_if_delinquent(&admin, &id, &1_000i128);
+    let cancelled_topic: soroban_sdk::Vec<soroban_sdk::Val> =
+        (soroban_sdk::symbol_short!("cancelled"), id).into_val(&env);
+    let (_, _, data) = env
+        .events()
+        .all()
+        .iter()
+        .find(|(_, topics, _)| *topics == cancelled_topic)
+        .unwrap();
+    let event = SubscriptionCancelledEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(event.authorizer, admin);
+    assert_eq!(event.reason, CancellationReason::Delinquent);
+    assert_eq!(client.get_scheduled_cancellation(&id), None);
+}
+
+#[test]
+fn test_cancel_if_delinquent_leaves_healthy_subscriptions() {
+    let (env, client, _, admin) = setup_test_env();
+    let (active_id, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let (funded_id, _, _) =
+        create_test_subscription(&env, &client, SubscriptionStatus::InsufficientBalance);
+    set_prepaid(&env, &client, funded_id, 5_000);
+
+    assert_eq!(
+        client.try_cancel_if_delinquent(&admin, &active_id, &1_000i128),
+        Err(Ok(Error::InvalidStatusTransition))
+    );
+    assert_eq!(
+        client.try_cancel_if_delinquent(&admin, &funded_id, &1_000i128),
+        Err(Ok(Error::InvalidStatusTransition))
+    );
+    assert_eq!(
+        client.try_cancel_if_delinquent(&Address::generate(&env), &funded_id, &10_000i128),
+        Err(Ok(Error::Forbidden))
+    );
+    assert_eq!(
+        client.get_subscription(&active_id).status,
+        SubscriptionStatus::Active
+    );
+    assert_eq!(
+        client.get_subscription(&funded_id).status,
+        SubscriptionStatus::InsufficientBalance
+    );
+}
+
+// =============================================================================
+// Blocked Address Tests
+// =============================================================================
+
+#[test]
+fn test_create_subscription_rejects_blocked_merchant() {
+    let (env, client, _, admin) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let burn = Address::generate(&env);
+
+    assert!(!client.is_address_blocked(&burn));
+    client.set_address_blocked(&admin, &burn, &true);
+    assert!(client.is_address_blocked(&burn));
+
+    assert_eq!(
+        client.try_create_subscription(&subscriber, &burn, &1_000i128, &INTERVAL, &false, &None),
+        Err(Ok(Error::Forbidden))
+    );
+
+    client.set_address_blocked(&admin, &burn, &false);
+    client.create_subscription(&subscriber, &burn, &1_000i128, &INTERVAL, &false, &None);
+}
+
+#[test]
+fn test_charge_rejected_after_merchant_blocked() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
+
+    client.set_address_blocked(&client.get_admin(), &merchant, &true);
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::Forbidden))
+    );
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID);
+}
+
+#[test]
+fn test_usage_charge_rejected_for_blocked_merchant_and_maintenance() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+    let merchant = client.get_subscription(&id).merchant;
+
+    client.set_maintenance_until(&merchant, &(env.ledger().timestamp() + 500));
+    assert_eq!(
+        client.try_charge_usage(&id, &1_000_000i128),
+        Err(Ok(Error::InMaintenance))
+    );
+    client.set_maintenance_until(&merchant, &0);
+
+    client.set_address_blocked(&client.get_admin(), &merchant, &true);
+    assert_eq!(
+        client.try_charge_usage(&id, &1_000_000i128),
+        Err(Ok(Error::Forbidden))
+    );
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID);
+}
+
+#[test]
+fn test_set_address_blocked_requires_admin() {
+    let (env, client, _, _) = setup_test_env();
+    let other = Address::generate(&env);
+    assert_eq!(
+        client.try_set_address_blocked(&other, &other, &true),
+        Err(Ok(Error::Forbidden))
+    );
+}
+
+#[test]
+fn test_preview_next_charge_matches_stored_subscription() {
+    let (env, client, _, _) = setup_test_env();
+    for status in [
+        SubscriptionStatus::Active,
+        SubscriptionStatus::Paused,
+        SubscriptionStatus::Cancelled,
+        SubscriptionStatus::InsufficientBalance,
+        SubscriptionStatus::GracePeriod,
+    ] {
+        let (id, _, _) = create_test_subscription(&env, &client, status.clone());
+        let sub = client.get_subscription(&id);
+        let stored = client.get_next_charge_info(&id);
+        let preview =
+            client.preview_next_charge(&sub.last_payment_timestamp, &sub.interval_seconds, &status);
+        assert_eq!(preview, stored);
+    }
+
+    // Overflowing schedules saturate like stored ones.
+    let info = client.preview_next_charge(&u64::MAX, &1, &SubscriptionStatus::Active);
+    assert_eq!(info.next_charge_timestamp, u64::MAX);
+}
+
+// =============================================================================
+// Post-paid Billing Tests
+// =============================================================================
+
+#[test]
+fn test_postpaid_cycle_bills_recorded_usage() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+    let merchant = client.get_subscription(&id).merchant;
+    client.set_postpaid(&id, &merchant, &true);
+    assert!(client.is_postpaid(&id));
+
+    client.record_usage(&id, &merchant, &1_500_000i128);
+    client.record_usage(&id, &merchant, &500_000i128);
+
+    // Nothing is billed before the period ends.
+    env.ledger().set_timestamp(T0 + INTERVAL - 1);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::IntervalNotElapsed))
+    );
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.prepaid_balance, PREPAID - 2_000_000);
+    assert_eq!(sub.last_payment_timestamp, T0 + INTERVAL);
+    assert_eq!(client.get_pending_usage(&id), 0);
+    assert_eq!(merchant_balance(&env, &client, &merchant), 2_000_000);
+
+    // A period without usage bills nothing.
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 2_000_000
+    );
+}
+
+#[test]
+fn test_set_postpaid_requires_usage_and_merchant() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
+
+    assert_eq!(
+        client.try_set_postpaid(&id, &merchant, &true),
+        Err(Ok(Error::UsageNotEnabled))
+    );
+    assert_eq!(
+        client.try_set_postpaid(&id, &Address::generate(&env), &false),
+        Err(Ok(Error::Unauthorized))
+    );
+    assert!(!client.is_postpaid(&id));
+}
+
+// =============================================================================
+// First Charge Grace Tests
+// =============================================================================
+
+const DAY: u64 = 24 * 60 * 60;
+
+#[test]
+fn test_first_charge_enters_grace_when_unfunded() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+    client.set_grace_period(&admin, &0);
+    client.set_first_charge_grace(&admin, &DAY);
+    assert_eq!(client.get_first_charge_grace(), DAY);
+    set_prepaid(&env, &client, id, 0);
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    // batch_charge keeps the status change that a failing direct charge would revert.
+    let results = client.batch_charge(&SorobanVec::from_array(&env, [id]));
+    assert_eq!(
+        results.get(0).unwrap().error_code,
+        Error::InsufficientBalance.to_code()
+    );
+    assert_eq!(
+        client.get_subscription(&id).status,
+        SubscriptionStatus::GracePeriod
+    );
+
+    // Funding within the window lets the first charge go through.
+    set_prepaid(&env, &client, id, PREPAID);
+    env.ledger().set_timestamp(T0 + INTERVAL + DAY / 2);
+    client.charge_subscription(&id);
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.status, SubscriptionStatus::Active);
+    assert_eq!(sub.charge_count, 1);
+}
+
+#[test]
+fn test_later_charge_suspends_without_first_charge_grace() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+    client.set_grace_period(&admin, &0);
+    client.set_first_charge_grace(&admin, &DAY);
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+    set_prepaid(&env, &client, id, 0);
+
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    let results = client.batch_charge(&SorobanVec::from_array(&env, [id]));
+    assert_eq!(
+        results.get(0).unwrap().error_code,
+        Error::InsufficientBalance.to_code()
+    );
+    assert_eq!(
+        client.get_subscription(&id).status,
+        SubscriptionStatus::InsufficientBalance
+    );
+}
+
+// =============================================================================
+// Charge Flag Tests
+// =============================================================================
+
+#[test]
+fn test_disabling_usage_keeps_interval_charges() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+    let subscriber = client.get_subscription(&id).subscriber;
+    assert_eq!(client.get_charge_flags(&id), (true, true));
+
+    client.set_charge_flags(&id, &subscriber, &true, &false);
+    assert_eq!(client.get_charge_flags(&id), (true, false));
+    assert_eq!(
+        client.try_charge_usage(&id, &1_000i128),
+        Err(Ok(Error::NotActive))
+    );
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.prepaid_balance, PREPAID - 10_000_000);
+    assert_eq!(sub.status, SubscriptionStatus::Active);
+
+    client.set_charge_flags(&id, &subscriber, &true, &true);
+    client.charge_usage(&id, &1_000i128);
+}
+
+#[test]
+fn test_disabling_interval_keeps_usage_charges() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+    let merchant = client.get_subscription(&id).merchant;
+
+    client.set_charge_flags(&id, &merchant, &false, &true);
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::NotActive))
+    );
+    client.charge_usage(&id, &1_000i128);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 1_000
+    );
+
+    assert_eq!(
+        client.try_set_charge_flags(&id, &Address::generate(&env), &true, &true),
+        Err(Ok(Error::Forbidden))
+    );
+}
+
+// =============================================================================
+// Grace Sweep Tests
+// =============================================================================
+
+#[test]
+fn test_sweep_expired_grace_suspends_only_expired() {
+    let (env, client, _, admin) = setup_test_env();
+    client.set_grace_period(&admin, &DAY);
+    env.ledger().set_timestamp(T0);
+
+    let (expired, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::GracePeriod);
+    env.ledger().set_timestamp(T0 + 10 * DAY);
+    let (within, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::GracePeriod);
+    let (active, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+
+    // 30-day interval: `expired` was due at T0 + 30d and its grace ended a day later;
+    // `within` is due at T0 + 40d.
+    env.ledger().set_timestamp(T0 + 40 * DAY);
+    assert_eq!(client.sweep_expired_grace(&admin, &0, &10), 1);
+
+    assert_eq!(
+        client.get_subscription(&expired).status,
+        SubscriptionStatus::InsufficientBalance
+    );
+    assert_eq!(
+        client.get_subscription(&within).status,
+        SubscriptionStatus::GracePeriod
+    );
+    assert_eq!(
+        client.get_subscription(&active).status,
+        SubscriptionStatus::Active
+    );
+
+    // Nothing left to sweep until `within` expires too.
+    assert_eq!(client.sweep_expired_grace(&admin, &0, &10), 0);
+    env.ledger().set_timestamp(T0 + 41 * DAY);
+    assert_eq!(client.sweep_expired_grace(&admin, &0, &10), 1);
+}
+
+#[test]
+fn test_sweep_expired_grace_requires_admin() {
+    let (env, client, _, _) = setup_test_env();
+    assert_eq!(
+        client.try_sweep_expired_grace(&Address::generate(&env), &0, &10),
+        Err(Ok(Error::Forbidden))
+    );
+}
+
+// =============================================================================
+// Merchant Dashboard Tests
+// =============================================================================
+
+#[test]
+fn test_merchant_dashboard_figures() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+
+    let empty = client.get_merchant_dashboard(&merchant, &0, &10);
+    assert_eq!(empty.active_subscriptions, 2);
+    assert_eq!(empty.total_subscriptions, 2);
+    assert_eq!(empty.lifetime_revenue, 0);
+
+    // Two charges on id0, a failed one on id1, then a withdrawal.
+    client.charge_subscription(&id0);
+    let _ = client.try_charge_subscription(&id1);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription(&id0);
+    client.withdraw_merchant_funds(&merchant, &1_500i128);
+    client.pause_subscription(&id1, &merchant, &None);
+
+    let dash = client.get_merchant_dashboard(&merchant, &0, &10);
+    assert_eq!(
+        dash,
+        crate::MerchantDashboard {
+            active_subscriptions: 1,
+            total_subscriptions: 2,
+            withdrawable_balance: 500,
+            lifetime_revenue: 2_000,
+        }
+    );
+
+    // The active count only covers the requested slice of the index.
+    assert_eq!(
+        client
+            .get_merchant_dashboard(&merchant, &1, &10)
+            .active_subscriptions,
+        0
+    );
+}
+
+// =============================================================================
+// Expected Amount Tests
+// =============================================================================
+
+#[test]
+fn test_charge_with_matching_expected_amount() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription_expected(&id, &Some(10_000_000i128));
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 10_000_000
+    );
+
+    // None skips the check.
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    client.charge_subscription_expected(&id, &None);
+    assert_eq!(client.get_subscription(&id).charge_count, 2);
+}
+
+#[test]
+fn test_charge_with_mismatched_expected_amount_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    // The price changed after the charger read it.
+    set_amount(&env, &client, id, 12_000_000);
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert_eq!(
+        client.try_charge_subscription_expected(&id, &Some(10_000_000i128)),
+        Err(Ok(Error::InvalidAmount))
+    );
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.prepaid_balance, PREPAID);
+    assert_eq!(sub.charge_count, 0);
+
+    client.charge_subscription_expected(&id, &Some(12_000_000i128));
+}
+
+/// Every entrypoint that charges goes through `charge_core::charge_one`; charging the
+/// same due subscriptions through each path must debit identically.
+#[test]
+fn test_all_charge_paths_share_charge_one() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let amount = client.get_subscription(&id0).amount;
+    let start = client.get_subscription(&id0).prepaid_balance;
+
+    client.charge_subscription(&id0);
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    assert!(
+        client
+            .batch_charge(&SorobanVec::from_array(&env, [id0]))
+            .get(0)
+            .unwrap()
+            .success
+    );
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL);
+    client.charge_bundle(&SorobanVec::from_array(&env, [id0]));
+    env.ledger().set_timestamp(T0 + 4 * INTERVAL);
+    client.charge_subscription_detailed(&id0);
+    env.ledger().set_timestamp(T0 + 5 * INTERVAL);
+    client.charge_subscription_expected(&id0, &Some(amount));
+
+    let sub = client.get_subscription(&id0);
+    assert_eq!(sub.charge_count, 5);
+    assert_eq!(sub.prepaid_balance, start - 5 * amount);
+}
+
+// =============================================================================
+// Calendar Month Billing Tests
+// =============================================================================
+
+const JAN_31_2023: u64 = 1_675_123_200;
+const FEB_28_2023: u64 = 1_677_542_400;
+const JAN_31_2024: u64 = 1_706_659_200;
+const FEB_29_2024: u64 = 1_709_164_800;
+const MAR_31_2024: u64 = 1_711_843_200;
+const APR_30_2024: u64 = 1_714_435_200;
+
+#[test]
+fn test_next_calendar_month_clamps_to_month_end() {
+    use crate::{next_calendar_month, next_calendar_month_on};
+
+    assert_eq!(next_calendar_month(JAN_31_2023), FEB_28_2023);
+    assert_eq!(next_calendar_month(JAN_31_2024), FEB_29_2024);
+    // A clamped payment keeps billing on the original day when it is known.
+    assert_eq!(next_calendar_month_on(FEB_29_2024, 31), MAR_31_2024);
+    assert_eq!(next_calendar_month(MAR_31_2024), APR_30_2024);
+}
+
+#[test]
+fn test_next_calendar_month_normal_rollovers() {
+    use crate::next_calendar_month;
+
+    // 2024-03-15 -> 2024-04-15, time of day preserved.
+    assert_eq!(
+        next_calendar_month(1_710_460_800 + 3_600),
+        1_713_139_200 + 3_600
+    );
+    // 2023-12-10 -> 2024-01-10 across the year boundary.
+    assert_eq!(next_calendar_month(1_702_166_400), 1_704_844_800);
+    assert_eq!(next_calendar_month(u64::MAX), u64::MAX);
+}
+
+#[test]
+fn test_monthly_calendar_subscription_charges_on_month_day() {
+    let (env, client, _, _) = setup_test_env();
+    env.ledger().set_timestamp(JAN_31_2024);
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let id =
+        client.create_subscription(&subscriber, &merchant, &1_000i128, &INTERVAL, &false, &None);
+    set_prepaid(&env, &client, id, 10_000);
+    assert_eq!(client.get_billing_mode(&id), crate::BillingMode::Interval);
+
+    client.set_billing_mode(&id, &merchant, &crate::BillingMode::MonthlyCalendar);
+    assert_eq!(
+        client.get_billing_mode(&id),
+        crate::BillingMode::MonthlyCalendar
+    );
+    assert_eq!(
+        client.get_next_charge_info(&id).next_charge_timestamp,
+        FEB_29_2024
+    );
+
+    // Due on Feb 29, a day before 30 days would have elapsed.
+    env.ledger().set_timestamp(FEB_29_2024 - 1);
+    assert!(client.try_charge_subscription(&id).is_err());
+    env.ledger().set_timestamp(FEB_29_2024);
+    client.charge_subscription(&id);
+
+    // The billing day stays the 31st after a clamped month.
+    assert_eq!(
+        client.get_next_charge_info(&id).next_charge_timestamp,
+        MAR_31_2024
+    );
+    env.ledger().set_timestamp(MAR_31_2024);
+    client.charge_subscription(&id);
+    assert_eq!(client.get_subscription(&id).charge_count, 2);
+
+    assert_eq!(
+        client.try_set_billing_mode(&id, &subscriber, &crate::BillingMode::Interval),
+        Err(Ok(Error::Unauthorized))
+    );
+}
+
+// =============================================================================
+// Deposit Count Tests
+// =============================================================================
+
+#[test]
+fn test_deposit_count_tracks_deposits() {
+    let (env, client, token, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let id =
+        client.create_subscription(&subscriber, &merchant, &1000i128, &INTERVAL, &false, &None);
+    assert_eq!(client.get_subscription(&id).deposit_count, 0);
+
+    mint_for_subscriber(&env, &token, &subscriber, 10_000000);
+    for _ in 0..3 {
+        client.deposit_funds(&id, &subscriber, &1_000000i128, &None);
+    }
+    // A rejected deposit is not counted.
+    assert!(client
+        .try_deposit_funds(&id, &subscriber, &1i128, &None)
+        .is_err());
+
+    assert_eq!(client.get_subscription(&id).deposit_count, 3);
+    assert_eq!(client.get_subscription_summary(&id).deposit_count, 3);
+}
+
+#[test]
+fn test_migration_from_v4_starts_deposit_count_at_zero() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+
+    let v4 = SubscriptionV4 {
+        subscriber: sub.subscriber,
+        merchant: sub.merchant,
+        amount: sub.amount,
+        interval_seconds: sub.interval_seconds,
+        last_payment_timestamp: sub.last_payment_timestamp,
+        status: sub.status,
+        prepaid_balance: sub.prepaid_balance,
+        usage_enabled: sub.usage_enabled,
+        category: sub.category,
+        charge_count: 4,
+        metadata: Some(soroban_sdk::Bytes::from_slice(&env, b"user-42")),
+    };
+    env.as_contract(&client.address, || {
+        env.storage().instance().set(&id, &v4);
+        env.storage()
+            .instance()
+            .set(&soroban_sdk::Symbol::new(&env, "storage_version"), &4u32);
+    });
+
+    client.migrate(&client.get_admin(), &100);
+    let upgraded = client.get_subscription(&id);
+    assert_eq!(upgraded.deposit_count, 0);
+    assert_eq!(upgraded.charge_count, 4);
+    assert_eq!(
+        upgraded.metadata,
+        Some(soroban_sdk::Bytes::from_slice(&env, b"user-42"))
+    );
+    assert_eq!(client.get_storage_version(), 6);
+}
+
+// =============================================================================
+// Contract pause and emergency withdrawal
+// =============================================================================
+
+#[test]
+fn test_emergency_withdraw_while_paused_refunds_and_cancels() {
+    let env = Env::default();
+    let (client, admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    let before = token.balance(&sub.subscriber);
+    let active_before = client.get_active_count();
+
+    client.set_contract_paused(&admin, &true);
+    assert!(client.is_contract_paused());
+    assert_eq!(
+        client.try_charge_subscription(&id0),
+        Err(Ok(Error::ContractPaused))
+    );
+    assert_eq!(
+        client.try_deposit_funds(&id1, &sub.subscriber, &10_000000i128, &None),
+        Err(Ok(Error::ContractPaused))
+    );
+
+    client.emergency_withdraw(&id0, &sub.subscriber);
+    let cancelled_topic: soroban_sdk::Vec<soroban_sdk::Val> =
+        (soroban_sdk::symbol_short!("cancelled"), id0).into_val(&env);
+    let (_, _, data) = env
+        .events()
+        .all()
+        .iter()
+        .find(|(_, topics, _)| *topics == cancelled_topic)
+        .unwrap();
+    let event = SubscriptionCancelledEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(event.authorizer, sub.subscriber);
+    assert_eq!(event.refund_amount, 10_000000i128);
+    let after = client.get_subscription(&id0);
+    assert_eq!(after.status, SubscriptionStatus::Cancelled);
+    assert_eq!(after.prepaid_balance, 0);
+    assert_eq!(token.balance(&sub.subscriber), before + 10_000000i128);
+    assert_eq!(client.get_active_count(), active_before - 1);
+}
+
+#[test]
+fn test_emergency_withdraw_during_migration_keeps_totals() {
+    let env = Env::default();
+    let (client, admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    client.set_contract_paused(&admin, &true);
+
+    // A version 5 deployment part-way through its migration: only id0 is counted.
+    set_prepaid(&env, &client, id1, 5_000000i128);
+    mint_for_subscriber(&env, &client.get_token(), &client.address, 5_000000i128);
+    env.as_contract(&client.address, || {
+        let storage = env.storage().instance();
+        storage.set(&soroban_sdk::Symbol::new(&env, "storage_version"), &5u32);
+        storage.remove(&soroban_sdk::Symbol::new(&env, "total_prepaid"));
+        storage.remove(&soroban_sdk::Symbol::new(&env, "total_merchant"));
+    });
+    assert_eq!(client.migrate(&admin, &1), 3);
+
+    // Withdrawals stay open, whether or not the record has been migrated.
+    client.emergency_withdraw(&id1, &sub.subscriber);
+    client.emergency_withdraw(&id0, &sub.subscriber);
+
+    assert_eq!(client.migrate(&admin, &100), 0);
+    assert_eq!(client.get_balance_sheet().prepaid_liabilities, 0);
+}
+
+#[test]
+fn test_emergency_withdraw_rejects_unmigrated_legacy_record() {
+    let env = Env::default();
+    let (client, admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id1);
+    client.set_contract_paused(&admin, &true);
+
+    // A version 4 deployment before `migrate` has reached id1.
+    let v4 = SubscriptionV4 {
+        subscriber: sub.subscriber.clone(),
+        merchant: sub.merchant,
+        amount: sub.amount,
+        interval_seconds: sub.interval_seconds,
+        last_payment_timestamp: sub.last_payment_timestamp,
+        status: sub.status,
+        prepaid_balance: sub.prepaid_balance,
+        usage_enabled: sub.usage_enabled,
+        category: sub.category,
+        charge_count: sub.charge_count,
+        metadata: None,
+    };
+    env.as_contract(&client.address, || {
+        let storage = env.storage().instance();
+        storage.set(&id1, &v4);
+        storage.set(&soroban_sdk::Symbol::new(&env, "storage_version"), &4u32);
+        storage.set(&soroban_sdk::Symbol::new(&env, "migrate_cursor"), &id1);
+    });
+
+    assert_eq!(
+        client.try_emergency_withdraw(&id1, &sub.subscriber),
+        Err(Ok(Error::MigrationRequired))
+    );
+    // id0 is below the cursor and already in the current layout.
+    client.emergency_withdraw(&id0, &sub.subscriber);
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 0);
+}
+
+#[test]
+fn test_emergency_withdraw_rejected_when_not_paused() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+
+    assert!(!client.is_contract_paused());
+    assert_eq!(
+        client.try_emergency_withdraw(&id0, &sub.subscriber),
+        Err(Ok(Error::Forbidden))
+    );
+    assert_eq!(client.get_subscription(&id0).prepaid_balance, 10_000000i128);
+}
+
+#[test]
+fn test_set_contract_paused_requires_admin() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let outsider = Address::generate(&env);
+    assert_eq!(
+        client.try_set_contract_paused(&outsider, &true),
+        Err(Ok(Error::Forbidden))
+    );
+
+    client.set_contract_paused(&admin, &true);
+    client.set_contract_paused(&admin, &false);
+    client.charge_subscription(&id0);
+}
+
+#[test]
+fn test_set_fee_bps_without_collector_rejected() {
+    let (env, client, _, admin) = setup_test_env();
+    assert_eq!(
+        client.try_set_fee_bps(&admin, &100),
+        Err(Ok(Error::InvalidInput))
+    );
+    assert_eq!(client.get_fee_bps(), 0);
+    assert_eq!(client.try_get_fee_collector(), Err(Ok(Error::NotFound)));
+    // A zero fee never needs a collector.
+    client.set_fee_bps(&admin, &0);
+
+    let outsider = Address::generate(&env);
+    assert_eq!(
+        client.try_set_fee_collector(&outsider, &outsider),
+        Err(Ok(Error::Forbidden))
+    );
+}
+
+#[test]
+fn test_set_fee_bps_with_collector_succeeds() {
+    let (env, client, _, admin) = setup_test_env();
+    let collector = Address::generate(&env);
+    client.set_fee_collector(&admin, &collector);
+    assert_eq!(client.get_fee_collector(), collector);
+
+    client.set_fee_bps(&admin, &250);
+    assert_eq!(client.get_fee_bps(), 250);
+    assert_eq!(
+        client.try_set_fee_bps(&admin, &10_001),
+        Err(Ok(Error::InvalidInput))
+    );
+}
+
+#[test]
+fn test_platform_fee_credited_to_collector_on_charge() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let collector = Address::generate(&env);
+    client.set_fee_collector(&admin, &collector);
+    client.set_fee_bps(&admin, &1_000);
+
+    client.charge_subscription(&id0);
+    assert_eq!(merchant_balance(&env, &client, &collector), 100);
+    assert_eq!(merchant_balance(&env, &client, &sub.merchant), 900);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        10_000_000 - sub.amount
+    );
+
+    // The collector withdraws its fees like a merchant.
+    client.withdraw_merchant_funds(&collector, &100);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    assert_eq!(token.balance(&collector), 100);
+}
+
+#[test]
+fn test_platform_fee_rounds_down_before_payee_split() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let collector = Address::generate(&env);
+    let payee = Address::generate(&env);
+    client.set_fee_collector(&admin, &collector);
+    // 3.33% of 1000 is 33.3: the collector gets 33.
+    client.set_fee_bps(&admin, &333);
+    client.set_payees(
+        &id0,
+        &sub.merchant,
+        &soroban_sdk::vec![&env, (payee.clone(), 2_500u32)],
+    );
+
+    client.charge_subscription(&id0);
+    assert_eq!(merchant_balance(&env, &client, &collector), 33);
+    // Payees split what is left after the fee: 25% of 967 is 241.75, rounded down.
+    assert_eq!(merchant_balance(&env, &client, &payee), 241);
+    assert_eq!(merchant_balance(&env, &client, &sub.merchant), 726);
+}
+
+#[test]
+fn test_get_delinquent_returns_only_delinquent_ids() {
+    let (env, client, _, _) = setup_test_env();
+    let (active, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let (insufficient, _, _) =
+        create_test_subscription(&env, &client, SubscriptionStatus::InsufficientBalance);
+    let (paused, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Paused);
+    let (grace, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::GracePeriod);
+    let (cancelled, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Cancelled);
+
+    let ids = client.get_delinquent(&0, &100);
+    assert_eq!(ids, soroban_sdk::vec![&env, insufficient, grace]);
+    for id in [active, paused, cancelled] {
+        assert!(!ids.contains(id));
+    }
+
+    // The scan range is bounded by start_id and limit.
+    assert_eq!(
+        client.get_delinquent(&(insufficient + 1), &2),
+        soroban_sdk::vec![&env, grace]
+    );
+    assert_eq!(client.get_delinquent(&0, &1).len(), 0);
+}
+
+#[test]
+fn test_gift_deposit_funds_another_users_subscription() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token_addr = client.get_token();
+    let sponsor = Address::generate(&env);
+    mint_for_subscriber(&env, &token_addr, &sponsor, 5_000000i128);
+
+    client.gift_deposit(&id0, &sponsor, &5_000000i128);
+    let (contract, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(contract, client.address);
+    let expected_topics: soroban_sdk::Vec<soroban_sdk::Val> =
+        (soroban_sdk::Symbol::new(&env, "gift_deposit"), id0).into_val(&env);
+    assert_eq!(topics, expected_topics);
+    let event = GiftDepositEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(event.sponsor, sponsor);
+    assert_eq!(event.subscriber, sub.subscriber);
+    assert_eq!(event.amount, 5_000000i128);
+    assert_eq!(event.new_balance, sub.prepaid_balance + 5_000000i128);
+
+    let after = client.get_subscription(&id0);
+    assert_eq!(after.subscriber, sub.subscriber);
+    assert_eq!(after.prepaid_balance, sub.prepaid_balance + 5_000000i128);
+    let token = soroban_sdk::token::Client::new(&env, &token_addr);
+    assert_eq!(token.balance(&sponsor), 0);
+}
+
+#[test]
+fn test_gift_deposit_allowed_under_owner_restriction() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let before = client.get_subscription(&id0).prepaid_balance;
+    let sponsor = Address::generate(&env);
+    mint_for_subscriber(&env, &client.get_token(), &sponsor, 10_000000i128);
+
+    client.set_restrict_deposits_to_owner(&admin, &true);
+    assert_eq!(
+        client.try_deposit_funds(&id0, &sponsor, &5_000000i128, &None),
+        Err(Ok(Error::Forbidden))
+    );
+    client.gift_deposit(&id0, &sponsor, &5_000000i128);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        before + 5_000000i128
+    );
+}
+
+#[test]
+fn test_auto_topup_triggers_below_threshold() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    token.approve(
+        &sub.subscriber,
+        &client.address,
+        &5_000000i128,
+        &(env.ledger().sequence() + 1000),
+    );
+    client.set_auto_topup(&id0, &sub.subscriber, &5_000000i128, &20_000000i128);
+    let wallet_before = token.balance(&sub.subscriber);
+
+    client.charge_subscription(&id0);
+    let after = client.get_subscription(&id0);
+    assert_eq!(
+        after.prepaid_balance,
+        sub.prepaid_balance - sub.amount + 5_000000i128
+    );
+    assert_eq!(token.balance(&sub.subscriber), wallet_before - 5_000000i128);
+}
+
+#[test]
+fn test_auto_topup_skipped_above_threshold() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    token.approve(
+        &sub.subscriber,
+        &client.address,
+        &5_000000i128,
+        &(env.ledger().sequence() + 1000),
+    );
+    client.set_auto_topup(&id0, &sub.subscriber, &5_000000i128, &1_000000i128);
+
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        sub.prepaid_balance - sub.amount
+    );
+}
+
+#[test]
+fn test_auto_topup_without_allowance_still_charges() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    client.set_auto_topup(&id0, &sub.subscriber, &5_000000i128, &20_000000i128);
+    assert_eq!(
+        client.get_auto_topup(&id0),
+        Some(AutoTopupConfig {
+            amount: 5_000000i128,
+            threshold: 20_000000i128,
+        })
+    );
+
+    client.charge_subscription(&id0);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        sub.prepaid_balance - sub.amount
+    );
+
+    client.set_auto_topup(&id0, &sub.subscriber, &0, &0);
+    assert_eq!(client.get_auto_topup(&id0), None);
+}
+
+#[test]
+fn test_auto_topup_not_pulled_by_rejected_charge() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    token.approve(
+        &sub.subscriber,
+        &client.address,
+        &10_000000i128,
+        &(env.ledger().sequence() + 1000),
+    );
+    client.set_auto_topup(&id0, &sub.subscriber, &5_000000i128, &20_000000i128);
+    let wallet_before = token.balance(&sub.subscriber);
+
+    // Price guard mismatch: rejected before anything is pulled.
+    assert_eq!(
+        client.try_charge_subscription_expected(&id0, &Some(sub.amount + 1)),
+        Err(Ok(Error::InvalidAmount))
+    );
+
+    // Maintenance window: a batch keeps the writes of failed slots, so a top-up taken
+    // before the check would stick.
+    client.set_maintenance_until(&sub.merchant, &(T0 + INTERVAL + 500));
+    let results = client.batch_charge(&SorobanVec::from_array(&env, [id0]));
+    assert_eq!(
+        results.get(0).unwrap().error_code,
+        Error::InMaintenance.to_code()
+    );
+    assert_eq!(token.balance(&sub.subscriber), wallet_before);
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        sub.prepaid_balance
+    );
+
+    // Once charged, the top-up is pulled; minimal event mode drops its event.
+    env.ledger().set_timestamp(T0 + INTERVAL + 500);
+    client.set_events_verbose(&admin, &false);
+    client.charge_subscription(&id0);
+    let topic = soroban_sdk::Symbol::new(&env, "auto_topup");
+    assert!(!env.events().all().iter().any(|(_, topics, _)| {
+        soroban_sdk::Symbol::try_from_val(&env, &topics.get(0).unwrap()).ok() == Some(topic.clone())
+    }));
+    assert_eq!(token.balance(&sub.subscriber), wallet_before - 5_000000i128);
+}
+
+#[test]
+fn test_get_merchant_pending_usage_aggregates_subscriptions() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id0) = setup_usage(&env);
+    let merchant = client.get_subscription(&id0).merchant;
+    let id1 = client.create_subscription(
+        &Address::generate(&env),
+        &merchant,
+        &10_000_000i128,
+        &INTERVAL,
+        &true,
+        &None,
+    );
+    let id2 = client.create_subscription(
+        &Address::generate(&env),
+        &merchant,
+        &10_000_000i128,
+        &INTERVAL,
+        &true,
+        &None,
+    );
+    // Another merchant's usage is not counted.
+    let other = client.create_subscription(
+        &Address::generate(&env),
+        &Address::generate(&env),
+        &10_000_000i128,
+        &INTERVAL,
+        &true,
+        &None,
+    );
+
+    client.record_usage(&id0, &merchant, &100);
+    client.record_usage(&id1, &merchant, &250);
+    client.record_usage(&id1, &merchant, &50);
+    let other_merchant = client.get_subscription(&other).merchant;
+    client.record_usage(&other, &other_merchant, &1_000);
+
+    assert_eq!(client.get_merchant_pending_usage(&merchant, &0, &10), 400);
+    // id2 has no usage; a window over it alone is zero.
+    assert_eq!(client.get_merchant_pending_usage(&merchant, &2, &10), 0);
+    assert_eq!(client.get_merchant_pending_usage(&merchant, &1, &1), 300);
+    assert_eq!(client.get_pending_usage(&id2), 0);
+}
+
+#[test]
+fn test_merchant_confirmation_blocks_charges_until_confirmed() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, _) = setup(&env, INTERVAL);
+    client.set_merchant_confirmation(&client.get_admin(), &true);
+    assert!(client.get_merchant_confirmation());
+
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &10_000_000i128,
+        &INTERVAL,
+        &true,
+        &None,
+    );
+    set_prepaid(&env, &client, id, PREPAID);
+    assert!(client.is_pending_merchant(&id));
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::NotActive))
+    );
+    assert_eq!(
+        client.try_charge_usage(&id, &100),
+        Err(Ok(Error::NotActive))
+    );
+
+    assert_eq!(
+        client.try_confirm_subscription(&id, &subscriber),
+        Err(Ok(Error::Unauthorized))
+    );
+    client.confirm_subscription(&id, &merchant);
+    assert!(!client.is_pending_merchant(&id));
+    client.charge_subscription(&id);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 10_000_000
+    );
+}
+
+#[test]
+fn test_subscriptions_auto_confirmed_by_default() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    assert!(!client.get_merchant_confirmation());
+    assert!(!client.is_pending_merchant(&id));
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+}
+
+#[test]
+fn test_cancellation_reason_in_event() {
+    let (env, client, _, _) = setup_test_env();
+    for reason in [
+        CancellationReason::UserRequested,
+        CancellationReason::Delinquent,
+        CancellationReason::MerchantClosed,
+        CancellationReason::Fraud,
+    ] {
+        let (id, subscriber, _) =
+            create_test_subscription(&env, &client, SubscriptionStatus::Active);
+        client.cancel_subscription_with_reason(&id, &subscriber, &reason);
+
+        let (_, topics, data) = env.events().all().last().unwrap();
+        let expected_topics: soroban_sdk::Vec<soroban_sdk::Val> =
+            (soroban_sdk::symbol_short!("cancelled"), id).into_val(&env);
+        assert_eq!(topics, expected_topics);
+        let event = SubscriptionCancelledEvent::try_from_val(&env, &data).unwrap();
+        assert_eq!(event.subscription_id, id);
+        assert_eq!(event.authorizer, subscriber);
+        assert_eq!(event.reason, reason);
+    }
+}
+
+#[test]
+fn test_cancel_subscription_defaults_to_user_requested() {
+    let (env, client, _, _) = setup_test_env();
+    let (id, _, merchant) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    client.cancel_subscription(&id, &merchant);
+
+    let (_, _, data) = env.events().all().last().unwrap();
+    let event = SubscriptionCancelledEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(event.reason, CancellationReason::UserRequested);
+    assert_eq!(event.authorizer, merchant);
+}
+
+#[test]
+fn test_balance_sheet_balances_after_deposits_and_charges() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+
+    let sheet = client.get_balance_sheet();
+    assert_eq!(sheet.prepaid_liabilities, 10_000000i128);
+    assert_eq!(sheet.merchant_liabilities, 0);
+    assert_eq!(sheet.token_balance, 10_000000i128);
+
+    client.deposit_funds(&id1, &sub.subscriber, &2_000000i128, &None);
+    client.charge_subscription(&id0);
+    let sheet = client.get_balance_sheet();
+    assert_eq!(sheet.prepaid_liabilities, 12_000000i128 - sub.amount);
+    assert_eq!(sheet.merchant_liabilities, sub.amount);
+    assert_eq!(
+        sheet.token_balance,
+        sheet.prepaid_liabilities + sheet.merchant_liabilities
+    );
+
+    // Tokens sent straight to the vault show up as surplus.
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    token.transfer(&sub.subscriber, &client.address, &500);
+    let sheet = client.get_balance_sheet();
+    assert_eq!(
+        sheet.token_balance - sheet.prepaid_liabilities - sheet.merchant_liabilities,
+        500
+    );
+}
+
+#[test]
+fn test_balance_sheet_totals_track_refunds_and_withdrawals() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let collector = Address::generate(&env);
+    client.set_fee_collector(&admin, &collector);
+    client.set_fee_bps(&admin, &1_000);
+
+    client.charge_subscription(&id0);
+    client.merchant_refund(&id0, &sub.merchant, &400);
+    client.withdraw_merchant_funds(&sub.merchant, &500);
+    client.withdraw_merchant_funds(&collector, &100);
+    client.cancel_subscription(&id0, &sub.subscriber);
+    client.withdraw_subscriber_funds(&id0, &sub.subscriber);
+
+    let sheet = client.get_balance_sheet();
+    assert_eq!(sheet.prepaid_liabilities, 0);
+    assert_eq!(sheet.merchant_liabilities, 0);
+    assert_eq!(sheet.token_balance, 0);
+}
+
+#[test]
+fn test_migration_backfills_balance_sheet_totals() {
+    let env = Env::default();
+    let (client, admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let payee = Address::generate(&env);
+    client.set_payees(
+        &id1,
+        &sub.merchant,
+        &soroban_sdk::vec![&env, (payee, 5_000u32)],
+    );
+    client.deposit_funds(&id1, &sub.subscriber, &2_000000i128, &None);
+    client.batch_charge(&soroban_sdk::vec![&env, id0, id1]);
+    let before = client.get_balance_sheet();
+
+    // Simulate a version 5 deployment, which kept no running totals.
+    env.as_contract(&client.address, || {
+        let storage = env.storage().instance();
+        storage.remove(&soroban_sdk::Symbol::new(&env, "total_prepaid"));
+        storage.remove(&soroban_sdk::Symbol::new(&env, "total_merchant"));
+        storage.set(&soroban_sdk::Symbol::new(&env, "storage_version"), &5u32);
+    });
+    assert_eq!(client.get_balance_sheet().prepaid_liabilities, 0);
+
+    assert_eq!(client.migrate(&admin, &1), 3);
+    assert_eq!(client.migrate(&admin, &1), 2);
+    assert_eq!(client.migrate(&admin, &1), 1);
+    assert_eq!(client.migrate(&admin, &1), 0);
+    assert_eq!(client.get_balance_sheet(), before);
+    // The per-recipient markers are gone once the migration completes.
+    env.as_contract(&client.address, || {
+        let marker = (
+            soroban_sdk::Symbol::new(&env, "migrate_counted"),
+            sub.merchant.clone(),
+        );
+        assert!(!env.storage().instance().has(&marker));
+    });
+    assert_eq!(
+        before.token_balance,
+        before.prepaid_liabilities + before.merchant_liabilities
+    );
+}
+
+#[test]
+fn test_maintenance_window_blocks_then_resumes_charges() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
+
+    // Window opens 100s before the charge is due and lasts 600s in total.
+    env.ledger().set_timestamp(T0 + INTERVAL - 100);
+    client.set_maintenance_until(&merchant, &(T0 + INTERVAL + 500));
+    assert_eq!(
+        client.get_maintenance_window(&merchant),
+        Some(MaintenanceWindow {
+            start: T0 + INTERVAL - 100,
+            until: T0 + INTERVAL + 500,
+        })
+    );
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::InMaintenance))
+    );
+
+    // The schedule shifts by the maintenance time, so the window's end is not yet due.
+    env.ledger().set_timestamp(T0 + INTERVAL + 500);
+    assert_eq!(
+        client.get_next_charge_info(&id).next_charge_timestamp,
+        T0 + INTERVAL + 600
+    );
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::IntervalNotElapsed))
+    );
+
+    env.ledger().set_timestamp(T0 + INTERVAL + 600);
+    client.charge_subscription(&id);
+    assert_eq!(
+        client.get_subscription(&id).prepaid_balance,
+        PREPAID - 10_000_000
+    );
+    // The next period is a full interval from this charge.
+    assert_eq!(
+        client.get_next_charge_info(&id).next_charge_timestamp,
+        T0 + 2 * INTERVAL + 600
+    );
+}
+
+#[test]
+fn test_set_maintenance_until_validation() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
+
+    assert_eq!(
+        client.try_set_maintenance_until(&merchant, &T0),
+        Err(Ok(Error::InvalidInput))
+    );
+    client.set_maintenance_until(&merchant, &(T0 + 100));
+    client.set_maintenance_until(&merchant, &0);
+    assert_eq!(client.get_maintenance_window(&merchant), None);
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+}
+
+#[test]
+fn test_export_subscriptions_by_status_filters_active() {
+    let (env, client, _, admin) = setup_test_env();
+    let (active0, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    create_test_subscription(&env, &client, SubscriptionStatus::Paused);
+    let (active1, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    create_test_subscription(&env, &client, SubscriptionStatus::Cancelled);
+    create_test_subscription(&env, &client, SubscriptionStatus::InsufficientBalance);
+
+    let out = client.export_subscriptions_by_status(&admin, &SubscriptionStatus::Active, &0, &10);
+    let (_, _, data) = env.events().all().last().unwrap();
+    let event = MigrationExportEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(event.exported, 2);
+    assert_eq!(event.limit, 10);
+
+    assert_eq!(out.len(), 2);
+    assert_eq!(out.get(0).unwrap().subscription_id, active0);
+    assert_eq!(out.get(1).unwrap().subscription_id, active1);
+    for summary in out.iter() {
+        assert_eq!(summary.status, SubscriptionStatus::Active);
+    }
+
+    assert_eq!(
+        client
+            .export_subscriptions_by_status(&admin, &SubscriptionStatus::Paused, &0, &10)
+            .len(),
+        1
+    );
+    assert_eq!(
+        client.try_export_subscriptions_by_status(&admin, &SubscriptionStatus::Active, &0, &101),
+        Err(Ok(Error::InvalidExportLimit))
+    );
+}
+
+#[test]
+fn test_charge_usage_rejected_in_grace_period_and_insufficient_balance() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup_usage(&env);
+
+    for status in [
+        SubscriptionStatus::GracePeriod,
+        SubscriptionStatus::InsufficientBalance,
+    ] {
+        let mut sub = client.get_subscription(&id);
+        sub.status = status;
+        env.as_contract(&client.address, || {
+            env.storage().instance().set(&id, &sub);
+        });
+        assert_eq!(
+            client.try_charge_usage(&id, &1_000),
+            Err(Ok(Error::NotActive))
+        );
+        assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID);
+    }
+}
+
+#[test]
+fn test_charge_within_tolerance_anchors_to_schedule() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    client.set_charge_tolerance(&client.get_admin(), &300);
+    assert_eq!(client.get_charge_tolerance(), 300);
+
+    env.ledger().set_timestamp(T0 + INTERVAL - 300);
+    client.charge_subscription(&id);
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.prepaid_balance, PREPAID - 10_000_000);
+    assert_eq!(sub.last_payment_timestamp, T0 + INTERVAL);
+
+    // The next cycle is due a full interval after the scheduled time, not after `now`.
+    assert_eq!(
+        client.get_next_charge_info(&id).next_charge_timestamp,
+        T0 + 2 * INTERVAL
+    );
+    assert_eq!(client.try_charge_subscription(&id), Err(Ok(Error::Replay)));
+}
+
+#[test]
+fn test_charge_beyond_tolerance_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+    client.set_charge_tolerance(&admin, &300);
+
+    env.ledger().set_timestamp(T0 + INTERVAL - 301);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::IntervalNotElapsed))
+    );
+    assert_eq!(
+        client.try_set_charge_tolerance(&admin, &3_601),
+        Err(Ok(Error::InvalidInput))
+    );
+}
+
+#[test]
+fn test_get_plans_page_empty_catalog() {
+    let (_env, client, _, _) = setup_test_env();
+    assert_eq!(client.get_plans_page(&0, &10).len(), 0);
+}
+
+#[test]
+fn test_get_plans_page_across_merchants_skips_gaps() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant_a = Address::generate(&env);
+    let merchant_b = Address::generate(&env);
+    let p0 = client.create_plan_template(&merchant_a, &1_000i128, &3600, &false);
+    let p1 = client.create_plan_template(&merchant_b, &2_000i128, &3600, &false);
+    let p2 = client.create_plan_template(&merchant_a, &3_000i128, &3600, &true);
+    env.as_contract(&client.address, || {
+        env.storage().instance().remove(&crate::DataKey::Plan(p1));
+    });
+
+    let page = client.get_plans_page(&0, &10);
+    assert_eq!(page.len(), 2);
+    let (id, plan) = page.get(0).unwrap();
+    assert_eq!(
+        (id, plan.merchant, plan.amount),
+        (p0, merchant_a.clone(), 1_000)
+    );
+    let (id, plan) = page.get(1).unwrap();
+    assert_eq!((id, plan.merchant, plan.amount), (p2, merchant_a, 3_000));
+
+    assert_eq!(client.get_plans_page(&p2, &10).len(), 1);
+    assert_eq!(client.get_plans_page(&(p2 + 1), &10).len(), 0);
+}
+
+#[test]
+fn test_get_plans_page_limit_capped() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    for _ in 0..(crate::queries::MAX_SCAN_LIMIT + 1) {
+        client.create_plan_template(&merchant, &1_000i128, &3600, &false);
+    }
+    assert_eq!(client.get_plans_page(&0, &2).len(), 2);
+    assert_eq!(
+        client.get_plans_page(&0, &u32::MAX).len(),
+        crate::queries::MAX_SCAN_LIMIT
+    );
+    assert_eq!(
+        client
+            .get_plans_page(&crate::queries::MAX_SCAN_LIMIT, &u32::MAX)
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_sweep_stale_pauses_cancels_and_refunds_beyond_max() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    client.set_max_pause_seconds(&admin, &WEEK);
+    client.pause_subscription(&id0, &sub.subscriber, &None);
+    let paused_at = env.ledger().timestamp();
+    let wallet_before = token.balance(&sub.subscriber);
+
+    env.ledger().set_timestamp(paused_at + WEEK + 1);
+    assert_eq!(client.sweep_stale_pauses(&admin, &0, &10), 1);
+    let after = client.get_subscription(&id0);
+    assert_eq!(after.status, SubscriptionStatus::Cancelled);
+    assert_eq!(after.prepaid_balance, 0);
+    assert_eq!(
+        token.balance(&sub.subscriber),
+        wallet_before + sub.prepaid_balance
+    );
+    // Already cancelled; a second sweep finds nothing.
+    assert_eq!(client.sweep_stale_pauses(&admin, &0, &10), 0);
+}
+
+#[test]
+fn test_sweep_stale_pauses_emits_cancelled_and_clears_pause() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    client.set_max_pause_seconds(&admin, &WEEK);
+    client.pause_subscription(&id0, &sub.subscriber, &None);
+    env.ledger()
+        .set_timestamp(env.ledger().timestamp() + WEEK + 1);
+
+    assert_eq!(client.sweep_stale_pauses(&admin, &0, &10), 1);
+    let cancelled_topic: soroban_sdk::Vec<soroban_sdk::Val> =
+        (soroban_sdk::symbol_short!("cancelled"), id0).into_val(&env);
+    let (_, _, data) = env
+        .events()
+        .all()
+        .iter()
+        .find(|(_, topics, _)| *topics == cancelled_topic)
+        .expect("cancelled event");
+    let event = SubscriptionCancelledEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(event.authorizer, admin);
+    assert_eq!(event.reason, CancellationReason::Delinquent);
+    env.as_contract(&client.address, || {
+        assert!(!env
+            .storage()
+            .instance()
+            .has(&crate::types::DataKey::PausedAt(id0)));
+    });
+}
+
+#[test]
+fn test_sweep_stale_pauses_keeps_pause_within_max() {
+    let env = Env::default();
+    let (client, admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    client.pause_subscription(&id0, &sub.subscriber, &None);
+    let paused_at = env.ledger().timestamp();
+
+    // Disabled by default.
+    env.ledger().set_timestamp(paused_at + 10 * WEEK);
+    assert_eq!(client.sweep_stale_pauses(&admin, &0, &10), 0);
+
+    client.set_max_pause_seconds(&admin, &(20 * WEEK));
+    assert_eq!(client.sweep_stale_pauses(&admin, &0, &10), 0);
+    let after = client.get_subscription(&id0);
+    assert_eq!(after.status, SubscriptionStatus::Paused);
+    assert_eq!(after.prepaid_balance, sub.prepaid_balance);
+
+    assert_eq!(
+        client.try_sweep_stale_pauses(&Address::generate(&env), &0, &10),
+        Err(Ok(Error::Forbidden))
+    );
+}
+
+#[test]
+fn test_subscription_revenue_accumulates_per_subscription() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let sub = client.get_subscription(&id);
+    let other = client.create_subscription(
+        &sub.subscriber,
+        &sub.merchant,
+        &10_000_000i128,
+        &INTERVAL,
+        &false,
+        &None,
+    );
+    assert_eq!(client.get_subscription_revenue(&id), 0);
+
+    for cycle in 1..=3u64 {
+        env.ledger().set_timestamp(T0 + cycle * INTERVAL);
+        client.charge_subscription(&id);
+    }
+    assert_eq!(client.get_subscription_revenue(&id), 30_000_000);
+    assert_eq!(client.get_subscription_revenue(&other), 0);
+
+    // A refund reduces the refundable accrual but not the lifetime total.
+    client.merchant_refund(&id, &sub.merchant, &5_000_000);
+    assert_eq!(client.get_subscription_revenue(&id), 30_000_000);
+}
+
+#[test]
+fn test_batch_charge_unaffected_by_trapping_token() {
+    // Charges only credit the merchant's internal balance; tokens move on withdrawal.
+    // A token whose transfers trap (e.g. a frozen merchant account) therefore cannot
+    // abort a batch, and the failure surfaces on the merchant's own withdrawal instead.
+    let env = Env::default();
+    env.mock_all_auths();
+    let token = env.register(TrappingToken, ());
+    let contract_id = env.register(SubscriptionVault, ());
+    let client = SubscriptionVaultClient::new(&env, &contract_id);
+    client.init(&token, &7, &Address::generate(&env), &1_000000i128, &43200);
+
+    env.ledger().set_timestamp(T0);
+    let (id0, _, merchant) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    let (id1, _, _) = create_test_subscription(&env, &client, SubscriptionStatus::Active);
+    set_prepaid(&env, &client, id0, PREPAID);
+    set_prepaid(&env, &client, id1, PREPAID);
+    let interval = client.get_subscription(&id0).interval_seconds;
+    env.ledger().set_timestamp(T0 + interval);
+
+    let results = client.batch_charge(&soroban_sdk::vec![&env, id0, id1]);
+    assert!(results.iter().all(|r| r.success));
+    let amount = client.get_subscription(&id0).amount;
+    assert_eq!(
+        client.get_subscription(&id0).prepaid_balance,
+        PREPAID - amount
+    );
+    assert_eq!(merchant_balance(&env, &client, &merchant), amount);
+
+    assert_eq!(
+        client.try_withdraw_merchant_funds(&merchant, &amount),
+        Err(Ok(Error::PayoutFailed))
+    );
+    assert_eq!(merchant_balance(&env, &client, &merchant), amount);
+    assert_eq!(client.get_last_withdrawal(&merchant), 0);
+}
+
+#[test]
+fn test_min_charge_amount_enforced_at_creation() {
+    let (env, client, _, admin) = setup_test_env();
+    assert_eq!(client.get_min_charge_amount(), 0);
+    client.set_min_charge_amount(&admin, &1_000_000i128);
+    assert_eq!(client.get_min_charge_amount(), 1_000_000);
+
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    assert_eq!(
+        client.try_create_subscription(
+            &subscriber,
+            &merchant,
+            &999_999i128,
+            &INTERVAL,
+            &false,
+            &None
+        ),
+        Err(Ok(Error::InvalidAmount))
+    );
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &1_000_000i128,
+        &INTERVAL,
+        &false,
+        &None,
+    );
+    assert_eq!(client.get_subscription(&id).amount, 1_000_000);
+
+    // Purely metered subscriptions carry no flat amount and are exempt.
+    client.create_subscription(&subscriber, &merchant, &0i128, &INTERVAL, &true, &None);
+    assert_eq!(
+        client.try_set_min_charge_amount(&admin, &-1),
+        Err(Ok(Error::InvalidAmount))
+    );
+}
+
+#[test]
+fn test_create_and_fund_creates_funded_subscription() {
+    let (env, client, token, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    mint_for_subscriber(&env, &token, &subscriber, 50_000_000);
+
+    let id = client.create_and_fund(
+        &subscriber,
+        &merchant,
+        &10_000_000,
+        &INTERVAL,
+        &false,
+        &20_000_000,
+    );
+
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.status, SubscriptionStatus::Active);
+    assert_eq!(sub.prepaid_balance, 20_000_000);
+    assert_eq!(sub.deposit_count, 1);
+    let token_client = soroban_sdk::token::Client::new(&env, &token);
+    assert_eq!(token_client.balance(&subscriber), 30_000_000);
+}
+
+#[test]
+fn test_create_and_fund_reverts_creation_when_deposit_below_min_topup() {
+    let (env, client, token, _) = setup_test_env();
+    let subscriber = Address::generate(&env);
+    let merchant = Address::generate(&env);
+    mint_for_subscriber(&env, &token, &subscriber, 50_000_000);
+
+    let result = client.try_create_and_fund(
+        &subscriber,
+        &merchant,
+        &10_000_000,
+        &INTERVAL,
+        &false,
+        &999_999,
+    );
+    assert_eq!(result, Err(Ok(Error::BelowMinimumTopup)));
+    assert!(matches!(
+        client.try_get_subscription(&0),
+        Err(Ok(Error::NotFound))
+    ));
+    assert_eq!(client.get_active_count(), 0);
+
+    // The id was not consumed: the next creation still gets id 0.
+    let id = client.create_subscription(
+        &subscriber,
+        &merchant,
+        &10_000_000,
+        &INTERVAL,
+        &false,
+        &None,
+    );
+    assert_eq!(id, 0);
+}
+
+#[test]
+fn test_preview_charge_matches_actual_charge() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    env.ledger().set_timestamp(T0 + INTERVAL - 1);
+    assert_eq!(
+        client.try_preview_charge(&id),
+        Err(Ok(Error::IntervalNotElapsed))
+    );
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::IntervalNotElapsed))
+    );
+
+    env.ledger().set_timestamp(T0 + INTERVAL + 5);
+    let (amount, timestamp) = client.preview_charge(&id);
+    assert_eq!(amount, 10_000_000);
+    assert_eq!(timestamp, T0 + INTERVAL + 5);
+    // Previewing changes nothing.
+    assert_eq!(client.get_subscription(&id).prepaid_balance, PREPAID);
+
+    client.charge_subscription(&id);
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.prepaid_balance, PREPAID - amount);
+    assert_eq!(sub.last_payment_timestamp, timestamp);
+}
+
+#[test]
+fn test_preview_charge_reports_partial_amount() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    set_prepaid(&env, &client, id, 4_000_000);
+    let merchant = client.get_subscription(&id).merchant;
+    client.set_allow_partial_charge(&id, &merchant, &true);
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    let (amount, _) = client.preview_charge(&id);
+    assert_eq!(amount, 4_000_000);
+
+    client.charge_subscription(&id);
+    assert_eq!(merchant_balance(&env, &client, &merchant), amount);
+}
+
+#[test]
+fn test_preview_charge_errors_without_mutating() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    set_prepaid(&env, &client, id, 1);
+
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    assert_eq!(
+        client.try_preview_charge(&id),
+        Err(Ok(Error::InsufficientBalance))
+    );
+    assert_eq!(
+        client.get_subscription(&id).status,
+        SubscriptionStatus::Active
+    );
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::InsufficientBalance))
+    );
+
+    let (client2, id2) = setup(&env, INTERVAL);
+    let subscriber = client2.get_subscription(&id2).subscriber;
+    client2.pause_subscription(&id2, &subscriber, &None);
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL);
+    assert_eq!(client2.try_preview_charge(&id2), Err(Ok(Error::NotActive)));
+}
+
+/// Charges `id` once, drains it, then runs a failing batch charge one interval later.
+fn fail_second_charge(env: &Env, client: &SubscriptionVaultClient, id: u32) -> SubscriptionStatus {
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+    set_prepaid(env, client, id, 0);
+
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL);
+    let results = client.batch_charge(&SorobanVec::from_array(env, [id]));
+    assert_eq!(
+        results.get(0).unwrap().error_code,
+        Error::InsufficientBalance.to_code()
+    );
+    client.get_subscription(&id).status
+}
+
+#[test]
+fn test_grace_override_replaces_global_grace() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+    client.set_grace_period(&admin, &0);
+    client.set_grace_override(&admin, &id, &Some(DAY));
+    assert_eq!(client.get_grace_override(&id), Some(DAY));
+
+    assert_eq!(
+        fail_second_charge(&env, &client, id),
+        SubscriptionStatus::GracePeriod
+    );
+
+    // The override also sets the deadline used by the sweep.
+    env.ledger().set_timestamp(T0 + 2 * INTERVAL + DAY);
+    assert_eq!(client.sweep_expired_grace(&admin, &0, &10), 1);
+    assert_eq!(
+        client.get_subscription(&id).status,
+        SubscriptionStatus::InsufficientBalance
+    );
+}
+
+#[test]
+fn test_next_charge_info_reports_grace_override() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+    client.set_grace_period(&admin, &WEEK);
+    // The first charge also gets the signup grace; leave it out of the comparison.
+    client.set_first_charge_grace(&admin, &0);
+
+    let info = client.get_next_charge_info(&id);
+    assert_eq!(info.next_charge_timestamp, T0 + INTERVAL);
+    assert_eq!(info.grace_deadline, Some(T0 + INTERVAL + WEEK));
+
+    client.set_grace_override(&admin, &id, &Some(DAY));
+    assert_eq!(
+        client.get_next_charge_info(&id).grace_deadline,
+        Some(T0 + INTERVAL + DAY)
+    );
+
+    client.set_grace_override(&admin, &id, &None);
+    assert_eq!(
+        client.get_next_charge_info(&id).grace_deadline,
+        Some(T0 + INTERVAL + WEEK)
+    );
+
+    let subscriber = client.get_subscription(&id).subscriber;
+    client.pause_subscription(&id, &subscriber, &None);
+    assert_eq!(client.get_next_charge_info(&id).grace_deadline, None);
+}
+
+#[test]
+fn test_zero_grace_override_suspends_despite_global_grace() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+    client.set_grace_period(&admin, &DAY);
+    client.set_grace_override(&admin, &id, &Some(0));
+
+    assert_eq!(
+        fail_second_charge(&env, &client, id),
+        SubscriptionStatus::InsufficientBalance
+    );
+}
+
+#[test]
+fn test_cleared_grace_override_falls_back_to_global() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+    client.set_grace_period(&admin, &0);
+    client.set_grace_override(&admin, &id, &Some(DAY));
+    client.set_grace_override(&admin, &id, &None);
+    assert_eq!(client.get_grace_override(&id), None);
+
+    assert_eq!(
+        fail_second_charge(&env, &client, id),
+        SubscriptionStatus::InsufficientBalance
+    );
+}
+
+#[test]
+fn test_set_grace_override_requires_admin_and_subscription() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let admin = client.get_admin();
+    let stranger = Address::generate(&env);
+
+    assert_eq!(
+        client.try_set_grace_override(&stranger, &id, &Some(DAY)),
+        Err(Ok(Error::Forbidden))
+    );
+    assert_eq!(
+        client.try_set_grace_override(&admin, &99, &Some(DAY)),
+        Err(Ok(Error::NotFound))
+    );
+}
+
+#[test]
+fn test_catch_up_charge_replayed_within_period_is_rejected() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+
+    // Three intervals behind: one catch-up charge goes through.
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL + 10);
+    client.charge_subscription(&id);
+    let charged = client.get_subscription(&id);
+    assert_eq!(charged.last_payment_timestamp, T0 + 3 * INTERVAL + 10);
+
+    // A repeat in the same period is a replay, even with the balance drained.
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL + 11);
+    assert_eq!(client.try_charge_subscription(&id), Err(Ok(Error::Replay)));
+    set_prepaid(&env, &client, id, 0);
+    assert_eq!(client.try_charge_subscription(&id), Err(Ok(Error::Replay)));
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.status, SubscriptionStatus::Active);
+    assert_eq!(sub.charge_count, charged.charge_count);
+    assert_eq!(sub.last_payment_timestamp, charged.last_payment_timestamp);
+}
+
+#[test]
+fn test_payees_split_interval_charge() {
+    let env = Env::default();
+    let (client, _admin, id0, _id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    let platform = Address::generate(&env);
+    let payees = soroban_sdk::vec![&env, (platform.clone(), 2_500u32)];
+    client.set_payees(&id0, &sub.merchant, &payees);
+    assert_eq!(client.get_payees(&id0), payees);
+
+    client.charge_subscription(&id0);
+    assert_eq!(merchant_balance(&env, &client, &platform), 250);
+    assert_eq!(merchant_balance(&env, &client, &sub.merchant), 750);
+    assert_eq!(client.get_subscription_revenue(&id0), sub.amount);
+
+    // Payee balances are liabilities, and payees withdraw like merchants.
+    let sheet = client.get_balance_sheet();
+    assert_eq!(sheet.merchant_liabilities, sub.amount);
+    assert_eq!(
+        sheet.token_balance,
+        sheet.prepaid_liabilities + sheet.merchant_liabilities
+    );
+    client.withdraw_merchant_funds(&platform, &250);
+    let token = soroban_sdk::token::Client::new(&env, &client.get_token());
+    assert_eq!(token.balance(&platform), 250);
+}
+
+#[test]
+fn test_set_payees_rejects_invalid_tables() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let (client, id) = setup(&env, INTERVAL);
+    let merchant = client.get_subscription(&id).merchant;
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+
+    let over = soroban_sdk::vec![&env, (a.clone(), 6_000u32), (b.clone(), 5_000u32)];
+    assert_eq!(
+        client.try_set_payees(&id, &merchant, &over),
+        Err(Ok(Error::InvalidInput))
+    );
+    let zero = soroban_sdk::vec![&env, (a.clone(), 0u32)];
+    assert_eq!(
+        client.try_set_payees(&id, &merchant, &zero),
+        Err(Ok(Error::InvalidInput))
+    );
+    let valid = soroban_sdk::vec![&env, (a, 6_000u32), (b, 4_000u32)];
+    assert_eq!(
+        client.try_set_payees(&id, &Address::generate(&env), &valid),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    // A full 100% split is allowed and leaves nothing for the merchant.
+    client.set_payees(&id, &merchant, &valid);
+    env.ledger().set_timestamp(T0 + INTERVAL);
+    client.charge_subscription(&id);
+    assert_eq!(merchant_balance(&env, &client, &merchant), 0);
+
+    client.set_payees(&id, &merchant, &SorobanVec::new(&env));
+    assert!(client.get_payees(&id).is_empty());
+}
+
+#[test]
+fn test_get_merchant_balance_tracks_charges_and_withdrawals() {
+    let env = Env::default();
+    let (client, _admin, id0, id1) = setup_batch_env(&env);
+    let sub = client.get_subscription(&id0);
+    assert_eq!(client.get_merchant_balance(&sub.merchant), 0);
+    assert_eq!(client.get_merchant_balance(&Address::generate(&env)), 0);
+
+    client.deposit_funds(&id1, &sub.subscriber, &2_000000i128, &None);
+    client.batch_charge(&soroban_sdk::vec![&env, id0, id1]);
+    assert_eq!(client.get_merchant_balance(&sub.merchant), 2000);
+
+    client.withdraw_merchant_funds(&sub.merchant, &1500);
+    assert_eq!(client.get_merchant_balance(&sub.merchant), 500);
+    // Lifetime revenue is unaffected by the withdrawal.
+    assert_eq!(
+        client.get_subscription_revenue(&id0) + client.get_subscription_revenue(&id1),
+        2000
+    );
+
+    client.withdraw_merchant_funds(&sub.merchant, &500);
+    assert_eq!(client.get_merchant_balance(&sub.merchant), 0);
+}
+
+#[test]
+fn test_cancellation_notice_defers_cancel_and_keeps_charging() {
+    let (env, client, _, _) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+    let merchant = Address::generate(&env);
+    let subscriber = Address::generate(&env);
+    let plan_id = client.create_plan_template(&merchant, &10_000_000i128, &INTERVAL, &false);
+    client.set_plan_cancellation_notice(&merchant, &plan_id, &(2 * INTERVAL));
+    assert_eq!(client.get_plan_cancellation_notice(&plan_id), 2 * INTERVAL);
+    let id = client.create_subscription_from_plan(&subscriber, &plan_id);
+    set_prepaid(&env, &client, id, PREPAID);
+
+    // The request only schedules the cancellation; asking again does not move it.
+    env.ledger().set_timestamp(T0 + 10);
+    client.cancel_subscription(&id, &subscriber);
+    let effective_at = T0 + 10 + 2 * INTERVAL;
+    assert_eq!(client.get_scheduled_cancellation(&id), Some(effective_at));
+    env.ledger().set_timestamp(T0 + 20);
+    client.cancel_subscription(&id, &subscriber);
+    assert_eq!(client.get_scheduled_cancellation(&id), Some(effective_at));
+    assert_eq!(
+        client.get_subscription(&id).status,
+        SubscriptionStatus::Active
+    );
+
+    // Charges due within the notice period still happen.
+    for cycle in 1..=2u64 {
+        env.ledger().set_timestamp(T0 + cycle * INTERVAL);
+        assert!(client.get_next_charge_info(&id).is_charge_expected);
+        client.charge_subscription(&id);
+    }
+    assert_eq!(client.get_subscription(&id).charge_count, 2);
+    // The next cycle falls after the scheduled end.
+    let info = client.get_next_charge_info(&id);
+    assert_eq!(info.next_charge_timestamp, T0 + 3 * INTERVAL);
+    assert!(!info.is_charge_expected);
+
+    env.ledger().set_timestamp(T0 + 3 * INTERVAL);
+    assert_eq!(
+        client.try_charge_subscription(&id),
+        Err(Ok(Error::NotActive))
+    );
+
+    // Once the notice has run out, cancelling completes it.
+    client.cancel_subscription(&id, &subscriber);
+    assert_eq!(
+        client.get_subscription(&id).status,
+        SubscriptionStatus::Cancelled
+    );
+    assert_eq!(client.get_scheduled_cancellation(&id), None);
+}
+
+#[test]
+fn test_sweep_finalizes_scheduled_cancellation_with_fee() {
+    let (env, client, _, admin) = setup_test_env();
+    env.ledger().set_timestamp(T0);
+    let merchant = Address::generate(&env);
+    let subscriber = Address::generate(&env);
+    let plan_id = client.create_plan_template(&merchant, &10_000_000i128, &INTERVAL, &false);
+    client.set_plan_cancellation_notice(&merchant, &plan_id, &INTERVAL);
+    let id = client.create_subscription_from_plan(&subscriber, &plan_id);
+    set_prepaid(&env, &client, id, PREPAID);
+    client.set_cancellation_fee_bps(&id, &merchant, &1_000);
+
+    client.cancel_subscription(&id, &subscriber);
+    let effective_at = T0 + INTERVAL;
+
+    // Before the notice runs out the sweep leaves it alone.
+    env.ledger().set_timestamp(effective_at - 1);
+    assert_eq!(client.sweep_scheduled_cancellations(&admin, &0, &10), 0);
+    assert_eq!(
+        client.get_subscription(&id).status,
+        SubscriptionStatus::Active
+    );
+
+    // No second cancel call: the sweep completes it for the original requester.
+    env.ledger().set_timestamp(effective_at);
+    assert_eq!(client.sweep_scheduled_cancellations(&admin, &0, &10), 1);
+    let fee = PREPAID / 10;
+    let (_, topics, data) = env.events().all().last().unwrap();
+    let expected_topics: soroban_sdk::Vec<soroban_sdk::Val> =
+        (soroban_sdk::symbol_short!("cancelled"), id).into_val(&env);
+    assert_eq!(topics, expected_topics);
+    let event = SubscriptionCancelledEvent::try_from_val(&env, &data).unwrap();
+    assert_eq!(event.authorizer, subscriber);
+    assert_eq!(event.refund_amount, PREPAID - fee);
+    assert_eq!(event.reason, CancellationReason::UserRequested);
+
+    let sub = client.get_subscription(&id);
+    assert_eq!(sub.status, SubscriptionStatus::Cancelled);
+    assert_eq!(sub.prepaid_balance, PREPAID - fee);
+    assert_eq!(merchant_balance(&env, &client, &merchant), fee);
+    assert_eq!(client.get_scheduled_cancellation(&id), None);
+    assert_eq!(client.sweep_scheduled_cancellations(&admin, &0, &10), 0);
+
+    assert_eq!(
+        client.try_sweep_scheduled_cancellations(&Address::generate(&env), &0, &10),
+        Err(Ok(Error::Forbidden))
+    );
+}
+
+#[test]
+fn test_plan_cancellation_notice_requires_plan_merchant() {
+    let (env, client, _, _) = setup_test_env();
+    let merchant = Address::generate(&env);
+    let subscriber = Address::generate(&env);
+    let plan_id = client.create_plan_template(&merchant, &10_000_000i128, &INTERVAL, &false);
+
+    assert_eq!(
+        client.try_set_plan_cancellation_notice(&Address::generate(&env), &plan_id, &INTERVAL),
+        Err(Ok(Error::Unauthorized))
+    );
+
+    // Without a notice period, cancellation is immediate.
+    let id = client.create_subscription_from_plan(&subscriber, &plan_id);
+    client.cancel_subscription(&id, &subscriber);
+    assert_eq!(
+        client.get_subscription(&id).status,
+        SubscriptionStatus::Cancelled
+    );
+    assert_eq!(client.get_scheduled_cancellation(&id), None);
+}
@@ -3,7 +3,7 @@
 //! Kept in a separate module to reduce merge conflicts when editing state machine
 //! or contract entrypoints.
 
-use soroban_sdk::{contracterror, contracttype, Address};
+use soroban_sdk::{contracterror, contracttype, Address, Bytes, BytesN, Symbol};
 
 /// Storage keys for secondary indices.
 #[contracttype]
@@ -11,6 +11,92 @@ use soroban_sdk::{contracterror, contracttype, Address};
 pub enum DataKey {
     /// Maps a merchant address to its list of subscription IDs.
     MerchantSubs(Address),
+    /// Withdrawable earnings accrued to a merchant across all subscriptions.
+    MerchantBalance(Address),
+    /// Earnings accrued to the merchant from one subscription, net of refunds.
+    SubscriptionAccrued(u32),
+    /// Maps a caller-supplied creation reference to the subscription ID it created.
+    RefToId(BytesN<32>),
+    /// Plan template by plan ID.
+    Plan(u32),
+    /// Maps a merchant address to the IDs of plan templates it created.
+    MerchantPlans(Address),
+    /// Timestamp at which a currently paused subscription was paused.
+    PausedAt(u32),
+    /// Seconds a subscription has spent paused since its last interval charge.
+    PausedSeconds(u32),
+    /// Present when the merchant has enabled partial charging for a subscription.
+    AllowPartialCharge(u32),
+    /// Address that receives a subscription's prepaid refunds instead of the subscriber.
+    RefundAddress(u32),
+    /// Deferred start time of a subscription created with `create_subscription_starting`.
+    StartTimestamp(u32),
+    /// Outstanding amount owed by a subscription after partial charges.
+    Arrears(u32),
+    /// Address that receives a merchant's withdrawals, if different from the merchant.
+    PayoutAddress(Address),
+    /// New per-interval amount proposed by the merchant, awaiting subscriber acceptance.
+    PendingAmount(u32),
+    /// Timestamp of a merchant's most recent withdrawal.
+    LastWithdrawal(Address),
+    /// Address whose auth is required to run interval charges (defaults to the admin).
+    BillingEngine,
+    /// Metered usage recorded by the merchant but not yet debited from the balance.
+    PendingUsage(u32),
+    /// For a subscription created by `admin_reactivate`, the cancelled ID it replaces.
+    ReactivatedFrom(u32),
+    /// Merchant-set billing anchor: charges fall on `anchor + k * interval_seconds`.
+    BillingAnchor(u32),
+    /// Subscriber address → list of their non-cancelled subscription IDs.
+    SubscriberSubs(Address),
+    /// Admin-set cap on a subscriber's non-cancelled subscriptions (0 / unset = unlimited).
+    MaxSubsPerSubscriber,
+    /// Merchant-set share (basis points) of the remaining balance kept on subscriber cancellation.
+    CancellationFeeBps(u32),
+    /// Reason given for the current pause, cleared on resume.
+    PauseReason(u32),
+    /// Plan whose terms replace the subscription's at its next charge (see `switch_plan`).
+    PendingPlan(u32),
+    /// Number of subscriptions not yet `Cancelled`.
+    TotalActive,
+    /// Token units charged per usage unit by `charge_usage` (1 if unset).
+    UsageRate(u32),
+    /// Usage rate copied onto subscriptions created from a plan (1 if unset).
+    PlanUsageRate(u32),
+    /// Admin replaced by the most recent `rotate_admin`.
+    PreviousAdmin,
+    /// Address that may not receive subscription payments (e.g. a known burn address).
+    BlockedAddress(Address),
+    /// Subscription billed in arrears for recorded usage (see `set_postpaid`).
+    Postpaid(u32),
+    /// Present when interval charges are switched off for a subscription.
+    IntervalChargesDisabled(u32),
+    /// Present when usage charges are switched off for a subscription.
+    UsageChargesDisabled(u32),
+    /// Total a merchant has been credited, net of refunds; withdrawals do not reduce it.
+    MerchantRevenue(Address),
+    /// Day of the month a `MonthlyCalendar` subscription bills on (see `set_billing_mode`).
+    CalendarDay(u32),
+    /// Automatic top-up settings for a subscription (see `set_auto_topup`).
+    AutoTopup(u32),
+    /// Present while a subscription awaits `confirm_subscription` by its merchant.
+    PendingMerchant(u32),
+    /// A merchant's current maintenance window (see `set_maintenance_until`).
+    MaintenanceWindow(Address),
+    /// Gross amount ever credited to the merchant from a subscription; refunds do not reduce it.
+    SubscriptionRevenue(u32),
+    /// Admin-set minimum `amount` for new subscriptions (0 / unset = no minimum).
+    MinChargeAmount,
+    /// Admin-set grace window for one subscription, replacing the global grace period.
+    GraceOverride(u32),
+    /// Revenue split table `Vec<(payee, bps)>` for a subscription's interval charges.
+    Payees(u32),
+    /// Notice period (seconds) copied onto subscriptions created from a plan.
+    PlanCancellationNotice(u32),
+    /// A subscription's cancellation notice period in seconds, from its plan.
+    CancellationNotice(u32),
+    /// Time at which a cancellation requested under a notice period takes effect.
+    ScheduledCancellation(u32),
 }
 
 /// Detailed error information for insufficient balance scenarios.
@@ -81,6 +167,8 @@ pub enum Error {
     InsufficientBalance = 1001,
     /// Usage-based charge exceeds the current available prepaid balance.
     InsufficientPrepaidBalance = 1002,
+    /// Refund exceeds what the merchant has accrued from the subscription.
+    ExceedsAccruedBalance = 1003,
 
     // --- Timing & Lifecycle Errors (11xx) ---
     /// Charge attempted before the 'interval_seconds' has elapsed since the last payment.
@@ -89,6 +177,18 @@ pub enum Error {
     Replay = 1102,
     /// Subscription is not in the 'Active' state (e.g. it is Paused or Cancelled).
     NotActive = 1103,
+    /// Plan template has been deactivated by its merchant and accepts no new subscriptions.
+    PlanInactive = 1104,
+    /// Charge attempted before the subscription's `start_timestamp`.
+    NotStarted = 1105,
+    /// Merchant withdrawal attempted before the configured cooldown has elapsed.
+    WithdrawalTooSoon = 1106,
+    /// Subscriber already holds the admin-configured maximum of non-cancelled subscriptions.
+    SubscriptionLimitReached = 1107,
+    /// Charge attempted while the merchant's maintenance window is open.
+    InMaintenance = 1108,
+    /// The token transfer paying out a merchant withdrawal failed (e.g. a frozen account).
+    PayoutFailed = 1109,
 
     // --- Algebra & Overflow (12xx) ---
     /// Arithmetic overflow in computation (e.g. total amount calculation).
@@ -101,6 +201,12 @@ pub enum Error {
     AlreadyInitialized = 1301,
     /// Contract has not been initialized. Most operations require 'init' to be called first.
     NotInitialized = 1302,
+    /// Stored data predates the current `STORAGE_VERSION`; the admin must call 'migrate' first.
+    MigrationRequired = 1303,
+    /// A stored record violates an internal invariant (see 'verify_invariants').
+    InvariantViolation = 1304,
+    /// The admin has paused the contract; charges, deposits and new subscriptions are rejected.
+    ContractPaused = 1305,
 }
 
 impl Error {
@@ -196,6 +302,27 @@ pub enum SubscriptionStatus {
     GracePeriod = 4,
 }
 
+/// Reusable subscription terms published by a merchant.
+///
+/// Subscribers create subscriptions from a template with
+/// [`crate::SubscriptionVault::create_subscription_from_plan`], which copies the
+/// template's terms onto the new subscription.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlanTemplate {
+    /// Merchant that owns the plan and receives payments.
+    pub merchant: Address,
+    /// Amount charged per interval.
+    pub amount: i128,
+    /// Billing interval in seconds.
+    pub interval_seconds: u64,
+    /// Whether subscriptions created from this plan allow usage charges.
+    pub usage_enabled: bool,
+    /// Whether new subscriptions may be created from this plan. Deactivating a plan
+    /// does not affect subscriptions already created from it.
+    pub active: bool,
+}
+
 /// Stores subscription details and current state.
 ///
 /// The `status` field is managed by the state machine. Use the provided
@@ -226,6 +353,163 @@ pub struct Subscription {
     pub status: SubscriptionStatus,
     pub prepaid_balance: i128,
     pub usage_enabled: bool,
+    /// Free-form merchant grouping for reporting (e.g. `pro`, `legacy`). Added in
+    /// storage version 2.
+    pub category: Option<Symbol>,
+    /// Lifetime number of successful interval charges (full or partial). Added in
+    /// storage version 3.
+    pub charge_count: u32,
+    /// Opaque integrator data (e.g. an external user id), at most
+    /// `MAX_METADATA_LEN` bytes. Added in storage version 4.
+    pub metadata: Option<Bytes>,
+    /// Number of successful `deposit_funds` calls. Added in storage version 5.
+    pub deposit_count: u32,
+}
+
+/// Storage version 1 layout of [`Subscription`] (before `category`), decoded by
+/// `migrate` to rewrite existing records.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionV1 {
+    pub subscriber: Address,
+    pub merchant: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub last_payment_timestamp: u64,
+    pub status: SubscriptionStatus,
+    pub prepaid_balance: i128,
+    pub usage_enabled: bool,
+}
+
+impl SubscriptionV1 {
+    /// Upgrades a version 1 record, leaving new fields at their defaults.
+    pub fn upgrade(self) -> Subscription {
+        Subscription {
+            subscriber: self.subscriber,
+            merchant: self.merchant,
+            amount: self.amount,
+            interval_seconds: self.interval_seconds,
+            last_payment_timestamp: self.last_payment_timestamp,
+            status: self.status,
+            prepaid_balance: self.prepaid_balance,
+            usage_enabled: self.usage_enabled,
+            category: None,
+            charge_count: 0,
+            metadata: None,
+            deposit_count: 0,
+        }
+    }
+}
+
+/// Storage version 2 layout of [`Subscription`] (before `charge_count`), decoded by
+/// `migrate` to rewrite existing records.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionV2 {
+    pub subscriber: Address,
+    pub merchant: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub last_payment_timestamp: u64,
+    pub status: SubscriptionStatus,
+    pub prepaid_balance: i128,
+    pub usage_enabled: bool,
+    pub category: Option<Symbol>,
+}
+
+impl SubscriptionV2 {
+    /// Upgrades a version 2 record, starting `charge_count` at 0.
+    pub fn upgrade(self) -> Subscription {
+        Subscription {
+            subscriber: self.subscriber,
+            merchant: self.merchant,
+            amount: self.amount,
+            interval_seconds: self.interval_seconds,
+            last_payment_timestamp: self.last_payment_timestamp,
+            status: self.status,
+            prepaid_balance: self.prepaid_balance,
+            usage_enabled: self.usage_enabled,
+            category: self.category,
+            charge_count: 0,
+            metadata: None,
+            deposit_count: 0,
+        }
+    }
+}
+
+/// Storage version 3 layout of [`Subscription`] (before `metadata`), decoded by
+/// `migrate` to rewrite existing records.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionV3 {
+    pub subscriber: Address,
+    pub merchant: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub last_payment_timestamp: u64,
+    pub status: SubscriptionStatus,
+    pub prepaid_balance: i128,
+    pub usage_enabled: bool,
+    pub category: Option<Symbol>,
+    pub charge_count: u32,
+}
+
+impl SubscriptionV3 {
+    /// Upgrades a version 3 record with no metadata.
+    pub fn upgrade(self) -> Subscription {
+        Subscription {
+            subscriber: self.subscriber,
+            merchant: self.merchant,
+            amount: self.amount,
+            interval_seconds: self.interval_seconds,
+            last_payment_timestamp: self.last_payment_timestamp,
+            status: self.status,
+            prepaid_balance: self.prepaid_balance,
+            usage_enabled: self.usage_enabled,
+            category: self.category,
+            charge_count: self.charge_count,
+            metadata: None,
+            deposit_count: 0,
+        }
+    }
+}
+
+/// Storage version 4 layout of [`Subscription`] (before `deposit_count`), decoded by
+/// `migrate` to rewrite existing records.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionV4 {
+    pub subscriber: Address,
+    pub merchant: Address,
+    pub amount: i128,
+    pub interval_seconds: u64,
+    pub last_payment_timestamp: u64,
+    pub status: SubscriptionStatus,
+    pub prepaid_balance: i128,
+    pub usage_enabled: bool,
+    pub category: Option<Symbol>,
+    pub charge_count: u32,
+    pub metadata: Option<Bytes>,
+}
+
+impl SubscriptionV4 {
+    /// Upgrades a version 4 record, starting `deposit_count` at 0.
+    pub fn upgrade(self) -> Subscription {
+        Subscription {
+            subscriber: self.subscriber,
+            merchant: self.merchant,
+            amount: self.amount,
+            interval_seconds: self.interval_seconds,
+            last_payment_timestamp: self.last_payment_timestamp,
+            status: self.status,
+            prepaid_balance: self.prepaid_balance,
+            usage_enabled: self.usage_enabled,
+            category: self.category,
+            charge_count: self.charge_count,
+            metadata: self.metadata,
+            deposit_count: 0,
+        }
+    }
 }
 
 // Event types
@@ -245,6 +529,21 @@ pub struct FundsDepositedEvent {
     pub subscription_id: u32,
     pub subscriber: Address,
     pub amount: i128,
+    pub new_balance: i128,
+    /// External reference (e.g. invoice id) supplied by the depositor; all zeros when omitted.
+    pub memo: BytesN<32>,
+}
+
+/// Emitted by [`crate::SubscriptionVault::gift_deposit`] when a sponsor funds someone
+/// else's subscription. The subscription's subscriber is unchanged.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct GiftDepositEvent {
+    pub subscription_id: u32,
+    pub sponsor: Address,
+    pub subscriber: Address,
+    pub amount: i128,
+    pub new_balance: i128,
 }
 
 #[contracttype]
@@ -255,12 +554,38 @@ pub struct SubscriptionChargedEvent {
     pub amount: i128,
 }
 
+/// Emitted once at the end of `batch_charge` with the outcome counts of the batch.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BatchChargeSummaryEvent {
+    pub total: u32,
+    pub succeeded: u32,
+    pub failed: u32,
+    pub timestamp: u64,
+}
+
+/// Why a subscription was cancelled, carried in [`SubscriptionCancelledEvent`].
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(u32)]
+pub enum CancellationReason {
+    /// The subscriber or merchant chose to end the subscription (the default).
+    UserRequested = 0,
+    /// Ended because the subscriber stopped paying.
+    Delinquent = 1,
+    /// The merchant stopped offering the service.
+    MerchantClosed = 2,
+    /// Ended because of suspected fraud.
+    Fraud = 3,
+}
+
 #[contracttype]
 #[derive(Clone, Debug)]
 pub struct SubscriptionCancelledEvent {
     pub subscription_id: u32,
     pub authorizer: Address,
     pub refund_amount: i128,
+    pub reason: CancellationReason,
 }
 
 #[contracttype]
@@ -268,6 +593,8 @@ pub struct SubscriptionCancelledEvent {
 pub struct SubscriptionPausedEvent {
     pub subscription_id: u32,
     pub authorizer: Address,
+    /// Why the subscription was paused (e.g. `vacation`, `dunning`), if given.
+    pub reason: Option<Symbol>,
 }
 
 #[contracttype]
@@ -293,6 +620,26 @@ pub struct OneOffChargedEvent {
     pub amount: i128,
 }
 
+/// Emitted when a merchant refunds part of a subscription's accrued earnings
+/// back into the subscriber's prepaid balance.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct MerchantRefundEvent {
+    pub subscription_id: u32,
+    pub merchant: Address,
+    pub amount: i128,
+}
+
+/// How a subscription's next charge time is derived from its last payment.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BillingMode {
+    /// Every `interval_seconds` (or on billing-anchor slots, if one is set).
+    Interval = 0,
+    /// The same day of the following calendar month, clamped to the month's last day.
+    MonthlyCalendar = 1,
+}
+
 /// Represents the reason for stranded funds that can be recovered by admin.
 #[contracttype]
 #[derive(Clone, Debug, Eq, PartialEq)]
@@ -321,6 +668,20 @@ pub struct RecoveryEvent {
     pub timestamp: u64,
 }
 
+/// Event emitted when admin force-cancels a subscription.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct AdminForceCancelEvent {
+    pub subscription_id: u32,
+    /// The admin who authorized the cancellation
+    pub admin: Address,
+    /// Prepaid balance returned to the subscriber
+    pub refunded: i128,
+    /// The documented reason for the cancellation
+    pub reason: RecoveryReason,
+    pub timestamp: u64,
+}
+
 /// Exported snapshot of contract-level configuration for migration tooling.
 #[contracttype]
 #[derive(Clone, Debug)]
@@ -333,6 +694,52 @@ pub struct ContractSnapshot {
     pub timestamp: u64,
 }
 
+/// A merchant's maintenance window: charges for its subscriptions are refused from
+/// `start` until `until`, and the time is added to their billing schedule.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MaintenanceWindow {
+    pub start: u64,
+    pub until: u64,
+}
+
+/// Contract-wide liabilities against the vault's token holdings, returned by
+/// `get_balance_sheet`. The recoverable surplus is
+/// `token_balance - prepaid_liabilities - merchant_liabilities`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BalanceSheet {
+    /// Sum of every subscription's `prepaid_balance` (owed to subscribers).
+    pub prepaid_liabilities: i128,
+    /// Sum of every merchant, payee and fee collector balance.
+    pub merchant_liabilities: i128,
+    /// Tokens actually held by the vault.
+    pub token_balance: i128,
+}
+
+/// A merchant's key figures, returned by `get_merchant_dashboard`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MerchantDashboard {
+    /// `Active` subscriptions within the scanned slice of the merchant's index.
+    pub active_subscriptions: u32,
+    /// Non-cancelled subscriptions in the merchant's index.
+    pub total_subscriptions: u32,
+    /// Accrued balance the merchant can withdraw now.
+    pub withdrawable_balance: i128,
+    /// Everything credited to the merchant so far, net of refunds.
+    pub lifetime_revenue: i128,
+}
+
+/// Auto-refill settings: after an interval charge leaves the prepaid balance below
+/// `threshold`, `amount` is pulled from the subscriber's wallet via `transfer_from`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AutoTopupConfig {
+    pub amount: i128,
+    pub threshold: i128,
+}
+
 /// Exported summary of a subscription for migration tooling.
 #[contracttype]
 #[derive(Clone, Debug)]
@@ -346,6 +753,8 @@ pub struct SubscriptionSummary {
     pub status: SubscriptionStatus,
     pub prepaid_balance: i128,
     pub usage_enabled: bool,
+    pub charge_count: u32,
+    pub deposit_count: u32,
 }
 
 /// Event emitted when a migration export is requested.
@@ -367,4 +776,9 @@ pub struct NextChargeInfo {
     pub next_charge_timestamp: u64,
     /// Whether a charge is actually expected based on the subscription status.
     pub is_charge_expected: bool,
+    /// Time from which a failing charge suspends the subscription instead of leaving it
+    /// in `GracePeriod`: the next charge time plus the grace window (the subscription's
+    /// override, else the global grace period). `None` when no charge is expected or
+    /// the subscription is not `Active` or `GracePeriod`.
+    pub grace_deadline: Option<u64>,
 }